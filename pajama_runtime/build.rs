@@ -0,0 +1,27 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Generates the runtime's C header into `OUT_DIR`, or to the path in
+/// `PAJAMA_RUNTIME_HEADER` when it's set, so that building never writes to
+/// the source tree unless asked to.
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let header = match env::var_os("PAJAMA_RUNTIME_HEADER") {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(env::var("OUT_DIR").unwrap()).join("pajama_runtime.h"),
+    };
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=PAJAMA_RUNTIME_HEADER");
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("Unable to read cbindgen.toml");
+
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate the runtime header")
+        .write_to_file(header);
+}
@@ -0,0 +1,115 @@
+#ifndef PAJAMA_RUNTIME_H
+#define PAJAMA_RUNTIME_H
+
+/* Generated by cbindgen from pajama_runtime/src/lib.rs, don't edit by hand. */
+
+#include <stdint.h>
+typedef struct TcpListener TcpListener;
+typedef struct TcpStream TcpStream;
+typedef struct Poll Poll;
+typedef struct Events Events;
+typedef struct Event Event;
+
+
+typedef struct HashMap_Token__TcpStream HashMap_Token__TcpStream;
+
+typedef struct HashMap_Token__Vec_u8 HashMap_Token__Vec_u8;
+
+typedef struct Vec_u8 Vec_u8;
+
+typedef struct InAddr {
+  int32_t s_addr;
+} InAddr;
+
+typedef struct SockaddrIn {
+  int8_t sin_len;
+  int8_t sin_family;
+  int16_t sin_port;
+  struct InAddr sin_addr;
+  int8_t sin_zero[8];
+} SockaddrIn;
+
+typedef struct PjStr {
+  const int8_t *buffer;
+  int64_t length;
+  int64_t max_length;
+} PjStr;
+
+/**
+ * An array of `Str`s, `buffer` points at `length` of them.
+ */
+typedef struct PjStrArray {
+  struct PjStr *buffer;
+  int64_t length;
+} PjStrArray;
+
+typedef struct PjTcpServer {
+  struct PjStr *host;
+  struct PjStr *port;
+  TcpListener *tcp_listener;
+  Poll *poll;
+  Events *events;
+  struct HashMap_Token__TcpStream *connections;
+  struct HashMap_Token__Vec_u8 *buffers;
+  int64_t conn_id;
+} PjTcpServer;
+
+typedef struct PjTcpConnection {
+  struct PjTcpServer *server;
+  TcpStream *tcp_stream;
+  const Event *event;
+  struct Vec_u8 *buffer;
+} PjTcpConnection;
+
+typedef struct PjTcpEvents {
+  void (*tcp_data_received_fn)(const struct PjTcpConnection*, const struct PjStr*);
+} PjTcpEvents;
+
+void print_int(int64_t int_);
+
+void print_class(struct SockaddrIn class_);
+
+void print_bytes(const uint8_t *bytes, int64_t len);
+
+/**
+ * The program's arguments, passed to a `main` that takes a `StrArray`.
+ * Arguments that aren't UTF-8 have the invalid bytes replaced.
+ */
+struct PjStrArray *pj_main_args(void);
+
+/**
+ * The `Str` at `index`, an empty `Str` when the index is out of bounds since
+ * a panic can't unwind into the program.
+ */
+struct PjStr *pj_str_array_get(const struct PjStrArray *pj_str_array, int64_t index);
+
+/**
+ * The value of the environment variable `name`, an empty `Str` when it
+ * isn't set.
+ */
+struct PjStr *env_get(const struct PjStr *name);
+
+/**
+ * Sets the environment variable `name` to `value` and returns the value it
+ * had before, an empty `Str` when it wasn't set.
+ */
+struct PjStr *env_set(const struct PjStr *name, const struct PjStr *value);
+
+void *pj_malloc_struct(const struct PjStr *pj_name);
+
+void pj_listen(struct PjTcpServer *pj_tcp_server);
+
+void pj_poll(struct PjTcpServer *pj_tcp_server);
+
+void pj_check_events(struct PjTcpServer *pj_tcp_server, const struct PjTcpEvents *pj_tcp_events);
+
+void pj_tcp_connection_buffer(struct PjTcpConnection *pj_tcp_connection,
+                              const struct PjStr *pj_str);
+
+void pj_tcp_connection_flush(TcpStream *connection,
+                             const Event *event,
+                             const struct PjTcpServer *pj_tcp_server);
+
+void pj_tcp_connection_write(struct PjTcpConnection *pj_tcp_connection, const struct PjStr *pj_str);
+
+#endif /* PAJAMA_RUNTIME_H */
@@ -5,6 +5,22 @@ pub struct PjStr {
     max_length: i64,
 }
 
+impl PjStr {
+    pub fn to_string_lossy(&self) -> String {
+        let bytes =
+            unsafe { std::slice::from_raw_parts(self.buffer as *const u8, self.length as usize) };
+
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+/// An array of `Str`s, `buffer` points at `length` of them.
+#[repr(C)]
+pub struct PjStrArray {
+    buffer: *mut PjStr,
+    length: i64,
+}
+
 #[repr(C)]
 pub struct PjTcpServer {
     host: *mut PjStr,
@@ -20,7 +36,7 @@ pub struct PjTcpServer {
 #[repr(C)]
 pub struct PjTcpEvents {
     // tcp_writable_fn: fn(&PjTcpConnection),
-    tcp_data_received_fn: fn(&PjTcpConnection, &PjStr),
+    tcp_data_received_fn: extern "C" fn(&PjTcpConnection, &PjStr),
 }
 
 #[repr(C)]
@@ -31,34 +47,153 @@ pub struct PjTcpConnection {
     buffer: *mut Vec<u8>,
 }
 
+#[derive(Debug)]
+#[repr(C)]
+pub struct InAddr {
+    s_addr: i32,
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct SockaddrIn {
+    sin_len: i8,       // length of structure (16)
+    sin_family: i8,    // AF_INET
+    sin_port: i16,     // 16-bit TCP or UDP port number (network byte ordered)
+    sin_addr: InAddr,  // 32-bit IPv4 address (network byte ordered)
+    sin_zero: [i8; 8], // unused
+}
+
 #[no_mangle]
-pub fn print_int(int: i64) {
+pub extern "C" fn print_int(int: i64) {
     println!("print_int: {:#.?}", int);
 }
 
-#[used]
-static EXTERNAL_FNS3: [fn(i64); 1] = [print_int];
+#[no_mangle]
+pub extern "C" fn print_class(class: SockaddrIn) {
+    println!("print_class: {:#?}", class);
+}
+
+#[no_mangle]
+pub extern "C" fn print_bytes(bytes: *const u8, len: i64) {
+    let slice = unsafe { std::slice::from_raw_parts(bytes, len as usize) };
+
+    for byte in slice {
+        print!("{}", *byte as char);
+    }
+
+    print!("\n");
+
+    std::io::Write::flush(&mut std::io::stdout()).unwrap(); // Ensure output is displayed
+}
+
+/// The arguments `pj_main_args` hands to the program, set by the JIT before
+/// it runs the program. Executables use the process arguments instead.
+static PROGRAM_ARGS: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+pub fn set_program_args(args: Vec<String>) {
+    *PROGRAM_ARGS.lock().unwrap() = Some(args);
+}
+
+/// Copies `string` into a `Str` owned by the program. Like the rest of the
+/// runtime's allocations it lives until the program exits.
+fn pj_str_from(string: String) -> PjStr {
+    let bytes: &'static mut [u8] = Box::leak(string.into_bytes().into_boxed_slice());
+
+    PjStr {
+        buffer: bytes.as_ptr() as *const i8,
+        length: bytes.len() as i64,
+        max_length: bytes.len() as i64,
+    }
+}
+
+/// The program's arguments, passed to a `main` that takes a `StrArray`.
+/// Arguments that aren't UTF-8 have the invalid bytes replaced.
+#[no_mangle]
+pub extern "C" fn pj_main_args() -> *mut PjStrArray {
+    let args = match PROGRAM_ARGS.lock().unwrap().clone() {
+        Some(args) => args,
+        None => std::env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+    };
+
+    let strs: Vec<PjStr> = args.into_iter().map(pj_str_from).collect();
+    let strs: &'static mut [PjStr] = Box::leak(strs.into_boxed_slice());
+
+    Box::into_raw(Box::new(PjStrArray {
+        buffer: strs.as_mut_ptr(),
+        length: strs.len() as i64,
+    }))
+}
+
+/// The `Str` at `index`, an empty `Str` when the index is out of bounds since
+/// a panic can't unwind into the program.
+#[no_mangle]
+pub extern "C" fn pj_str_array_get(pj_str_array: &PjStrArray, index: i64) -> *mut PjStr {
+    if index < 0 || index >= pj_str_array.length {
+        return Box::into_raw(Box::new(pj_str_from(String::new())));
+    }
+
+    unsafe { pj_str_array.buffer.add(index as usize) }
+}
+
+/// The value of the environment variable `name`, an empty `Str` when it
+/// isn't set.
+#[no_mangle]
+pub extern "C" fn env_get(name: &PjStr) -> *mut PjStr {
+    let value = std::env::var(pjstr_to_str(name)).unwrap_or_default();
+
+    Box::into_raw(Box::new(pj_str_from(value)))
+}
+
+/// Sets the environment variable `name` to `value` and returns the value it
+/// had before, an empty `Str` when it wasn't set.
+#[no_mangle]
+pub extern "C" fn env_set(name: &PjStr, value: &PjStr) -> *mut PjStr {
+    let name = pjstr_to_str(name);
+    let previous = std::env::var(name).unwrap_or_default();
+
+    std::env::set_var(name, pjstr_to_str(value));
+
+    Box::into_raw(Box::new(pj_str_from(previous)))
+}
+
+/// The exported runtime functions by symbol name, for registering with the
+/// JIT so compiled programs can call into the runtime.
+pub fn symbols() -> [(&'static str, *mut ()); 14] {
+    [
+        ("pj_main_args", pj_main_args as *mut ()),
+        ("pj_str_array_get", pj_str_array_get as *mut ()),
+        ("env_get", env_get as *mut ()),
+        ("env_set", env_set as *mut ()),
+        ("print_int", print_int as *mut ()),
+        ("print_class", print_class as *mut ()),
+        ("print_bytes", print_bytes as *mut ()),
+        ("pj_malloc_struct", pj_malloc_struct as *mut ()),
+        ("pj_listen", pj_listen as *mut ()),
+        ("pj_poll", pj_poll as *mut ()),
+        ("pj_check_events", pj_check_events as *mut ()),
+        ("pj_tcp_connection_buffer", pj_tcp_connection_buffer as *mut ()),
+        ("pj_tcp_connection_flush", pj_tcp_connection_flush as *mut ()),
+        ("pj_tcp_connection_write", pj_tcp_connection_write as *mut ()),
+    ]
+}
 
 // #[no_mangle]
 // pub fn base_print(pj_str: PjStr) {
 //     print_bytes(pj_str.buffer as *const u8, pj_str.length);
 // }
 
-// #[used]
-// static EXTERNAL_FNS15: [fn(PjStr); 1] = [base_print];
-
 use libc::{c_void, malloc};
 // You can run this example from the root of the mio repo:
 // cargo run --example tcp_server --features="os-poll net"
 use mio::event::Event;
 use mio::net::{TcpListener, TcpStream};
 use mio::{Events, Interest, Poll, Registry, Token};
-use safer_ffi::vec;
 use std::collections::HashMap;
 use std::io::{self, Read, Write};
 use std::mem::size_of;
-
-use crate::codegen::print_bytes;
+use std::sync::Mutex;
 
 // // Setup some tokens to allow us to identify which event is for which socket.
 const SERVER: Token = Token(0);
@@ -66,9 +201,6 @@ const SERVER: Token = Token(0);
 // // Some data we'll send over the connection.
 // const DATA: &[u8] = b"Hello world!\n";
 
-#[used]
-static EXTERNAL_FNS6: [extern "C" fn(&PjStr) -> *mut c_void; 1] = [pj_malloc_struct];
-
 fn pjstr_to_str(pj_str: &PjStr) -> &str {
     unsafe {
         // Create a slice from the raw buffer and length
@@ -123,9 +255,6 @@ pub extern "C" fn pj_malloc_struct(pj_name: &PjStr) -> *mut c_void {
     }
 }
 
-#[used]
-static EXTERNAL_FNS9: [extern "C" fn(&mut PjTcpServer); 1] = [pj_listen];
-
 #[no_mangle]
 pub extern "C" fn pj_listen(pj_tcp_server: &mut PjTcpServer) {
     let addr = unsafe {
@@ -160,9 +289,6 @@ pub extern "C" fn pj_listen(pj_tcp_server: &mut PjTcpServer) {
     println!("You'll see our welcome message and anything you type will be printed here.");
 }
 
-#[used]
-static EXTERNAL_FNS10: [extern "C" fn(&mut PjTcpServer); 1] = [pj_poll];
-
 #[no_mangle]
 pub extern "C" fn pj_poll(pj_tcp_server: &mut PjTcpServer) {
     let events = unsafe { pj_tcp_server.events.as_mut().unwrap() };
@@ -178,9 +304,6 @@ pub extern "C" fn pj_poll(pj_tcp_server: &mut PjTcpServer) {
     }
 }
 
-#[used]
-static EXTERNAL_FNS11: [extern "C" fn(&mut PjTcpServer, &PjTcpEvents); 1] = [pj_check_events];
-
 #[no_mangle]
 pub extern "C" fn pj_check_events(pj_tcp_server: &mut PjTcpServer, pj_tcp_events: &PjTcpEvents) {
     let events = unsafe { pj_tcp_server.events.as_ref().unwrap() };
@@ -358,10 +481,6 @@ fn handle_connection_event(
     Ok(false)
 }
 
-#[used]
-static EXTERNAL_FNS17: [extern "C" fn(&mut PjTcpConnection, &PjStr); 1] =
-    [pj_tcp_connection_buffer];
-
 #[no_mangle]
 pub extern "C" fn pj_tcp_connection_buffer(
     pj_tcp_connection: &mut PjTcpConnection,
@@ -373,10 +492,6 @@ pub extern "C" fn pj_tcp_connection_buffer(
     });
 }
 
-#[used]
-static EXTERNAL_FNS20: [extern "C" fn(&mut TcpStream, &Event, &PjTcpServer); 1] =
-    [pj_tcp_connection_flush];
-
 #[no_mangle]
 pub extern "C" fn pj_tcp_connection_flush(
     connection: &mut TcpStream,
@@ -444,9 +559,6 @@ pub extern "C" fn pj_tcp_connection_flush(
     }
 }
 
-#[used]
-static EXTERNAL_FNS12: [extern "C" fn(&mut PjTcpConnection, &PjStr); 1] = [pj_tcp_connection_write];
-
 #[no_mangle]
 pub extern "C" fn pj_tcp_connection_write(pj_tcp_connection: &mut PjTcpConnection, pj_str: &PjStr) {
     let connection = unsafe { pj_tcp_connection.tcp_stream.as_mut().unwrap() };
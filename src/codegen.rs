@@ -1,5 +1,7 @@
+use crate::debug_info::DebugInfo;
 use crate::parser;
 use crate::parser::{BaseType, Def, FnRef, Node, ParserResult};
+use crate::source::Span;
 // use crate::mi_malloc;
 use melior::dialect::llvm::attributes::{linkage, Linkage};
 use melior::dialect::llvm::AllocaOptions;
@@ -7,7 +9,7 @@ use melior::dialect::scf;
 use melior::dialect::{index, llvm, memref};
 use melior::ir::attribute::{
     ArrayAttribute, DenseElementsAttribute, DenseI32ArrayAttribute, DenseI64ArrayAttribute,
-    FlatSymbolRefAttribute, IntegerAttribute,
+    FlatSymbolRefAttribute, FloatAttribute, IntegerAttribute,
 };
 use melior::ir::operation::{OperationBuilder, OperationResult};
 use melior::ir::r#type::{IntegerType, MemRefType, RankedTensorType};
@@ -29,54 +31,16 @@ use std::borrow::BorrowMut;
 use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
 
-#[no_mangle]
-#[derive(Debug)]
-#[repr(C)]
-struct InAddr {
-    s_addr: i32,
-}
-
-#[no_mangle]
-#[derive(Debug)]
-#[repr(C)]
-struct SockaddrIn {
-    sin_len: i8,       // length of structure (16)
-    sin_family: i8,    // AF_INET
-    sin_port: i16,     // 16-bit TCP or UDP port number (network byte ordered)
-    sin_addr: InAddr,  // 32-bit IPv4 address (network byte ordered)
-    sin_zero: [i8; 8], // unused
-}
-
-#[no_mangle]
-pub fn print_class(class: SockaddrIn) {
-    unsafe { println!("print_class: {:#?}", class) };
-}
-
-#[no_mangle]
-pub fn print_bytes(bytes: *const u8, len: i64) {
-    let slice = unsafe { std::slice::from_raw_parts(bytes, len as usize) };
-
-    for byte in slice {
-        print!("{}", *byte as char);
-    }
-
-    print!("\n");
-
-    std::io::Write::flush(&mut std::io::stdout()).unwrap(); // Ensure output is displayed
-}
-
-#[used]
-static EXTERNAL_FNS4: [fn(*const u8, i64); 1] = [print_bytes];
-#[used]
-static EXTERNAL_FNS5: [fn(SockaddrIn); 1] = [print_class];
-
 /// Defines the `Expr` compiler.
 #[derive(Debug, Clone, Copy)]
 pub struct LlvmTypes<'c> {
+    pub i1_type: Type<'c>,
     pub i8_type: Type<'c>,
     pub i16_type: Type<'c>,
     pub i32_type: Type<'c>,
     pub i64_type: Type<'c>,
+    pub f32_type: Type<'c>,
+    pub f64_type: Type<'c>,
     pub i8_ptr_type: Type<'c>,
     pub i8_array_type: Type<'c>,
     pub i8_array_ptr_type: Type<'c>,
@@ -88,6 +52,18 @@ pub struct LlvmTypes<'c> {
     pub void_type: Type<'c>,
 }
 
+/// The symbol a `main` that takes the program's arguments is emitted as, the
+/// C `main` generated by `compile_c_main` takes its place and passes them in.
+pub const MAIN_WITH_ARGS: &str = "pj_main";
+
+/// The function the JIT enters programs with a C `main` at. The execution
+/// engine only calls functions through their C interface, the function named
+/// with `C_INTERFACE_PREFIX`, so `compile_jit_main` defines it under that name.
+pub const JIT_MAIN: &str = "pj_jit_main";
+
+/// What `llvm.emit_c_interface` prefixes the C interface of a function with.
+pub const C_INTERFACE_PREFIX: &str = "_mlir_ciface_";
+
 /// Defines the `Expr` compiler.
 #[derive(Debug)]
 pub struct Compiler<'c, 'm> {
@@ -97,6 +73,8 @@ pub struct Compiler<'c, 'm> {
     pub llvm_types: LlvmTypes<'c>,
     pub class_type_index: HashMap<String, Type<'m>>,
     pub struct_type_index: HashMap<String, Type<'m>>,
+    /// Emits debug info for functions and local variables when set
+    pub debug_info: Option<DebugInfo>,
     // pub llvm_types: LlvmTypes<'m>,
     // pub class_type_index: HashMap<String, Type<'m>>,
 
@@ -115,6 +93,24 @@ pub struct FnCtx<'c, 'a> {
     pub lvars: HashMap<String, Value<'c, 'a>>,
     pub lvar_stores: HashMap<String, Value<'c, 'a>>,
     pub parent_ctx: Option<Box<&'c FnCtx<'c, 'a>>>,
+    /// The first block of the function, where allocas go so that the ones in
+    /// loops aren't made again on each iteration
+    pub entry_block: &'a Block<'c>,
+    /// The `#llvm.di_subprogram` of the function, when emitting debug info
+    pub debug_scope: Option<String>,
+    /// The flags of the innermost loop, set on the context of its body
+    pub loop_flags: Option<LoopFlags<'c, 'a>>,
+}
+
+/// Pointers to the `i1` flags `break` and `next` set to leave a loop. `scf`
+/// regions can't branch out, so the loop checks `stop` before each iteration
+/// and the statements after a `break` or `next` check `skip`.
+#[derive(Debug, Clone, Copy)]
+pub struct LoopFlags<'c, 'a> {
+    pub stop: Value<'c, 'a>,
+    pub skip: Value<'c, 'a>,
+    /// Where `break` stores the value of the loop, when it has one
+    pub result: Option<Value<'c, 'a>>,
 }
 
 impl<'c, 'm> Compiler<'c, 'm> {
@@ -123,10 +119,13 @@ impl<'c, 'm> Compiler<'c, 'm> {
         module: &'m Module<'c>,
         parser_result: &'m ParserResult,
     ) -> Self {
+        let i1_type = IntegerType::new(context, 1).into();
         let i8_type = IntegerType::new(context, 8).into();
         let i16_type = IntegerType::new(context, 16).into();
         let i32_type = IntegerType::new(context, 32).into();
         let i64_type = IntegerType::new(context, 64);
+        let f32_type = Type::float32(context);
+        let f64_type = Type::float64(context);
         let i8_ptr_type = llvm::r#type::r#pointer(i8_type, 0);
         let i8_array_type = llvm::r#type::array(i8_type, 5);
         let i8_array_ptr_type = llvm::r#type::r#pointer(i8_array_type, 0);
@@ -143,9 +142,12 @@ impl<'c, 'm> Compiler<'c, 'm> {
         let void_type = llvm::r#type::void(context);
 
         let llvm_types = LlvmTypes {
+            i1_type,
             i16_type,
             i32_type,
             i64_type: i64_type.into(),
+            f32_type,
+            f64_type,
             i8_array_ptr_type,
             i8_array_type,
             i8_ptr_type,
@@ -191,7 +193,87 @@ impl<'c, 'm> Compiler<'c, 'm> {
             llvm_types,
             class_type_index,
             struct_type_index,
+            debug_info: None,
+        }
+    }
+
+    /// The location of a span in the source, for the operations compiled from
+    /// its node. Nodes the parser synthesized without a span get an unknown
+    /// location.
+    fn location(&self, span: Span) -> Location<'c> {
+        if span.line == 0 {
+            return Location::unknown(&self.context);
         }
+
+        Location::new(
+            &self.context,
+            self.file_name(span),
+            span.line,
+            span.start_column,
+        )
+    }
+
+    fn file_name(&self, span: Span) -> &str {
+        self.parser_result
+            .source_map
+            .file_name(span.file)
+            .unwrap_or("<input>")
+    }
+
+    /// The `#llvm.di_subprogram` of a function, when emitting debug info.
+    fn subprogram(&self, node: &parser::Def) -> Option<String> {
+        let debug_info = self.debug_info.as_ref()?;
+
+        Some(debug_info.subprogram(
+            self.file_name(node.span),
+            &node.prototype.name,
+            symbol_name(node),
+            node.span.line,
+        ))
+    }
+
+    /// Describes a variable to the debugger, with `llvm.intr.dbg.declare` when
+    /// `value` is the address it's stored at and `llvm.intr.dbg.value`
+    /// otherwise. Local variables are described by the same name, type and
+    /// span as arguments, `arg` is only set for the function's parameters.
+    fn declare_variable<'a>(
+        &self,
+        block: &'a Block<'c>,
+        ctx: &FnCtx<'c, 'a>,
+        value: Value<'c, 'a>,
+        is_address: bool,
+        variable: &parser::Arg,
+        arg: Option<usize>,
+    ) {
+        let (Some(debug_info), Some(debug_scope)) = (&self.debug_info, &ctx.debug_scope) else {
+            return;
+        };
+
+        let local_variable = debug_info.local_variable(
+            debug_scope,
+            self.file_name(variable.span),
+            &variable.name,
+            variable.span.line,
+            arg,
+            &variable.return_type,
+        );
+
+        let op_name = if is_address {
+            "llvm.intr.dbg.declare"
+        } else {
+            "llvm.intr.dbg.value"
+        };
+
+        block.append_operation(
+            OperationBuilder::new(op_name, self.location(variable.span))
+                .add_operands(&[value])
+                .add_attributes(&[(
+                    Identifier::new(&self.context, "varInfo"),
+                    Attribute::parse(&self.context, &local_variable).unwrap(),
+                )])
+                .build()
+                .unwrap(),
+        );
     }
 
     pub fn compile(&mut self) -> Result<(), &'static str> {
@@ -261,7 +343,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         //     class_type_index,
         // };
 
-        self.compile_ast();
+        self.compile_ast()?;
 
         // println!("PRE VERIFICATION:");
         // println!("{}", self.module.body().to_string());
@@ -321,18 +403,16 @@ impl<'c, 'm> Compiler<'c, 'm> {
         Ok(())
     }
 
-    fn compile_ast(&mut self) {
+    fn compile_ast(&mut self) -> Result<(), &'static str> {
         match &self.parser_result.module {
-            Node::Module(module) => {
-                self.compile_module(module);
-            }
+            Node::Module(module) => self.compile_module(module),
             _ => {
                 panic!("Expected module to compile")
             }
         }
     }
 
-    fn compile_module(&mut self, module: &parser::Module) {
+    fn compile_module(&mut self, module: &parser::Module) -> Result<(), &'static str> {
         let mut mctx = ModuleCtx {
             global_var_counter: 0,
         };
@@ -341,7 +421,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             match &node {
                 Node::Def(def) => self.compile_def(def, &mut mctx),
                 Node::DefE(def_e) => self.compile_external_fn(def_e),
-                Node::AssignConstant(node) => self.compile_assign_constant(node, &mut mctx),
+                Node::AssignConstant(node) => self.compile_assign_constant(node, &mut mctx)?,
                 Node::Access(_) => todo!(),
                 Node::AssignAttribute(_) => todo!(),
                 Node::AssignAttributeAccess(_) => todo!(),
@@ -361,12 +441,20 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 Node::Send(_) => todo!(),
                 Node::StringLiteral(_) => todo!(),
                 Node::Trait(_) => todo!(),
+                Node::Unary(_) => todo!(),
                 Node::Array(_) => todo!(),
                 Node::BuildStruct(_) => todo!(),
                 Node::Struct(_) => todo!(),
                 Node::FnRef(_) => todo!(),
+                Node::Float(_) => todo!(),
+                Node::Bool(_) => todo!(),
+                Node::If(_) => todo!(),
+                Node::Break(_) => todo!(),
+                Node::Next(_) => todo!(),
             }
         }
+
+        Ok(())
     }
 
     // fn compile_class(&mut self, class: &parser::Class) {
@@ -386,35 +474,16 @@ impl<'c, 'm> Compiler<'c, 'm> {
         // :D
     }
 
-    fn compile_assign_constant(&mut self, node: &parser::AssignConstant, mctx: &mut ModuleCtx) {
+    fn compile_assign_constant(
+        &mut self,
+        node: &parser::AssignConstant,
+        mctx: &mut ModuleCtx,
+    ) -> Result<(), &'static str> {
+        let location = self.location(node.span);
         let node_type = self.basetype_to_mlir_type(&node.return_type);
         let node_value = match node.value.as_ref() {
             Node::Int(int_node) => int_node.value,
-            Node::FnRef(_) => todo!(),
-            Node::Access(_) => todo!(),
-            Node::AssignAttribute(_) => todo!(),
-            Node::AssignAttributeAccess(_) => todo!(),
-            Node::AssignLocalVar(_) => todo!(),
-            Node::AssignConstant(_) => todo!(),
-            Node::Attribute(_) => todo!(),
-            Node::Binary(_) => todo!(),
-            Node::Call(_) => todo!(),
-            Node::Class(_) => todo!(),
-            Node::Const(_) => todo!(),
-            Node::Def(_) => todo!(),
-            Node::DefE(_) => todo!(),
-            Node::Impl(_) => todo!(),
-            Node::LocalVar(_) => todo!(),
-            Node::Loop(_) => todo!(),
-            Node::Module(_) => todo!(),
-            Node::Ret(_) => todo!(),
-            Node::SelfRef(_) => todo!(),
-            Node::Send(_) => todo!(),
-            Node::StringLiteral(_) => todo!(),
-            Node::Trait(_) => todo!(),
-            Node::Array(_) => todo!(),
-            Node::BuildStruct(_) => todo!(),
-            Node::Struct(_) => todo!(),
+            _ => return Err("constants can only be initialized with an integer"),
         };
 
         let int_attr = IntegerAttribute::new(node_type, node_value as i64).into();
@@ -431,12 +500,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Some(int_attr),
             node_type,
             region,
-            Location::unknown(&self.context),
+            location,
         ));
+
+        Ok(())
     }
 
     fn compile_def(&mut self, node: &parser::Def, mctx: &mut ModuleCtx) {
-        let fn_name = StringAttribute::new(&self.context, node.prototype.name.as_str());
+        let fn_name = StringAttribute::new(&self.context, symbol_name(node));
         let mut inputs = vec![];
 
         for arg in &node.prototype.args {
@@ -492,7 +563,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
             // ));
         }
 
-        let location = Location::unknown(&self.context);
+        let location = match self.subprogram(node) {
+            Some(subprogram) => Location::fused(
+                &self.context,
+                &[self.location(node.span)],
+                Attribute::parse(&self.context, &subprogram).unwrap(),
+            ),
+            None => self.location(node.span),
+        };
         // let operation = func::func(
         let operation = llvm::func(
             &self.context,
@@ -539,7 +617,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Identifier::new(&self.context, "sym_visibility"),
             StringAttribute::new(&self.context, "private").into(),
         )];
-        let location = Location::unknown(&self.context);
+        let location = self.location(node.span);
         let operation = llvm::func(
             &self.context,
             name,
@@ -552,17 +630,132 @@ impl<'c, 'm> Compiler<'c, 'm> {
         self.module.body().append_operation(operation);
     }
 
+    /// Defines a C `main` that calls `entry_point`, so programs whose entry
+    /// point isn't named `main` can still be linked into an executable. The
+    /// entry point's status is passed on when `returns_status` is set, and an
+    /// entry point that takes an argument is passed the program's arguments.
+    pub fn compile_c_main(&self, entry_point: &str, returns_status: bool) {
+        let i32_type = self.llvm_types.i32_type;
+        let block = Block::new(&[]);
+        let prototype = &self.parser_result.index.fn_prototype_index[entry_point];
+        let location = self.location(prototype.span);
+
+        // A value that isn't a status is dropped and the status is `0`
+        let result_types = match &prototype.return_type {
+            _ if returns_status => vec![i32_type],
+            Some(return_type) => vec![self.basetype_to_mlir_type(return_type)],
+            None => vec![],
+        };
+
+        let mut args = vec![];
+
+        if let Some(arg) = prototype.args.first() {
+            let args_type = self.basetype_to_mlir_type(&arg.return_type);
+
+            self.module.body().append_operation(llvm::func(
+                &self.context,
+                StringAttribute::new(&self.context, "pj_main_args"),
+                TypeAttribute::new(llvm::r#type::function(args_type, &[], false)),
+                Region::new(),
+                &[(
+                    Identifier::new(&self.context, "sym_visibility"),
+                    StringAttribute::new(&self.context, "private").into(),
+                )],
+                location,
+            ));
+
+            let main_args = block.append_operation(llvm::call(
+                &self.context,
+                FlatSymbolRefAttribute::new(&self.context, "pj_main_args"),
+                &[],
+                &[args_type],
+                location,
+            ));
+
+            args.push(main_args.result(0).unwrap().into());
+        }
+
+        let callee = if entry_point == "main" {
+            MAIN_WITH_ARGS
+        } else {
+            entry_point
+        };
+
+        let call = block.append_operation(llvm::call(
+            &self.context,
+            FlatSymbolRefAttribute::new(&self.context, callee),
+            &args,
+            &result_types,
+            location,
+        ));
+
+        let status_code = if returns_status {
+            call.result(0).unwrap().into()
+        } else {
+            self.compile_status_code(&block, None, location)
+        };
+
+        block.append_operation(llvm::r#return(Some(status_code), location));
+
+        let region = Region::new();
+        region.append_block(block);
+
+        self.module.body().append_operation(llvm::func(
+            &self.context,
+            StringAttribute::new(&self.context, "main"),
+            TypeAttribute::new(llvm::r#type::function(i32_type, &[], false)),
+            region,
+            &[],
+            location,
+        ));
+    }
+
+    /// Defines the C interface of `JIT_MAIN`, which returns the status of the
+    /// C `main` of the program entered at `entry_point`. `llvm.func`s don't
+    /// get one generated, `llvm.emit_c_interface` is only for `func.func`s.
+    pub fn compile_jit_main(&self, entry_point: &str) {
+        let i32_type = self.llvm_types.i32_type;
+        let block = Block::new(&[]);
+        let location = self.location(self.parser_result.index.fn_prototype_index[entry_point].span);
+
+        let call = block.append_operation(llvm::call(
+            &self.context,
+            FlatSymbolRefAttribute::new(&self.context, "main"),
+            &[],
+            &[i32_type],
+            location,
+        ));
+
+        block.append_operation(llvm::r#return(
+            Some(call.result(0).unwrap().into()),
+            location,
+        ));
+
+        let region = Region::new();
+        region.append_block(block);
+
+        self.module.body().append_operation(llvm::func(
+            &self.context,
+            StringAttribute::new(
+                &self.context,
+                &format!("{}{}", C_INTERFACE_PREFIX, JIT_MAIN),
+            ),
+            TypeAttribute::new(llvm::r#type::function(i32_type, &[], false)),
+            region,
+            &[],
+            location,
+        ));
+    }
+
     fn compile_fn_body(
         &mut self,
         node: &parser::Def,
         mctx: &mut ModuleCtx,
     ) -> Result<Region<'c>, &'static str> {
+        let location = self.location(node.span);
         let mut inputs = vec![];
         for arg in node.prototype.args.iter() {
-            inputs.push((
-                self.basetype_to_mlir_type(&arg.return_type),
-                Location::unknown(&self.context),
-            ));
+            inputs.push((self.basetype_to_mlir_type(&arg.return_type), location));
 
             // match &arg.return_type {
             //     parser::BaseType::Int => inputs.push((
@@ -589,10 +782,13 @@ impl<'c, 'm> Compiler<'c, 'm> {
             lvars: HashMap::new(),
             lvar_stores: HashMap::new(),
             parent_ctx: None,
+            entry_block: &block,
+            debug_scope: self.subprogram(node),
+            loop_flags: None,
         };
 
         if node.body.iter().len() == 0 && !node.main_fn {
-            block.append_operation(llvm::r#return(None, Location::unknown(&self.context)));
+            block.append_operation(llvm::r#return(None, location));
 
             let region = Region::new();
             region.append_block(block);
@@ -601,21 +797,9 @@ impl<'c, 'm> Compiler<'c, 'm> {
 
             // panic!("Empty body not supported")
         } else if node.body.iter().len() == 0 && node.main_fn {
-            let success_int_value = block
-                .append_operation(arith::constant(
-                    &self.context,
-                    IntegerAttribute::new(IntegerType::new(&self.context, 32).into(), 1 as i64)
-                        .into(),
-                    Location::unknown(&self.context),
-                ))
-                .result(0)
-                .unwrap()
-                .into();
+            let status_code = self.compile_status_code(&block, None, location);
 
-            block.append_operation(llvm::r#return(
-                Some(success_int_value),
-                Location::unknown(&self.context),
-            ));
+            block.append_operation(llvm::r#return(Some(status_code), location));
         }
 
         for (index, arg) in node.prototype.args.iter().enumerate() {
@@ -628,6 +812,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                     // if index == 0 {
                     let arg_n = block.argument(index).unwrap();
                     ctx.lvars.insert(arg.name.clone(), arg_n.into());
+                    self.declare_variable(&block, &ctx, arg_n.into(), false, arg, Some(index + 1));
                     continue;
                     // }
                 }
@@ -635,9 +820,10 @@ impl<'c, 'm> Compiler<'c, 'm> {
             };
 
             let return_val = block.argument(index).unwrap().into();
-            let ptr = self.append_alloca_store(return_val, &block);
+            let ptr = self.append_alloca_store(return_val, &block, location);
             ctx.lvars.insert(arg.name.clone(), ptr);
             ctx.lvar_stores.insert(arg.name.clone(), ptr);
+            self.declare_variable(&block, &ctx, ptr, true, arg, Some(index + 1));
         }
 
         let last_op_index = node.body.len();
@@ -652,24 +838,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
             let last_node = i == last_op_index - 1;
             if last_node {
                 if node.main_fn {
-                    let success_int_value = block
-                        .append_operation(arith::constant(
-                            &self.context,
-                            IntegerAttribute::new(
-                                IntegerType::new(&self.context, 32).into(),
-                                1 as i64,
-                            )
-                            .into(),
-                            Location::unknown(&self.context),
-                        ))
-                        .result(0)
-                        .unwrap()
-                        .into();
+                    let status_code = match node.prototype.return_type {
+                        Some(BaseType::Int32) => {
+                            self.compile_status_code(&block, return_val, location)
+                        }
+                        _ => self.compile_status_code(&block, None, location),
+                    };
 
-                    block.append_operation(llvm::r#return(
-                        Some(success_int_value),
-                        Location::unknown(&self.context),
-                    ));
+                    block.append_operation(llvm::r#return(Some(status_code), location));
                 } else {
                     match &node.prototype.return_type {
                         Some(rt) => match rt {
@@ -677,24 +853,35 @@ impl<'c, 'm> Compiler<'c, 'm> {
                                 todo!()
                             }
                             _ => {
-                                if node.prototype.name.ends_with(".new") {
-                                    block.append_operation(llvm::r#return(
-                                        None,
-                                        Location::unknown(&self.context),
-                                    ));
-                                } else {
-                                    block.append_operation(llvm::r#return(
-                                        None,
-                                        Location::unknown(&self.context),
-                                    ));
-                                }
+                                // `.new` and `.alloca` return their value by sret
+                                let sret = node.prototype.name.ends_with(".new")
+                                    || node.prototype.name.ends_with(".alloca");
+                                let return_type = self.basetype_to_mlir_type(rt);
+                                let value = match return_val {
+                                    _ if sret => None,
+                                    // Numbers the type checker allows are converted
+                                    Some(value) if value.r#type() != return_type => {
+                                        let value_node = match body_node {
+                                            Node::Ret(ret_node) => ret_node.value.as_ref(),
+                                            _ => body_node,
+                                        };
+
+                                        Some(self.compile_type_cast(
+                                            &block,
+                                            value,
+                                            self.node_base_type(value_node).unwrap(),
+                                            rt.clone(),
+                                            location,
+                                        ))
+                                    }
+                                    value => value,
+                                };
+
+                                block.append_operation(llvm::r#return(value, location));
                             }
                         },
                         None => {
-                            block.append_operation(llvm::r#return(
-                                None,
-                                Location::unknown(&self.context),
-                            ));
+                            block.append_operation(llvm::r#return(None, location));
                         }
                     }
                 }
@@ -707,6 +894,35 @@ impl<'c, 'm> Compiler<'c, 'm> {
         Ok(region)
     }
 
+    /// The `i32` exit status an entry point returns, the value of its last
+    /// expression narrowed to `i32`, or `0` when there isn't one.
+    fn compile_status_code<'a>(
+        &self,
+        block: &'a Block<'c>,
+        value: Option<Value<'c, 'a>>,
+        location: Location<'c>,
+    ) -> Value<'c, 'a> {
+        let i32_type = self.llvm_types.i32_type;
+
+        match value {
+            Some(value) if value.r#type() == i32_type => value,
+            Some(value) => block
+                .append_operation(arith::trunci(value, i32_type, location))
+                .result(0)
+                .unwrap()
+                .into(),
+            None => block
+                .append_operation(arith::constant(
+                    &self.context,
+                    IntegerAttribute::new(i32_type, 0).into(),
+                    location,
+                ))
+                .result(0)
+                .unwrap()
+                .into(),
+        }
+    }
+
     fn compile_expr<'a>(
         &self,
         block: &'a Block<'c>,
@@ -726,9 +942,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Node::Binary(binary) => self.compile_binary(block, binary, ctx, mctx),
             Node::Call(call) => self.compile_call(block, call, ctx, mctx),
             Node::Int(nb) => self.compile_int(block, nb),
+            Node::Float(nb) => self.compile_float(block, nb),
+            Node::Bool(node) => self.compile_bool(block, node),
             Node::FnRef(fn_ref) => self.compile_fn_ref(block, fn_ref, ctx, mctx),
             Node::LocalVar(lvar) => self.compile_local_var(block, lvar, ctx, mctx),
             Node::Loop(node) => self.compile_loop(block, node, ctx, mctx),
+            Node::Break(node) => self.compile_break(block, node, ctx, mctx),
+            Node::Next(node) => self.compile_next(block, node, ctx),
+            Node::If(node) => self.compile_if(block, node, ctx, mctx),
             Node::Ret(ret) => self.compile_return(block, ret, ctx, mctx),
             Node::SelfRef(lvar) => self.compile_self_ref(block, lvar, ctx, mctx),
             Node::Send(node) => self.compile_send(block, node, ctx, mctx),
@@ -744,6 +965,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Node::Impl(_) => panic!("Syntax error"),
             Node::Module(_) => panic!("Syntax error"),
             Node::Trait(_) => panic!("Syntax error"),
+            Node::Unary(node) => self.compile_unary(block, node, ctx, mctx),
             Node::Struct(_) => panic!("Syntax error"),
         }
     }
@@ -755,6 +977,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(access.span);
         let value = match &*access.receiver {
             Node::LocalVar(lvar) => {
                 let lvar_value = self.get_lvar(&lvar.name, ctx).unwrap();
@@ -771,14 +994,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
                             lvar_value,
                             DenseI32ArrayAttribute::new(&self.context, &[0, attribute_index]),
                             llvm::r#type::r#pointer(result_type, 0),
-                            Location::unknown(&self.context),
+                            location,
                         ));
 
                         let load_op = block.append_operation(llvm::load(
                             &self.context,
                             gep.result(0).unwrap().into(),
                             result_type,
-                            Location::unknown(&self.context),
+                            location,
                             Default::default(),
                         ));
 
@@ -855,14 +1078,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
                     lvar_value,
                     DenseI32ArrayAttribute::new(&self.context, &[0, attribute_index]),
                     llvm::r#type::r#pointer(result_type, 0),
-                    Location::unknown(&self.context),
+                    location,
                 ));
 
                 let load_op = block.append_operation(llvm::load(
                     &self.context,
                     gep.result(0).unwrap().into(),
                     result_type,
-                    Location::unknown(&self.context),
+                    location,
                     Default::default(),
                 ));
 
@@ -931,16 +1154,12 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(send_node.span);
         let call_node = match send_node.message.as_ref() {
             Node::Call(call_node) => call_node,
             _ => return Err("Expected send_node message to be a Call"),
         };
 
-        println!("{:#?}", send_node);
-
-        println!("{:#?}", "ctx.lvars");
-        println!("{:#?}", ctx.lvars);
-
         let value = match send_node.receiver.as_ref() {
             Node::LocalVar(local_var) => match &local_var.return_type {
                 Some(rt) => {
@@ -950,6 +1169,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                             if call_node.fn_name == "fn_ref" {
                                 let fn_ref = FnRef {
                                     fn_name: local_var.name.clone(),
+                                    span: local_var.span,
                                 };
 
                                 return self.compile_fn_ref(block, &fn_ref, ctx, mctx);
@@ -968,11 +1188,11 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 let value = if call_node.fn_name.ends_with(".new") {
                     let class_type = self.class_type_index.get(&const_node.name).unwrap();
                     // add sret
-                    self.append_alloca_class(class_type.clone(), block)
+                    self.append_alloca_class(class_type.clone(), block, location)
                 } else if call_node.fn_name.ends_with(".alloca") {
                     let class_type = self.class_type_index.get(&const_node.name).unwrap();
                     // add sret
-                    self.append_alloca_class(class_type.clone(), block)
+                    self.append_alloca_class(class_type.clone(), block, location)
                 } else {
                     // Class methods
                     todo!()
@@ -990,10 +1210,6 @@ impl<'c, 'm> Compiler<'c, 'm> {
         // let mut inputs = vec![receiver_value.r#type()];
         let mut inputs = vec![];
 
-        println!("{:#?}", &call_node.fn_name);
-
-        println!("{:#?}", self.parser_result.index.fn_prototype_index);
-
         let prototype = self
             .parser_result
             .index
@@ -1037,7 +1253,6 @@ impl<'c, 'm> Compiler<'c, 'm> {
         //     inputs.push(arg_return_type);
         // }
 
-        let location = Location::unknown(&self.context);
         let mut results = match &send_node.return_type {
             Some(base_type) => vec![self.basetype_to_mlir_type(&base_type)],
             None => vec![],
@@ -1073,23 +1288,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
 
         let mut compiled_args = vec![receiver_value];
 
-        println!("call_node.args");
-        println!("{:#?}", call_node.args);
-
         for (index, arg) in call_node.args.iter().enumerate() {
             let mut value = self.compile_expr(block, &arg, ctx, mctx).unwrap().unwrap();
             let arg_return_type = self.node_base_type(arg).unwrap();
             let prototype_arg_type = prototype.args[index + 1].return_type.clone();
 
-            println!("prototype: {:#?}", prototype);
-            println!("arg_return_type: {:#?}", arg_return_type);
-
-            println!("prototype_arg_type: {:#?}", prototype_arg_type);
-
-            println!("arg: {:#?}", arg);
-
             // refactor, duplicate of call
-            value = self.compile_type_cast(block, value, arg_return_type, prototype_arg_type);
+            value =
+                self.compile_type_cast(block, value, arg_return_type, prototype_arg_type, location);
 
             compiled_args.push(value);
         }
@@ -1143,200 +1349,31 @@ impl<'c, 'm> Compiler<'c, 'm> {
         mut value: Value<'c, 'a>,
         arg_return_type: BaseType,
         prototype_arg_type: BaseType,
+        location: Location<'c>,
     ) -> Value<'c, 'a> {
         if arg_return_type != prototype_arg_type {
-            println!("{:#?}", "mismatch:");
-            println!("{:#?}", arg_return_type);
-            println!("{:#?}", prototype_arg_type);
-
             let cast_type = self.basetype_to_mlir_type(&prototype_arg_type);
 
             match arg_return_type {
-                BaseType::Byte => match prototype_arg_type {
-                    BaseType::Byte => todo!(),
-                    BaseType::Int => {
-                        value = block
-                            .append_operation(arith::extsi(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int16 => todo!(),
-                    BaseType::Int32 => {
-                        value = block
-                            .append_operation(arith::extsi(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int64 => todo!(),
-                    BaseType::Array(_, _) => todo!(),
-                    BaseType::Class(_) => todo!(),
-                    BaseType::BytePtr => todo!(),
-                    BaseType::Void => todo!(),
-                    BaseType::Struct(_) => todo!(),
-                    BaseType::FnRef => todo!(),
-                },
-                BaseType::Int => match prototype_arg_type {
-                    BaseType::Byte => todo!(),
-                    BaseType::Int => todo!(),
-                    BaseType::Int16 => {
-                        value = block
-                            .append_operation(arith::trunci(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int32 => todo!(),
-                    BaseType::Int64 => todo!(),
-                    BaseType::Array(_, _) => todo!(),
-                    BaseType::Class(_) => todo!(),
-                    BaseType::Struct(_) => todo!(),
-                    BaseType::BytePtr => todo!(),
-                    BaseType::Void => todo!(),
-                    BaseType::FnRef => todo!(),
-                },
-                BaseType::Int16 => match prototype_arg_type {
-                    BaseType::Byte => {
-                        value = block
-                            .append_operation(arith::trunci(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int => {
-                        value = block
-                            .append_operation(arith::extsi(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int16 => todo!(),
-                    BaseType::Int32 => todo!(),
-                    BaseType::Int64 => todo!(),
-                    BaseType::Array(_, _) => todo!(),
-                    BaseType::Class(_) => todo!(),
-                    BaseType::BytePtr => todo!(),
-                    BaseType::Void => todo!(),
-                    BaseType::Struct(_) => todo!(),
-                    BaseType::FnRef => todo!(),
-                },
-                BaseType::Int32 => match prototype_arg_type {
-                    BaseType::Byte => {
-                        value = block
-                            .append_operation(arith::trunci(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int => {
-                        value = block
-                            .append_operation(arith::extsi(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int16 => {
-                        value = block
-                            .append_operation(arith::trunci(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int32 => todo!(),
-                    BaseType::Int64 => {
-                        value = block
-                            .append_operation(arith::extsi(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Array(_, _) => todo!(),
-                    BaseType::Class(_) => todo!(),
-                    BaseType::BytePtr => todo!(),
-                    BaseType::Void => todo!(),
-                    BaseType::Struct(_) => todo!(),
-                    BaseType::FnRef => todo!(),
-                },
+                _ if arg_return_type.is_number() && prototype_arg_type.is_number() => {
+                    value = self.compile_number_cast(
+                        block,
+                        value,
+                        &arg_return_type,
+                        &prototype_arg_type,
+                        location,
+                    );
+                }
                 BaseType::Int64 => match prototype_arg_type {
-                    BaseType::Byte => {
-                        value = block
-                            .append_operation(arith::trunci(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int => {}
-                    BaseType::Int16 => {
-                        value = block
-                            .append_operation(arith::trunci(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int32 => {
-                        value = block
-                            .append_operation(arith::trunci(
-                                value,
-                                cast_type,
-                                Location::unknown(&self.context),
-                            ))
-                            .result(0)
-                            .unwrap()
-                            .into();
-                    }
-                    BaseType::Int64 => todo!(),
-                    BaseType::Array(_, _) => todo!(),
                     BaseType::Class(_) => {}
-                    BaseType::BytePtr => todo!(),
-                    BaseType::Void => todo!(),
-                    BaseType::Struct(_) => todo!(),
-                    BaseType::FnRef => todo!(),
+                    _ => todo!(),
                 },
+                BaseType::Byte
+                | BaseType::Int
+                | BaseType::Int16
+                | BaseType::Int32
+                | BaseType::Float32
+                | BaseType::Float64 => todo!(),
                 BaseType::Array(_, _) => match prototype_arg_type {
                     BaseType::Byte => todo!(),
                     BaseType::Int => todo!(),
@@ -1357,14 +1394,12 @@ impl<'c, 'm> Compiler<'c, 'm> {
                     BaseType::BytePtr => todo!(),
                     BaseType::Void => todo!(),
                     BaseType::FnRef => todo!(),
+                    BaseType::Bool => todo!(),
+                    BaseType::Float32 | BaseType::Float64 => todo!(),
                 },
                 BaseType::Class(class_name) => {
                     value = block
-                        .append_operation(llvm::bitcast(
-                            value,
-                            cast_type,
-                            Location::unknown(&self.context),
-                        ))
+                        .append_operation(llvm::bitcast(value, cast_type, location))
                         .result(0)
                         .unwrap()
                         .into();
@@ -1377,15 +1412,13 @@ impl<'c, 'm> Compiler<'c, 'm> {
                         BaseType::Int32 => todo!(),
                         BaseType::Int64 => todo!(),
                         BaseType::FnRef => todo!(),
+                        BaseType::Bool => todo!(),
+                        BaseType::Float32 | BaseType::Float64 => todo!(),
                         BaseType::Array(_, _) => todo!(),
                         BaseType::Class(class_name) => {
                             // pj_alloc_struct returns a BytePtr, this casts it to a user defined class
                             value = block
-                                .append_operation(llvm::bitcast(
-                                    value,
-                                    cast_type,
-                                    Location::unknown(&self.context),
-                                ))
+                                .append_operation(llvm::bitcast(value, cast_type, location))
                                 .result(0)
                                 .unwrap()
                                 .into();
@@ -1396,6 +1429,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                     }
                 }
                 BaseType::Void => todo!(),
+                BaseType::Bool => todo!(),
                 BaseType::Struct(_) => {}
                 BaseType::FnRef => {
                     // match prototype_arg_type {
@@ -1433,6 +1467,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(self_ref.span);
         let lvar_value = match self.get_lvar(&"sret".to_string(), ctx) {
             Some(value) => value,
             None => todo!(),
@@ -1448,6 +1483,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(fn_ref.span);
         // let func_type = FunctionType::new(&self.context, &[], &[]).into();
         let prototype = self
             .parser_result
@@ -1492,7 +1528,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 &self.context,
                 &fn_ref.fn_name,
                 llvm::r#type::pointer(function_type, 0),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -1502,7 +1538,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             .append_operation(llvm::bitcast(
                 addressof_op,
                 self.llvm_types.ptr_type,
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -1554,6 +1590,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(call.span);
         let prototype = self
             .parser_result
             .index
@@ -1564,11 +1601,6 @@ impl<'c, 'm> Compiler<'c, 'm> {
 
         let mut inputs = vec![];
 
-        println!("self.parser_result.index.fn_prototype_index");
-        println!("{:#?}", self.parser_result.index.fn_prototype_index);
-
-        println!("{:#?}", &call.fn_name);
-
         for arg in &prototype.args {
             // use the prototype to find the value. 0 is causing i64 instead of the needed i32
 
@@ -1627,24 +1659,13 @@ impl<'c, 'm> Compiler<'c, 'm> {
 
         let mut compiled_args = vec![];
 
-        println!("{:#?}", "CALLLL");
-        println!("{:#?}", call);
-
         for (index, arg) in call.args.iter().enumerate() {
             let mut value = self.compile_expr(block, &arg, ctx, mctx).unwrap().unwrap();
             let arg_return_type = self.node_base_type(arg).unwrap();
             let prototype_arg_type = prototype.args[index].return_type.clone();
 
-            println!("{:#?}", "AAAAAAAAAA");
-
-            println!("{:#?}", prototype);
-            println!("{:#?}", arg_return_type);
-
-            println!("{:#?}", prototype_arg_type);
-
-            println!("{:#?}", arg);
-
-            value = self.compile_type_cast(block, value, arg_return_type, prototype_arg_type);
+            value =
+                self.compile_type_cast(block, value, arg_return_type, prototype_arg_type, location);
 
             // if arg_return_type != prototype_arg_type {
             //     let cast_type = self.basetype_to_mlir_type(&prototype_arg_type);
@@ -1745,7 +1766,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                     &compiled_args,
                     // &function_type.result(0).into_iter().collect::<Vec<_>>(),
                     &results,
-                    Location::unknown(&self.context),
+                    location,
                 ))
                 .result(0)
                 .unwrap()
@@ -1759,7 +1780,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 &compiled_args,
                 // &function_type.result(0).into_iter().collect::<Vec<_>>(),
                 &results,
-                Location::unknown(&self.context),
+                location,
             ));
 
             Ok(None)
@@ -1771,12 +1792,13 @@ impl<'c, 'm> Compiler<'c, 'm> {
         block: &'a Block<'c>,
         nb: &parser::Int,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(nb.span);
         let value = block
             .append_operation(arith::constant(
                 &self.context,
                 IntegerAttribute::new(IntegerType::new(&self.context, 64).into(), nb.value as i64)
                     .into(),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -1792,6 +1814,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(string.span);
         let string_attr = StringAttribute::new(&self.context, &string.value);
         let i8_array_type = llvm::r#type::array(self.llvm_types.i8_type, string.value.len() as u32);
 
@@ -1804,7 +1827,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Some(string_attr.into()),
             i8_array_type,
             region,
-            Location::unknown(&self.context),
+            location,
         ));
 
         let region = Region::new();
@@ -1815,7 +1838,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 &self.context,
                 temp_name.as_str(),
                 llvm::r#type::pointer(i8_array_type, 0),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -1829,17 +1852,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 addressof_op,
                 DenseI32ArrayAttribute::new(&self.context, &[0, 0]),
                 self.llvm_types.i8_ptr_type,
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
             .into();
 
         let undef_struct = string_block
-            .append_operation(llvm::undef(
-                self.llvm_types.struct_type,
-                Location::unknown(&self.context),
-            ))
+            .append_operation(llvm::undef(self.llvm_types.struct_type, location))
             .result(0)
             .unwrap()
             .into();
@@ -1849,7 +1869,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             undef_struct,
             DenseI64ArrayAttribute::new(&self.context, &[0]),
             buffer_gep,
-            Location::unknown(&self.context),
+            location,
         ));
 
         let string_length = string.value.len() as i64;
@@ -1858,7 +1878,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             .append_operation(arith::constant(
                 &self.context,
                 IntegerAttribute::new(self.llvm_types.i64_type, string_length).into(),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -1868,7 +1888,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             .append_operation(arith::constant(
                 &self.context,
                 IntegerAttribute::new(self.llvm_types.i64_type, string_length).into(),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -1879,7 +1899,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             prev_result.result(0).unwrap().into(),
             DenseI64ArrayAttribute::new(&self.context, &[1]),
             length_const,
-            Location::unknown(&self.context),
+            location,
         ));
 
         let last_undef_op = string_block.append_operation(llvm::insert_value(
@@ -1887,12 +1907,12 @@ impl<'c, 'm> Compiler<'c, 'm> {
             prev_result.result(0).unwrap().into(),
             DenseI64ArrayAttribute::new(&self.context, &[2]),
             max_length_const,
-            Location::unknown(&self.context),
+            location,
         ));
 
         string_block.append_operation(llvm::r#return(
             Some(last_undef_op.result(0).unwrap().into()),
-            Location::unknown(&self.context),
+            location,
         ));
 
         region.append_block(string_block);
@@ -1905,7 +1925,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             None,
             self.llvm_types.struct_type,
             region,
-            Location::unknown(&self.context),
+            location,
         ));
 
         let struct_addressof_op = block
@@ -1913,7 +1933,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 &self.context,
                 temp_name.as_str(),
                 self.llvm_types.struct_ptr_type,
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -1931,10 +1951,66 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
-        println!("{:#?}", binary);
+        if binary.is_logical() {
+            return self.compile_logical(block, binary, ctx, mctx);
+        }
 
-        let left_val = self.compile_expr(block, &binary.left, ctx, mctx).unwrap();
-        let right_val = self.compile_expr(block, &binary.right, ctx, mctx).unwrap();
+        let location = self.location(binary.span);
+        let mut left_val = self.compile_expr(block, &binary.left, ctx, mctx)?;
+        let mut right_val = self.compile_expr(block, &binary.right, ctx, mctx)?;
+
+        let left_type = self.node_base_type(&binary.left);
+        let right_type = self.node_base_type(&binary.right);
+        let mut operand_type = left_type.clone();
+
+        if let (Some(left_type), Some(right_type)) = (&left_type, &right_type) {
+            if let Some(float_type) = BaseType::float_promotion(left_type, right_type) {
+                let lhs = self.compile_type_cast(
+                    block,
+                    left_val.unwrap(),
+                    left_type.clone(),
+                    float_type.clone(),
+                    location,
+                );
+                let rhs = self.compile_type_cast(
+                    block,
+                    right_val.unwrap(),
+                    right_type.clone(),
+                    float_type,
+                    location,
+                );
+
+                return Ok(Some(
+                    self.compile_float_binary(block, binary, lhs, rhs, location)?,
+                ));
+            }
+
+            // Integers of different widths are extended to the wider one
+            if let Some(int_type) = BaseType::integer_promotion(left_type, right_type) {
+                left_val = left_val
+                    .map(|value| self.extend_integer(block, value, left_type, &int_type, location));
+                right_val = right_val.map(|value| {
+                    self.extend_integer(block, value, right_type, &int_type, location)
+                });
+                operand_type = Some(int_type);
+            }
+        }
+
+        let is_unsigned = matches!(operand_type, Some(BaseType::Byte | BaseType::Bool));
+
+        if let Some(predicate) = cmpi_predicate(binary.op, is_unsigned) {
+            let operation = arith::cmpi(
+                &self.context,
+                predicate,
+                left_val.unwrap(),
+                right_val.unwrap(),
+                location,
+            );
+
+            return Ok(Some(
+                block.append_operation(operation).result(0).unwrap().into(),
+            ));
+        }
 
         // todo: Hardcoded + op for the moment, match on operator
 
@@ -1943,45 +2019,311 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 block.append_operation(arith::addi(
                     left_val.unwrap().into(),
                     right_val.unwrap().into(),
-                    Location::unknown(&self.context),
+                    location,
                 ))            .result(0).unwrap() .into()
             }
             ['-', '\0', '\0', '\0'] => {
                 block.append_operation(arith::subi(
                     left_val.unwrap().into(),
                     right_val.unwrap().into(),
-                    Location::unknown(&self.context),
+                    location,
                 ))            .result(0).unwrap() .into()
             }
             ['*', '\0', '\0', '\0'] => {
                 block.append_operation(arith::muli(
                     left_val.unwrap().into(),
                     right_val.unwrap().into(),
-                    Location::unknown(&self.context),
+                    location,
                 ))            .result(0).unwrap() .into()
             }
             ['/', '\0', '\0', '\0'] => {
                 block.append_operation(arith::divsi(
                     left_val.unwrap().into(),
                     right_val.unwrap().into(),
-                    Location::unknown(&self.context),
+                    location,
                 ))            .result(0).unwrap() .into()
             }
             ['%', '\0', '\0', '\0'] => {
                 block.append_operation(arith::remsi(
                     left_val.unwrap().into(),
                     right_val.unwrap().into(),
-                    Location::unknown(&self.context),
+                    location,
                 ))            .result(0).unwrap() .into()
             }
-            _ => {
-                panic!("Unhandled binary operator: {:#?}", binary.op)
-            }
+            ['&', '\0', '\0', '\0'] => block
+                .append_operation(arith::andi(left_val.unwrap(), right_val.unwrap(), location))
+                .result(0)
+                .unwrap()
+                .into(),
+            ['|', '\0', '\0', '\0'] => block
+                .append_operation(arith::ori(left_val.unwrap(), right_val.unwrap(), location))
+                .result(0)
+                .unwrap()
+                .into(),
+            ['^', '\0', '\0', '\0'] => block
+                .append_operation(arith::xori(left_val.unwrap(), right_val.unwrap(), location))
+                .result(0)
+                .unwrap()
+                .into(),
+            ['<', '<', '\0', '\0'] => block
+                .append_operation(arith::shli(left_val.unwrap(), right_val.unwrap(), location))
+                .result(0)
+                .unwrap()
+                .into(),
+            // Bytes shift in zeros, the other integers their sign bit
+            ['>', '>', '\0', '\0'] if is_unsigned => block
+                .append_operation(arith::shrui(
+                    left_val.unwrap(),
+                    right_val.unwrap(),
+                    location,
+                ))
+                .result(0)
+                .unwrap()
+                .into(),
+            ['>', '>', '\0', '\0'] => block
+                .append_operation(arith::shrsi(
+                    left_val.unwrap(),
+                    right_val.unwrap(),
+                    location,
+                ))
+                .result(0)
+                .unwrap()
+                .into(),
+            ['*', '*', '\0', '\0'] => block
+                .append_operation(self.pow_operation(
+                    "math.ipowi",
+                    left_val.unwrap(),
+                    right_val.unwrap(),
+                    location,
+                ))
+                .result(0)
+                .unwrap()
+                .into(),
+            _ => return Err("unsupported binary operator"),
         };
 
         return Ok(Some(result));
     }
 
+    /// Converts a number to another number type. Integers are extended, see
+    /// `extend_integer`, or truncated, and converted to and from floats as
+    /// signed, except `Byte`s which are unsigned.
+    fn compile_number_cast<'a>(
+        &self,
+        block: &'a Block<'c>,
+        value: Value<'c, 'a>,
+        from: &BaseType,
+        to: &BaseType,
+        location: Location<'c>,
+    ) -> Value<'c, 'a> {
+        let to_type = self.basetype_to_mlir_type(to);
+        let operation = match (from.integer_width(), to.integer_width()) {
+            (Some(from_width), Some(to_width)) if from_width < to_width => {
+                return self.extend_integer(block, value, from, to, location);
+            }
+            (Some(from_width), Some(to_width)) if from_width > to_width => {
+                arith::trunci(value, to_type, location)
+            }
+            (Some(_), Some(_)) => return value,
+            (Some(_), None) if *from == BaseType::Byte => arith::uitofp(value, to_type, location),
+            (Some(_), None) => arith::sitofp(value, to_type, location),
+            (None, Some(_)) if *to == BaseType::Byte => arith::fptoui(value, to_type, location),
+            (None, Some(_)) => arith::fptosi(value, to_type, location),
+            (None, None) if from == to => return value,
+            (None, None) if *to == BaseType::Float64 => arith::extf(value, to_type, location),
+            (None, None) => arith::truncf(value, to_type, location),
+        };
+
+        block.append_operation(operation).result(0).unwrap().into()
+    }
+
+    /// Extends an integer to a wider integer type, a `Byte` with zeros and the
+    /// other integers with their sign bit.
+    fn extend_integer<'a>(
+        &self,
+        block: &'a Block<'c>,
+        value: Value<'c, 'a>,
+        from: &BaseType,
+        to: &BaseType,
+        location: Location<'c>,
+    ) -> Value<'c, 'a> {
+        if from.integer_width() >= to.integer_width() {
+            return value;
+        }
+
+        let to_type = self.basetype_to_mlir_type(to);
+        let operation = match from {
+            BaseType::Byte => arith::extui(value, to_type, location),
+            _ => arith::extsi(value, to_type, location),
+        };
+
+        block.append_operation(operation).result(0).unwrap().into()
+    }
+
+    /// Compiles `&&` and `||` to an `scf.if` on the left-hand side, which
+    /// only evaluates the right-hand side when the result depends on it.
+    fn compile_logical<'a>(
+        &self,
+        block: &'a Block<'c>,
+        binary: &parser::Binary,
+        ctx: &mut FnCtx<'c, 'a>,
+        mctx: &mut ModuleCtx,
+    ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(binary.span);
+        let left = self.compile_expr(block, &binary.left, ctx, mctx)?.unwrap();
+
+        let right_region = self.compile_branch(
+            std::slice::from_ref(binary.right.as_ref()),
+            Some(&BaseType::Bool),
+            None,
+            ctx,
+            mctx,
+            location,
+        )?;
+
+        // `false && ...` is false and `true || ...` is true
+        let (then_region, else_region) = match binary.op {
+            ['&', '&', '\0', '\0'] => (right_region, self.bool_region(false, location)),
+            _ => (self.bool_region(true, location), right_region),
+        };
+
+        let value = block
+            .append_operation(scf::r#if(
+                left,
+                &[self.llvm_types.i1_type],
+                then_region,
+                else_region,
+                location,
+            ))
+            .result(0)
+            .unwrap()
+            .into();
+
+        Ok(Some(value))
+    }
+
+    fn compile_unary<'a>(
+        &self,
+        block: &'a Block<'c>,
+        unary: &parser::Unary,
+        ctx: &mut FnCtx<'c, 'a>,
+        mctx: &mut ModuleCtx,
+    ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(unary.span);
+        let operand = self
+            .compile_expr(block, &unary.operand, ctx, mctx)?
+            .unwrap();
+        let operand_type = self.node_base_type(&unary.operand);
+
+        let operation = match unary.op {
+            ['-', '\0', '\0', '\0'] if operand_type.as_ref().is_some_and(BaseType::is_float) => {
+                arith::negf(operand, location)
+            }
+            ['-', '\0', '\0', '\0'] => {
+                let zero = block
+                    .append_operation(arith::constant(
+                        &self.context,
+                        IntegerAttribute::new(operand.r#type(), 0).into(),
+                        location,
+                    ))
+                    .result(0)
+                    .unwrap()
+                    .into();
+
+                arith::subi(zero, operand, location)
+            }
+            // `!` flips the bit of a `Bool` and `~` every bit of an integer
+            ['!', '\0', '\0', '\0'] | ['~', '\0', '\0', '\0'] => {
+                let ones = block
+                    .append_operation(arith::constant(
+                        &self.context,
+                        IntegerAttribute::new(operand.r#type(), -1).into(),
+                        location,
+                    ))
+                    .result(0)
+                    .unwrap()
+                    .into();
+
+                arith::xori(operand, ones, location)
+            }
+            _ => return Err("unsupported unary operator"),
+        };
+
+        Ok(Some(
+            block.append_operation(operation).result(0).unwrap().into(),
+        ))
+    }
+
+    fn compile_float_binary<'a>(
+        &self,
+        block: &'a Block<'c>,
+        binary: &parser::Binary,
+        lhs: Value<'c, 'a>,
+        rhs: Value<'c, 'a>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'a>, &'static str> {
+        let operation = if let Some(predicate) = cmpf_predicate(binary.op) {
+            arith::cmpf(&self.context, predicate, lhs, rhs, location)
+        } else {
+            match binary.op {
+                ['+', '\0', '\0', '\0'] => arith::addf(lhs, rhs, location),
+                ['-', '\0', '\0', '\0'] => arith::subf(lhs, rhs, location),
+                ['*', '\0', '\0', '\0'] => arith::mulf(lhs, rhs, location),
+                ['/', '\0', '\0', '\0'] => arith::divf(lhs, rhs, location),
+                ['%', '\0', '\0', '\0'] => arith::remf(lhs, rhs, location),
+                ['*', '*', '\0', '\0'] => self.pow_operation("math.powf", lhs, rhs, location),
+                _ => return Err("bitwise and shift operators only take integers"),
+            }
+        };
+
+        Ok(block.append_operation(operation).result(0).unwrap().into())
+    }
+
+    /// `math.ipowi` or `math.powf`, raising `base` to `exponent` in the type
+    /// of `base`.
+    fn pow_operation(
+        &self,
+        op_name: &str,
+        base: Value<'c, '_>,
+        exponent: Value<'c, '_>,
+        location: Location<'c>,
+    ) -> Operation<'c> {
+        OperationBuilder::new(op_name, location)
+            .add_operands(&[base, exponent])
+            .add_results(&[base.r#type()])
+            .build()
+            .unwrap()
+    }
+
+    fn compile_bool<'a>(
+        &self,
+        block: &'a Block<'c>,
+        node: &parser::Bool,
+    ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(node.span);
+
+        Ok(Some(self.append_i1_constant(block, node.value, location)))
+    }
+
+    fn compile_float<'a>(
+        &self,
+        block: &'a Block<'c>,
+        nb: &parser::Float,
+    ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(nb.span);
+        let value = block
+            .append_operation(arith::constant(
+                &self.context,
+                FloatAttribute::new(&self.context, nb.value, self.llvm_types.f64_type).into(),
+                location,
+            ))
+            .result(0)
+            .unwrap()
+            .into();
+
+        Ok(Some(value))
+    }
+
     fn compile_local_var<'a>(
         &self,
         block: &'a Block<'c>,
@@ -1989,6 +2331,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(lvar.span);
         let lvar_value = match self.get_lvar(&lvar.name, ctx) {
             Some(value) => value,
             None => todo!(),
@@ -2007,7 +2350,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 &self.context,
                 self.get_lvar(&lvar.name, ctx).unwrap(),
                 lvar_type,
-                Location::unknown(&self.context),
+                location,
                 Default::default(),
             ))
             .result(0)
@@ -2022,6 +2365,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         block: &'a Block<'c>,
         const_node: &parser::Const,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(const_node.span);
         let const_type = self
             .parser_result
             .index
@@ -2035,7 +2379,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 &self.context,
                 const_node.name.as_str(),
                 llvm::r#type::pointer(mlir_type, 0),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -2046,7 +2390,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 &self.context,
                 addressof_op,
                 mlir_type,
-                Location::unknown(&self.context),
+                location,
                 Default::default(),
             ))
             .result(0)
@@ -2063,11 +2407,12 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(array_node.span);
         let size = block
             .append_operation(arith::constant(
                 &self.context,
                 IntegerAttribute::new(self.llvm_types.i64_type.clone(), 1).into(),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -2086,7 +2431,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 size,
                 ptr_type,
                 // struct_type,
-                Location::unknown(&self.context),
+                location,
                 Default::default(),
                 // AllocaOptions::new().elem_type(Some(TypeAttribute::new(value.r#type()))),
             ))
@@ -2103,7 +2448,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                     array_alloca,
                     DenseI32ArrayAttribute::new(&self.context, &[0, index as i32]),
                     llvm::r#type::r#pointer(array_item_type, 0),
-                    Location::unknown(&self.context),
+                    location,
                 ))
                 .result(0)
                 .unwrap()
@@ -2114,13 +2459,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 return_val.unwrap(),
                 self.node_base_type(arg).unwrap(),
                 array_node.item_type.clone(),
+                location,
             ));
 
             block.append_operation(llvm::store(
                 &self.context,
                 return_val.unwrap(),
                 gep,
-                Location::unknown(&self.context),
+                location,
                 Default::default(),
             ));
         }
@@ -2148,11 +2494,12 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(build_struct_node.span);
         let size = block
             .append_operation(arith::constant(
                 &self.context,
                 IntegerAttribute::new(self.llvm_types.i64_type.clone(), 1).into(),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -2173,7 +2520,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 size,
                 ptr_type,
                 // struct_type,
-                Location::unknown(&self.context),
+                location,
                 Default::default(),
                 // AllocaOptions::new().elem_type(Some(TypeAttribute::new(value.r#type()))),
             ))
@@ -2192,7 +2539,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                     struct_alloca,
                     DenseI32ArrayAttribute::new(&self.context, &[0, index as i32]),
                     llvm::r#type::r#pointer(return_type, 0),
-                    Location::unknown(&self.context),
+                    location,
                 ))
                 .result(0)
                 .unwrap()
@@ -2203,13 +2550,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 return_val.unwrap(),
                 self.node_base_type(arg).unwrap(),
                 struct_attr_type.clone(),
+                location,
             ));
 
             block.append_operation(llvm::store(
                 &self.context,
                 return_val.unwrap(),
                 gep,
-                Location::unknown(&self.context),
+                location,
                 Default::default(),
             ));
         }
@@ -2237,72 +2585,348 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
-        let location = Location::unknown(&self.context);
-        let index_type = Type::index(&self.context);
+        let location = self.location(loop_node.span);
+        let i1_type = self.llvm_types.i1_type;
+
+        let false_value = self.append_i1_constant(block, false, location);
+        let result_type = loop_node
+            .return_type
+            .as_ref()
+            .map(|base_type| self.basetype_to_mlir_type(base_type));
+
+        let loop_flags = LoopFlags {
+            stop: self.append_alloca(i1_type, ctx.entry_block, location),
+            skip: self.append_alloca(i1_type, ctx.entry_block, location),
+            result: result_type.map(|r#type| self.append_alloca(r#type, ctx.entry_block, location)),
+        };
 
-        let initial = block.append_operation(arith::constant(
+        // A nested loop is reached again on each iteration of the outer one,
+        // `skip` is reset by each of its own iterations
+        block.append_operation(llvm::store(
             &self.context,
-            // IntegerAttribute::new(index_type, 1).into(),
-            IntegerAttribute::new(IntegerType::new(&self.context, 1).into(), 1).into(),
+            false_value,
+            loop_flags.stop,
             location,
+            Default::default(),
         ));
 
-        block.append_operation(scf::r#while(
-            &[],
-            &[],
-            {
-                let before_block = Block::new(&[]);
-
-                // let condition = block.append_operation(arith::constant(
-                //     &self.context,
-                //     IntegerAttribute::new(IntegerType::new(&self.context, 1).into(), 0)
-                //         .into(),
-                //     location,
-                // ));
-
-                // let result = block.append_operation(arith::constant(
-                //     &self.context,
-                //     IntegerAttribute::new(Type::index(&self.context), 42).into(),
-                //     location,
-                // ));
-
-                before_block.append_operation(scf::condition(
-                    // arith::constant( &self.context, IntegerAttribute::new(IntegerType::new(&self.context, 1).into(), 0)).into()
-                    initial.result(0).unwrap().into(),
-                    &[],
+        let before_block = Block::new(&[]);
+        let stopped: Value = before_block
+            .append_operation(llvm::load(
+                &self.context,
+                loop_flags.stop,
+                i1_type,
+                location,
+                Default::default(),
+            ))
+            .result(0)
+            .unwrap()
+            .into();
+        let true_value = self.append_i1_constant(&before_block, true, location);
+
+        let keep_going = match &loop_node.condition {
+            None => before_block
+                .append_operation(arith::xori(stopped, true_value, location))
+                .result(0)
+                .unwrap()
+                .into(),
+            Some(condition) => {
+                // A loop that broke doesn't check its condition again. The
+                // constant is negated along with the condition of an `until`.
+                let stopped_region = self.bool_region(loop_node.until, location);
+
+                let condition_region = self.compile_branch(
+                    std::slice::from_ref(condition.as_ref()),
+                    Some(&BaseType::Bool),
+                    None,
+                    ctx,
+                    mctx,
                     location,
-                ));
+                )?;
+
+                let value: Value = before_block
+                    .append_operation(scf::r#if(
+                        stopped,
+                        &[i1_type],
+                        stopped_region,
+                        condition_region,
+                        location,
+                    ))
+                    .result(0)
+                    .unwrap()
+                    .into();
 
-                let region = Region::new();
-                region.append_block(before_block);
-                region
-            },
-            self.compile_block(&loop_node.body, ctx, mctx)?,
+                if loop_node.until {
+                    before_block
+                        .append_operation(arith::xori(value, true_value, location))
+                        .result(0)
+                        .unwrap()
+                        .into()
+                } else {
+                    value
+                }
+            }
+        };
+
+        before_block.append_operation(scf::condition(keep_going, &[], location));
+
+        let before_region = Region::new();
+        before_region.append_block(before_block);
+
+        let body_region =
+            self.compile_branch(&loop_node.body, None, Some(loop_flags), ctx, mctx, location)?;
+
+        block.append_operation(scf::r#while(&[], &[], before_region, body_region, location));
+
+        let Some(result) = loop_flags.result else {
+            return Ok(None);
+        };
+
+        let value = block
+            .append_operation(llvm::load(
+                &self.context,
+                result,
+                result_type.unwrap(),
+                location,
+                Default::default(),
+            ))
+            .result(0)
+            .unwrap()
+            .into();
+
+        Ok(Some(value))
+    }
+
+    fn compile_break<'a>(
+        &self,
+        block: &'a Block<'c>,
+        break_node: &parser::Break,
+        ctx: &mut FnCtx<'c, 'a>,
+        mctx: &mut ModuleCtx,
+    ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(break_node.span);
+        let loop_flags = self
+            .get_loop_flags(ctx)
+            .ok_or("`break` outside of a loop")?;
+
+        if let (Some(value_node), Some(result)) = (&break_node.value, loop_flags.result) {
+            let value = self.compile_expr(block, value_node, ctx, mctx)?.unwrap();
+
+            block.append_operation(llvm::store(
+                &self.context,
+                value,
+                result,
+                location,
+                Default::default(),
+            ));
+        }
+
+        let true_value = self.append_i1_constant(block, true, location);
+
+        for flag in [loop_flags.stop, loop_flags.skip] {
+            block.append_operation(llvm::store(
+                &self.context,
+                true_value,
+                flag,
+                location,
+                Default::default(),
+            ));
+        }
+
+        Ok(None)
+    }
+
+    fn compile_next<'a>(
+        &self,
+        block: &'a Block<'c>,
+        next_node: &parser::Next,
+        ctx: &FnCtx<'c, 'a>,
+    ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(next_node.span);
+        let loop_flags = self.get_loop_flags(ctx).ok_or("`next` outside of a loop")?;
+
+        let true_value = self.append_i1_constant(block, true, location);
+        block.append_operation(llvm::store(
+            &self.context,
+            true_value,
+            loop_flags.skip,
             location,
+            Default::default(),
         ));
 
         Ok(None)
     }
 
-    fn compile_block(
+    fn compile_if<'a>(
+        &self,
+        block: &'a Block<'c>,
+        if_node: &parser::If,
+        ctx: &mut FnCtx<'c, 'a>,
+        mctx: &mut ModuleCtx,
+    ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(if_node.span);
+        let condition = self
+            .compile_expr(block, &if_node.condition, ctx, mctx)?
+            .unwrap();
+
+        let result_type = if_node.return_type.as_ref();
+        let result_types: Vec<Type<'c>> = result_type
+            .map(|base_type| self.basetype_to_mlir_type(base_type))
+            .into_iter()
+            .collect();
+
+        let then_region =
+            self.compile_branch(&if_node.then_body, result_type, None, ctx, mctx, location)?;
+        // An `if` without an `else` has no value, its else region can be empty
+        let else_region = match &if_node.else_body {
+            Some(body) => self.compile_branch(body, result_type, None, ctx, mctx, location)?,
+            None => Region::new(),
+        };
+
+        let operation = block.append_operation(scf::r#if(
+            condition,
+            &result_types,
+            then_region,
+            else_region,
+            location,
+        ));
+
+        Ok(operation.result(0).ok().map(|result| result.into()))
+    }
+
+    /// Compiles a branch of an `if` or the body of a loop, yielding the value
+    /// of its last expression as the `if`'s type when it has one. A loop body
+    /// gets the loop's flags. The statements after a `break` or `next` are
+    /// nested in an `scf.if`, which skips them once one of them ran.
+    fn compile_branch(
         &self,
-        nodes: &Vec<Node>,
+        nodes: &[Node],
+        result_type: Option<&BaseType>,
+        loop_flags: Option<LoopFlags<'c, 'm>>,
         ctx: &mut FnCtx<'c, 'm>,
         mctx: &mut ModuleCtx,
+        location: Location<'c>,
     ) -> Result<Region<'c>, &'static str> {
         let builder = Block::new(&[]);
+        let debug_scope = ctx.debug_scope.clone();
+
+        if let Some(loop_flags) = loop_flags {
+            // Each iteration starts out running every statement
+            let false_value = self.append_i1_constant(&builder, false, location);
+            builder.append_operation(llvm::store(
+                &self.context,
+                false_value,
+                loop_flags.skip,
+                location,
+                Default::default(),
+            ));
+        }
 
         let mut block_ctx = FnCtx {
             lvars: HashMap::new(),
             lvar_stores: HashMap::new(),
+            entry_block: ctx.entry_block,
             parent_ctx: Some(Box::new(ctx)),
+            debug_scope,
+            loop_flags,
         };
 
-        nodes.iter().for_each(|node| {
-            self.compile_expr(&builder, node, &mut block_ctx, mctx);
-        });
+        let mut last_value = None;
+        let mut last_node = nodes.last();
+
+        for (index, node) in nodes.iter().enumerate() {
+            last_value = self.compile_expr(&builder, node, &mut block_ctx, mctx)?;
 
-        builder.append_operation(scf::r#yield(&[], Location::unknown(&self.context)));
+            let rest = &nodes[index + 1..];
+            if rest.is_empty() || !skips_rest(node) {
+                continue;
+            }
+
+            let flags = self.get_loop_flags(&block_ctx).unwrap();
+            let skipping = builder
+                .append_operation(llvm::load(
+                    &self.context,
+                    flags.skip,
+                    self.llvm_types.i1_type,
+                    location,
+                    Default::default(),
+                ))
+                .result(0)
+                .unwrap()
+                .into();
+            let true_value = self.append_i1_constant(&builder, true, location);
+            let running = builder
+                .append_operation(arith::xori(skipping, true_value, location))
+                .result(0)
+                .unwrap()
+                .into();
+
+            let result_types: Vec<Type<'c>> = result_type
+                .map(|base_type| self.basetype_to_mlir_type(base_type))
+                .into_iter()
+                .collect();
+
+            let rest_region =
+                self.compile_branch(rest, result_type, None, &mut block_ctx, mctx, location)?;
+
+            // Nothing uses the value of a skipped branch, it's left undefined
+            let skipped_block = Block::new(&[]);
+            let undefined: Vec<Value> = result_types
+                .iter()
+                .map(|r#type| {
+                    skipped_block
+                        .append_operation(llvm::undef(*r#type, location))
+                        .result(0)
+                        .unwrap()
+                        .into()
+                })
+                .collect();
+            skipped_block.append_operation(scf::r#yield(&undefined, location));
+
+            let skipped_region = Region::new();
+            skipped_region.append_block(skipped_block);
+
+            let operation = builder.append_operation(scf::r#if(
+                running,
+                &result_types,
+                rest_region,
+                skipped_region,
+                location,
+            ));
+
+            // The nested branch already yields the value as the `if`'s type
+            last_value = operation.result(0).ok().map(|result| result.into());
+            last_node = None;
+            break;
+        }
+
+        let yielded = match (result_type, last_value, last_node) {
+            (Some(result_type), Some(value), Some(node)) => {
+                let value_type = match node {
+                    Node::AssignLocalVar(assign_node) => self.node_base_type(&assign_node.value),
+                    node => self.node_base_type(node),
+                };
+
+                match value_type {
+                    Some(value_type)
+                        if value.r#type() != self.basetype_to_mlir_type(result_type) =>
+                    {
+                        vec![self.compile_type_cast(
+                            &builder,
+                            value,
+                            value_type,
+                            result_type.clone(),
+                            location,
+                        )]
+                    }
+                    _ => vec![value],
+                }
+            }
+            (Some(_), Some(value), None) => vec![value],
+            _ => vec![],
+        };
+
+        builder.append_operation(scf::r#yield(&yielded, location));
 
         let region = Region::new();
         region.append_block(builder);
@@ -2348,6 +2972,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(assignment.span);
         let return_val = match self.compile_expr(&block, &assignment.value, ctx, mctx) {
             Ok(ret_val) => ret_val,
             Err(e) => return Err(e),
@@ -2380,6 +3005,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Node::SelfRef(_) => todo!(),
             Node::Send(_) => todo!(),
             Node::Trait(_) => todo!(),
+            Node::Unary(_) => todo!(),
             Node::AssignAttribute(_) => todo!(),
             Node::AssignAttributeAccess(_) => todo!(),
             Node::Const(_) => todo!(),
@@ -2389,6 +3015,11 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Node::BuildStruct(_) => todo!(),
             Node::Struct(_) => todo!(),
             Node::FnRef(_) => todo!(),
+            Node::Float(_) => todo!(),
+            Node::Bool(_) => todo!(),
+            Node::If(_) => todo!(),
+            Node::Break(_) => todo!(),
+            Node::Next(_) => todo!(),
         };
 
         // let sret_value = ctx.lvar_stores.get(&asgn_attr.name);
@@ -2399,7 +3030,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 class_ptr_ref,
                 DenseI32ArrayAttribute::new(&self.context, &[0, assignment.access.index]),
                 llvm::r#type::r#pointer(return_val.unwrap().r#type(), 0),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -2409,7 +3040,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             &self.context,
             return_val.unwrap(),
             gep,
-            Location::unknown(&self.context),
+            location,
             Default::default(),
         ));
 
@@ -2534,11 +3165,9 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(asgn_attr.span);
         // let sret_value = ctx.lvar_stores.get(&asgn_attr.name);
 
-        println!("lvar: {:#?}", asgn_attr.value.as_ref());
-        println!("{:#?}", ctx.lvars);
-
         let return_val = match asgn_attr.value.as_ref() {
             // Node::LocalVar(lvar) => match ctx.lvar_stores.get(&lvar.name) {
             //     Some(value) => *value,
@@ -2577,7 +3206,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 DenseI32ArrayAttribute::new(&self.context, &[0, asgn_attr.index]),
                 llvm::r#type::r#pointer(return_val.r#type(), 0),
                 // return_val.r#type(),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -2587,7 +3216,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
             &self.context,
             return_val,
             gep,
-            Location::unknown(&self.context),
+            location,
             Default::default(),
         ));
 
@@ -2601,6 +3230,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
         ctx: &mut FnCtx<'c, 'a>,
         mctx: &mut ModuleCtx,
     ) -> Result<Option<Value<'c, 'a>>, &'static str> {
+        let location = self.location(asgn_lvar.span);
         let return_val = match self.compile_expr(&block, &asgn_lvar.value, ctx, mctx) {
             Ok(ret_val) => ret_val,
             Err(e) => return Err(e),
@@ -2633,9 +3263,6 @@ impl<'c, 'm> Compiler<'c, 'm> {
         //     Node::AssignConstant(_) => todo!(),
         // };
 
-        println!("asgn_lvar");
-        println!("{:#?}", asgn_lvar);
-
         match &return_type {
             Some(base_type) => {
                 match base_type {
@@ -2649,6 +3276,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
                             ctx.lvars
                                 .insert(asgn_lvar.name.clone(), return_val.unwrap());
                             // ctx.lvar_stores.insert(asgn_lvar.name.clone(), return_val.unwrap());
+                            self.declare_local_var(
+                                block,
+                                ctx,
+                                return_val.unwrap(),
+                                false,
+                                asgn_lvar,
+                                base_type,
+                            );
                             return Ok(return_val);
                         }
                     }
@@ -2656,11 +3291,22 @@ impl<'c, 'm> Compiler<'c, 'm> {
                     BaseType::Int16 => {}
                     BaseType::Int32 => {}
                     BaseType::Int64 => {}
+                    BaseType::Bool => {}
+                    BaseType::Float32 => {}
+                    BaseType::Float64 => {}
                     BaseType::Array(_, _) => {}
                     BaseType::Struct(_) => {
                         ctx.lvars
                             .insert(asgn_lvar.name.clone(), return_val.unwrap());
                         // ctx.lvar_stores.insert(asgn_lvar.name.clone(), return_val.unwrap());
+                        self.declare_local_var(
+                            block,
+                            ctx,
+                            return_val.unwrap(),
+                            false,
+                            asgn_lvar,
+                            base_type,
+                        );
                         return Ok(return_val);
                     }
                     BaseType::FnRef => {}
@@ -2669,55 +3315,150 @@ impl<'c, 'm> Compiler<'c, 'm> {
             None => todo!(),
         }
 
-        let ptr = self.append_alloca_store(return_val.unwrap(), block);
+        // Reassigning a variable stores into it, so that a loop's condition
+        // sees what its body assigned
+        if let Some(ptr) = self.get_lvar_store(&asgn_lvar.name, ctx) {
+            let value = return_val.unwrap();
+
+            if ptr.r#type() == r#type::pointer(value.r#type(), 0) {
+                block.append_operation(llvm::store(
+                    &self.context,
+                    value,
+                    ptr,
+                    location,
+                    Default::default(),
+                ));
+
+                return Ok(return_val);
+            }
+        }
+
+        let value = return_val.unwrap();
+        let ptr = self.append_alloca(value.r#type(), ctx.entry_block, location);
+        block.append_operation(llvm::store(
+            &self.context,
+            value,
+            ptr,
+            location,
+            Default::default(),
+        ));
         ctx.lvars.insert(asgn_lvar.name.clone(), ptr);
         ctx.lvar_stores.insert(asgn_lvar.name.clone(), ptr);
 
+        if let Some(base_type) = &return_type {
+            self.declare_local_var(block, ctx, ptr, true, asgn_lvar, base_type);
+        }
+
         Ok(return_val)
     }
 
-    fn append_alloca_store<'a>(&self, value: Value<'m, '_>, block: &'a Block<'c>) -> Value<'c, 'a> {
+    fn declare_local_var<'a>(
+        &self,
+        block: &'a Block<'c>,
+        ctx: &FnCtx<'c, 'a>,
+        value: Value<'c, 'a>,
+        is_address: bool,
+        asgn_lvar: &parser::AssignLocalVar,
+        base_type: &BaseType,
+    ) {
+        let variable = parser::Arg {
+            name: asgn_lvar.name.clone(),
+            return_type: base_type.clone(),
+            span: asgn_lvar.span,
+        };
+
+        self.declare_variable(block, ctx, value, is_address, &variable, None);
+    }
+
+    fn append_alloca_store<'a>(
+        &self,
+        value: Value<'m, '_>,
+        block: &'a Block<'c>,
+        location: Location<'c>,
+    ) -> Value<'c, 'a> {
+        let ptr = self.append_alloca(value.r#type(), block, location);
+
+        block.append_operation(llvm::store(
+            &self.context,
+            value,
+            ptr,
+            location,
+            Default::default(),
+        ));
+
+        ptr
+    }
+
+    fn append_alloca<'a>(
+        &self,
+        r#type: Type<'c>,
+        block: &'a Block<'c>,
+        location: Location<'c>,
+    ) -> Value<'c, 'a> {
         let size = block
             .append_operation(arith::constant(
                 &self.context,
                 IntegerAttribute::new(self.llvm_types.i64_type.clone(), 1).into(),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
             .into();
 
-        let ptr_type = r#type::pointer(value.r#type(), 0);
-        let ptr = block
+        let ptr_type = r#type::pointer(r#type, 0);
+        block
             .append_operation(llvm::alloca(
                 &self.context,
                 size,
                 ptr_type,
-                Location::unknown(&self.context),
+                location,
                 Default::default(),
                 // AllocaOptions::new().elem_type(Some(TypeAttribute::new(value.r#type()))),
             ))
             .result(0)
             .unwrap()
-            .into();
+            .into()
+    }
 
-        block.append_operation(llvm::store(
-            &self.context,
-            value,
-            ptr,
-            Location::unknown(&self.context),
-            Default::default(),
-        ));
+    /// A region of an `scf.if` that yields a constant `Bool`.
+    fn bool_region(&self, value: bool, location: Location<'c>) -> Region<'c> {
+        let block = Block::new(&[]);
+        let constant = self.append_i1_constant(&block, value, location);
+        block.append_operation(scf::r#yield(&[constant], location));
 
-        ptr
+        let region = Region::new();
+        region.append_block(block);
+        region
+    }
+
+    fn append_i1_constant<'a>(
+        &self,
+        block: &'a Block<'c>,
+        value: bool,
+        location: Location<'c>,
+    ) -> Value<'c, 'a> {
+        block
+            .append_operation(arith::constant(
+                &self.context,
+                IntegerAttribute::new(self.llvm_types.i1_type, value as i64).into(),
+                location,
+            ))
+            .result(0)
+            .unwrap()
+            .into()
     }
 
-    fn append_alloca_class<'a>(&self, class_type: Type<'m>, block: &'a Block<'c>) -> Value<'c, 'a> {
+    fn append_alloca_class<'a>(
+        &self,
+        class_type: Type<'m>,
+        block: &'a Block<'c>,
+        location: Location<'c>,
+    ) -> Value<'c, 'a> {
         let size = block
             .append_operation(arith::constant(
                 &self.context,
                 IntegerAttribute::new(self.llvm_types.i64_type.clone(), 1).into(),
-                Location::unknown(&self.context),
+                location,
             ))
             .result(0)
             .unwrap()
@@ -2730,7 +3471,7 @@ impl<'c, 'm> Compiler<'c, 'm> {
                 &self.context,
                 size,
                 ptr_type,
-                Location::unknown(&self.context),
+                location,
                 Default::default(),
                 // AllocaOptions::new().elem_type(Some(TypeAttribute::new(value.r#type()))),
             ))
@@ -2764,6 +3505,14 @@ impl<'c, 'm> Compiler<'c, 'm> {
         }
     }
 
+    fn get_loop_flags<'a>(&self, ctx: &FnCtx<'c, 'a>) -> Option<LoopFlags<'c, 'a>> {
+        match (ctx.loop_flags, &ctx.parent_ctx) {
+            (Some(loop_flags), _) => Some(loop_flags),
+            (None, Some(parent_ctx)) => self.get_loop_flags(parent_ctx),
+            (None, None) => None,
+        }
+    }
+
     fn get_lvar_store<'a>(&self, key: &String, ctx: &FnCtx<'c, 'a>) -> Option<Value<'c, 'a>> {
         if let Some(value) = ctx.lvar_stores.get(key) {
             Some(value.clone())
@@ -2797,11 +3546,29 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Node::AssignAttributeAccess(_) => todo!(),
             Node::AssignLocalVar(_) => todo!(),
             Node::Attribute(_) => todo!(),
-            Node::Binary(_) => {
-                // todo: hardcoded to int
-                Some(BaseType::Int64)
+            Node::Binary(binary) if binary.is_comparison() || binary.is_logical() => {
+                Some(BaseType::Bool)
+            }
+            Node::Binary(binary) => {
+                let number_type = match (
+                    self.node_base_type(&binary.left),
+                    self.node_base_type(&binary.right),
+                ) {
+                    (Some(left), Some(right)) => BaseType::float_promotion(&left, &right)
+                        .or_else(|| BaseType::integer_promotion(&left, &right)),
+                    _ => None,
+                };
+
+                // todo: hardcoded to int unless both sides are numbers
+                number_type.or(Some(BaseType::Int64))
             },
-            Node::Call(call_node) => call_node.return_type.clone(),
+            Node::Call(call_node) => call_node.return_type.clone().or_else(|| {
+                self.parser_result
+                    .index
+                    .fn_prototype_index
+                    .get(&call_node.fn_name)
+                    .and_then(|prototype| prototype.return_type.clone())
+            }),
             Node::Class(_) => todo!(),
             Node::Const(const_node) => {
                 let entry = self
@@ -2815,12 +3582,18 @@ impl<'c, 'm> Compiler<'c, 'm> {
             Node::DefE(_) => todo!(),
             Node::Impl(_) => todo!(),
             Node::Int(_) => Some(BaseType::Int64),
+            Node::Bool(_) => Some(BaseType::Bool),
+            Node::Float(_) => Some(BaseType::Float64),
+            Node::If(if_node) => if_node.return_type.clone(),
             Node::LocalVar(lvar) => lvar.return_type.clone(),
-            Node::Loop(_) => todo!(),
+            Node::Loop(loop_node) => loop_node.return_type.clone(),
+            Node::Break(_) | Node::Next(_) => None,
             Node::Module(_) => todo!(),
             Node::Ret(_) => todo!(),
             Node::SelfRef(self_ref) => Some(self_ref.return_type.clone()),
             Node::Trait(_) => todo!(),
+            Node::Unary(unary) if unary.op[0] == '!' => Some(BaseType::Bool),
+            Node::Unary(unary) => self.node_base_type(&unary.operand),
             Node::AssignConstant(_) => todo!(),
             Node::FnRef(_) => Some(BaseType::FnRef),
         }
@@ -2830,9 +3603,6 @@ impl<'c, 'm> Compiler<'c, 'm> {
         match return_type {
             BaseType::Int => self.llvm_types.i64_type.into(),
             BaseType::Class(name) => {
-                println!("class_name {:#?}", name);
-
-                println!("{:#?}", name);
                 match self.class_type_index.get(name) {
                     Some(struct_type) => llvm::r#type::r#pointer(*struct_type, 0),
                     None => self.struct_type_index.get(name).unwrap().clone(),
@@ -2843,6 +3613,9 @@ impl<'c, 'm> Compiler<'c, 'm> {
             BaseType::Int16 => self.llvm_types.i16_type.into(),
             BaseType::Int32 => self.llvm_types.i32_type.into(),
             BaseType::Int64 => self.llvm_types.i64_type.into(),
+            BaseType::Bool => self.llvm_types.i1_type,
+            BaseType::Float32 => self.llvm_types.f32_type,
+            BaseType::Float64 => self.llvm_types.f64_type,
             BaseType::Array(length, base_type) => {
                 llvm::r#type::array(self.basetype_to_mlir_type(base_type), *length as u32)
             }
@@ -2857,6 +3630,68 @@ impl<'c, 'm> Compiler<'c, 'm> {
     }
 }
 
+/// The symbol a function is emitted as.
+fn symbol_name(node: &parser::Def) -> &str {
+    let takes_args = !node.prototype.args.is_empty();
+
+    if node.main_fn && node.prototype.name == "main" && takes_args {
+        MAIN_WITH_ARGS
+    } else {
+        node.prototype.name.as_str()
+    }
+}
+
+/// Whether a statement can `break` or `next`, which skips the statements
+/// after it. Loops handle their own.
+fn skips_rest(node: &Node) -> bool {
+    match node {
+        Node::Break(_) | Node::Next(_) => true,
+        Node::AssignLocalVar(assign_node) => skips_rest(&assign_node.value),
+        Node::If(if_node) => if_node
+            .then_body
+            .iter()
+            .chain(if_node.else_body.iter().flatten())
+            .any(skips_rest),
+        _ => false,
+    }
+}
+
+/// The `arith.cmpi` predicate of a comparison operator. Bytes and `Bool`s
+/// compare unsigned, the other integers signed.
+fn cmpi_predicate(op: [char; 4], is_unsigned: bool) -> Option<arith::CmpiPredicate> {
+    let predicate = match (op, is_unsigned) {
+        (['=', '=', '\0', '\0'], _) => arith::CmpiPredicate::Eq,
+        (['!', '=', '\0', '\0'], _) => arith::CmpiPredicate::Ne,
+        (['<', '\0', '\0', '\0'], false) => arith::CmpiPredicate::Slt,
+        (['<', '\0', '\0', '\0'], true) => arith::CmpiPredicate::Ult,
+        (['>', '\0', '\0', '\0'], false) => arith::CmpiPredicate::Sgt,
+        (['>', '\0', '\0', '\0'], true) => arith::CmpiPredicate::Ugt,
+        (['<', '=', '\0', '\0'], false) => arith::CmpiPredicate::Sle,
+        (['<', '=', '\0', '\0'], true) => arith::CmpiPredicate::Ule,
+        (['>', '=', '\0', '\0'], false) => arith::CmpiPredicate::Sge,
+        (['>', '=', '\0', '\0'], true) => arith::CmpiPredicate::Uge,
+        _ => return None,
+    };
+
+    Some(predicate)
+}
+
+/// The `arith.cmpf` predicate of a comparison operator. Comparing with a NaN
+/// is false, except for `!=`.
+fn cmpf_predicate(op: [char; 4]) -> Option<arith::CmpfPredicate> {
+    let predicate = match op {
+        ['=', '=', '\0', '\0'] => arith::CmpfPredicate::Oeq,
+        ['!', '=', '\0', '\0'] => arith::CmpfPredicate::Une,
+        ['<', '\0', '\0', '\0'] => arith::CmpfPredicate::Olt,
+        ['>', '\0', '\0', '\0'] => arith::CmpfPredicate::Ogt,
+        ['<', '=', '\0', '\0'] => arith::CmpfPredicate::Ole,
+        ['>', '=', '\0', '\0'] => arith::CmpfPredicate::Oge,
+        _ => return None,
+    };
+
+    Some(predicate)
+}
+
 fn basetype_to_mlir_type<'c>(llvm_types: LlvmTypes<'c>, return_type: &BaseType) -> Type<'c> {
     match return_type {
         // Note Class is an opaque pointer here, but is a pointer to a struct in
@@ -2872,6 +3707,9 @@ fn basetype_to_mlir_type<'c>(llvm_types: LlvmTypes<'c>, return_type: &BaseType)
         BaseType::Int16 => llvm_types.i16_type.into(),
         BaseType::Int32 => llvm_types.i32_type.into(),
         BaseType::Int64 => llvm_types.i64_type.into(),
+        BaseType::Bool => llvm_types.i1_type,
+        BaseType::Float32 => llvm_types.f32_type,
+        BaseType::Float64 => llvm_types.f64_type,
         BaseType::Void => todo!(),
         BaseType::Struct(_) => todo!(),
         // BaseType::FnRef => { llvm_types.fn_ptr },
@@ -2891,6 +3729,9 @@ pub fn pajama_class_name(base_type: &BaseType) -> String {
         BaseType::Int64 => "Int64".to_string(),
         BaseType::Void => "".to_string(),
         BaseType::Struct(_) => "Struct".to_string(),
+        BaseType::Bool => "Bool".to_string(),
+        BaseType::Float32 => "Float32".to_string(),
+        BaseType::Float64 => "Float64".to_string(),
         BaseType::FnRef => "FnRef".to_string(),
     }
 }
@@ -0,0 +1,118 @@
+use crate::parser::BaseType;
+use crate::source::{FileId, SourceMap};
+
+/// Builds the LLVM dialect debug info attributes, as text for
+/// `Attribute::parse`. Codegen fuses a `#llvm.di_subprogram` into the
+/// location of every function and declares local variables with
+/// `#llvm.di_local_variable`s, LLVM turns them into DWARF when the module is
+/// translated, for the JIT and for object files alike.
+#[derive(Debug, Clone)]
+pub struct DebugInfo {
+    directory: String,
+    compile_unit_file: String,
+    is_optimized: bool,
+}
+
+impl DebugInfo {
+    pub fn new(source_map: &SourceMap, is_optimized: bool) -> DebugInfo {
+        let directory = std::env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|_| ".".to_string());
+
+        DebugInfo {
+            directory,
+            compile_unit_file: source_map
+                .file_name(FileId(0))
+                .unwrap_or("<input>")
+                .to_string(),
+            is_optimized,
+        }
+    }
+
+    pub fn subprogram(
+        &self,
+        file_name: &str,
+        name: &str,
+        linkage_name: &str,
+        line: usize,
+    ) -> String {
+        let file = self.file(file_name);
+
+        format!(
+            "#llvm.di_subprogram<compileUnit = {}, scope = {}, name = {:?}, linkageName = {:?}, \
+             file = {}, line = {}, scopeLine = {}, subprogramFlags = Definition>",
+            self.compile_unit(),
+            file,
+            name,
+            linkage_name,
+            file,
+            line,
+            line
+        )
+    }
+
+    /// A local variable of the function `subprogram`, `arg` is the position
+    /// of the parameter it holds starting at 1.
+    pub fn local_variable(
+        &self,
+        subprogram: &str,
+        file_name: &str,
+        name: &str,
+        line: usize,
+        arg: Option<usize>,
+        base_type: &BaseType,
+    ) -> String {
+        let arg = match arg {
+            Some(arg) => format!("arg = {}, ", arg),
+            None => String::new(),
+        };
+
+        format!(
+            "#llvm.di_local_variable<scope = {}, name = {:?}, file = {}, line = {}, {}type = {}>",
+            subprogram,
+            name,
+            self.file(file_name),
+            line,
+            arg,
+            basic_type(base_type)
+        )
+    }
+
+    fn file(&self, file_name: &str) -> String {
+        format!("#llvm.di_file<{:?} in {:?}>", file_name, self.directory)
+    }
+
+    fn compile_unit(&self) -> String {
+        format!(
+            "#llvm.di_compile_unit<sourceLanguage = DW_LANG_C, file = {}, producer = \"pajama\", \
+             isOptimized = {}, emissionKind = Full>",
+            self.file(&self.compile_unit_file),
+            self.is_optimized
+        )
+    }
+}
+
+/// Numbers and `Bool`s are described as themselves, everything that's passed around by
+/// pointer as an address named after its type.
+fn basic_type(base_type: &BaseType) -> String {
+    let (name, size, encoding) = match base_type {
+        BaseType::Byte => ("Byte".to_string(), 8, "DW_ATE_unsigned_char"),
+        BaseType::Int16 => ("Int16".to_string(), 16, "DW_ATE_signed"),
+        BaseType::Int32 => ("Int32".to_string(), 32, "DW_ATE_signed"),
+        BaseType::Int => ("Int".to_string(), 64, "DW_ATE_signed"),
+        BaseType::Int64 => ("Int64".to_string(), 64, "DW_ATE_signed"),
+        BaseType::Bool => ("Bool".to_string(), 8, "DW_ATE_boolean"),
+        BaseType::Float32 => ("Float32".to_string(), 32, "DW_ATE_float"),
+        BaseType::Float64 => ("Float64".to_string(), 64, "DW_ATE_float"),
+        BaseType::Array(_, _) => ("Array".to_string(), 64, "DW_ATE_address"),
+        BaseType::BytePtr => ("BytePtr".to_string(), 64, "DW_ATE_address"),
+        BaseType::Class(name) | BaseType::Struct(name) => (name.clone(), 64, "DW_ATE_address"),
+        BaseType::FnRef => ("FnRef".to_string(), 64, "DW_ATE_address"),
+        BaseType::Void => ("Void".to_string(), 64, "DW_ATE_address"),
+    };
+
+    format!(
+        "#llvm.di_basic_type<tag = DW_TAG_base_type, name = {:?}, sizeInBits = {}, encoding = {}>",
+        name, size, encoding
+    )
+}
@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// A compiler phase whose output can be dumped with `--dump=<phase>,...`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpPhase {
+    Tokens,
+    Ast,
+    TypedAst,
+    Mlir,
+    LlvmMlir,
+}
+
+impl DumpPhase {
+    pub fn from_name(name: &str) -> Option<DumpPhase> {
+        match name {
+            "tokens" => Some(DumpPhase::Tokens),
+            "ast" => Some(DumpPhase::Ast),
+            "typed-ast" => Some(DumpPhase::TypedAst),
+            "mlir" => Some(DumpPhase::Mlir),
+            "llvm-mlir" => Some(DumpPhase::LlvmMlir),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DumpPhase::Tokens => "tokens",
+            DumpPhase::Ast => "ast",
+            DumpPhase::TypedAst => "typed-ast",
+            DumpPhase::Mlir => "mlir",
+            DumpPhase::LlvmMlir => "llvm-mlir",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum DumpSink {
+    #[default]
+    Stderr,
+    File(PathBuf),
+}
+
+/// Which phases to dump and where to. Nothing is dumped by default.
+#[derive(Debug, Clone, Default)]
+pub struct DumpOptions {
+    pub phases: Vec<DumpPhase>,
+    pub sink: DumpSink,
+}
+
+/// Writes phase dumps to the sink. A file sink is created on the first dump,
+/// so a compilation that dumps nothing leaves no file behind.
+pub struct Dumper {
+    options: DumpOptions,
+    writer: RefCell<Option<Box<dyn Write>>>,
+}
+
+impl Dumper {
+    pub fn new(options: DumpOptions) -> Dumper {
+        Dumper {
+            options,
+            writer: RefCell::new(None),
+        }
+    }
+
+    pub fn enabled(&self, phase: DumpPhase) -> bool {
+        self.options.phases.contains(&phase)
+    }
+
+    /// Dumps the text from `render` when `phase` is enabled, `render` isn't
+    /// called otherwise.
+    pub fn dump(&self, phase: DumpPhase, render: impl FnOnce() -> String) -> io::Result<()> {
+        if !self.enabled(phase) {
+            return Ok(());
+        }
+
+        let mut writer = self.writer.borrow_mut();
+
+        if writer.is_none() {
+            *writer = Some(match &self.options.sink {
+                DumpSink::Stderr => Box::new(io::stderr()),
+                DumpSink::File(path) => Box::new(File::create(path)?),
+            });
+        }
+
+        let writer = writer.as_mut().unwrap();
+
+        writeln!(writer, "// ----- {} -----", phase.name())?;
+        writeln!(writer, "{}", render())?;
+        writer.flush()
+    }
+}
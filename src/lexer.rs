@@ -1,26 +1,59 @@
 use std::{iter::Peekable, str::Chars};
 
-#[derive(Debug, Clone)]
+use crate::source::SourceMap;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct TokenPosition {
     line: usize,
     start_column: usize,
     end_column: usize,
 }
 
+impl TokenPosition {
+    pub fn new(line: usize, start_column: usize, end_column: usize) -> TokenPosition {
+        TokenPosition {
+            line,
+            start_column,
+            end_column,
+        }
+    }
+
+    /// The line of the token, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column of the first character of the token, starting at 1.
+    pub fn start_column(&self) -> usize {
+        self.start_column
+    }
+
+    /// The column of the last character of the token.
+    pub fn end_column(&self) -> usize {
+        self.end_column
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Token {
     Arrow,
     Assign,
     Attribute(TokenPosition, String),
     Binary,
+    Bool(TokenPosition, bool),
+    Break,
     Class,
     Comma,
     Const(TokenPosition, String),
     Def,
     DefE,
     Dot,
+    Else,
+    Elsif,
     End,
+    Float(TokenPosition, f64),
     Ident(TokenPosition, String),
+    If,
     Illegal(TokenPosition, String),
     Impl,
     LCurlyBrace,
@@ -28,6 +61,7 @@ pub enum Token {
     LParen,
     LSquareBrace,
     NewLine(usize),
+    Next,
     Number(TokenPosition, u64),
     Op([char; 4]),
     RCurlyBrace,
@@ -40,9 +74,77 @@ pub enum Token {
     Comment(TokenPosition, String),
     Trait,
     Unary,
+    Until,
     Struct,
+    While,
+}
+
+impl Token {
+    /// Describes the token for diagnostics, e.g. "`end`" or "identifier `foo`".
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Arrow => "`->`".to_string(),
+            Token::Assign => "`=`".to_string(),
+            Token::Attribute(_, name) => format!("attribute `@{}`", name),
+            Token::Binary => "`binary`".to_string(),
+            Token::Bool(_, value) => format!("`{}`", value),
+            Token::Break => "`break`".to_string(),
+            Token::Class => "`class`".to_string(),
+            Token::Comma => "`,`".to_string(),
+            Token::Comment(_, _) => "a comment".to_string(),
+            Token::Const(_, name) => format!("constant `{}`", name),
+            Token::Def => "`def`".to_string(),
+            Token::DefE => "`def_e`".to_string(),
+            Token::Dot => "`.`".to_string(),
+            Token::Else => "`else`".to_string(),
+            Token::Elsif => "`elsif`".to_string(),
+            Token::End => "`end`".to_string(),
+            Token::Float(_, number) => format!("number `{}`", number),
+            Token::Ident(_, name) => format!("identifier `{}`", name),
+            Token::If => "`if`".to_string(),
+            Token::Illegal(_, ch) => format!("unexpected character `{}`", ch),
+            Token::Impl => "`impl`".to_string(),
+            Token::LCurlyBrace => "`{`".to_string(),
+            Token::Loop => "`loop`".to_string(),
+            Token::LParen => "`(`".to_string(),
+            Token::LSquareBrace => "`[`".to_string(),
+            Token::NewLine(_) => "a new line".to_string(),
+            Token::Next => "`next`".to_string(),
+            Token::Number(_, number) => format!("number `{}`", number),
+            Token::Op(op) => format!(
+                "operator `{}`",
+                op.iter().filter(|&&ch| ch != '\0').collect::<String>()
+            ),
+            Token::RCurlyBrace => "`}`".to_string(),
+            Token::Ret => "`ret`".to_string(),
+            Token::RParen => "`)`".to_string(),
+            Token::RSquareBrace => "`]`".to_string(),
+            Token::SelfRef => "`self`".to_string(),
+            Token::Space(_) => "a space".to_string(),
+            Token::StringLiteral(_, _) => "a string literal".to_string(),
+            Token::Struct => "`struct`".to_string(),
+            Token::Trait => "`trait`".to_string(),
+            Token::Unary => "`unary`".to_string(),
+            Token::Until => "`until`".to_string(),
+            Token::While => "`while`".to_string(),
+        }
+    }
+}
+
+/// The tokens of a source and where each one is, `positions[i]` is the
+/// position of `tokens[i]`. The source is kept to render diagnostics, and the
+/// source map to trace positions back to the files it was made of.
+#[derive(Debug, Clone)]
+pub struct TokenStream {
+    pub source: String,
+    pub source_map: SourceMap,
+    pub tokens: Vec<Token>,
+    pub positions: Vec<TokenPosition>,
 }
 
+/// The operators of more than one character.
+const OPERATORS: [&str; 9] = ["**", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>"];
+
 pub struct Lexer<'a> {
     input: &'a str,
     chars: Box<Peekable<Chars<'a>>>,
@@ -63,13 +165,59 @@ impl Lexer<'_> {
     }
 
     pub fn tokenize(&mut self) -> Vec<Token> {
+        self.token_stream().tokens
+    }
+
+    /// Lexes the whole input, keeping the position of every token.
+    pub fn token_stream(&mut self) -> TokenStream {
         let mut tokens = vec![];
+        let mut positions = vec![];
+
+        loop {
+            let line = self.line_pos;
+            let start_column = self.column_pos + 1;
+
+            let Some(token) = self.lex() else {
+                break;
+            };
+
+            // Tokens that end a line point at where the line ended
+            let end_column = if self.line_pos == line {
+                self.column_pos
+            } else {
+                start_column
+            };
 
-        while let Some(token) = self.lex() {
             tokens.push(token);
+            positions.push(TokenPosition::new(line, start_column, end_column));
+        }
+
+        TokenStream {
+            source: self.input.to_string(),
+            source_map: SourceMap::default(),
+            tokens,
+            positions,
         }
+    }
+
+    /// Whether the character `offset` characters after the next one is a
+    /// digit.
+    fn digit_follows(&self, offset: usize) -> bool {
+        let mut chars = (*self.chars).clone();
 
-        tokens
+        chars.nth(offset).is_some_and(|ch| ch.is_ascii_digit())
+    }
+
+    /// Whether the next character starts an exponent, as in `1e9` or `1e-9`.
+    fn exponent_follows(&self) -> bool {
+        let mut chars = (*self.chars).clone();
+        chars.next();
+
+        match chars.next() {
+            Some('0'..='9') => true,
+            Some('+' | '-') => chars.next().is_some_and(|ch| ch.is_ascii_digit()),
+            _ => false,
+        }
     }
 
     pub fn lex(&mut self) -> Option<Token> {
@@ -112,6 +260,12 @@ impl Lexer<'_> {
 
                 token_pos.end_column = self.column_pos;
 
+                // The comment takes the new line that ends it
+                if src[start..pos].ends_with('\n') {
+                    self.line_pos += 1;
+                    self.column_pos = 0;
+                }
+
                 Token::Comment(token_pos, src[start..pos].to_string())
             }
             ' ' => {
@@ -238,33 +392,52 @@ impl Lexer<'_> {
                     end_column: self.column_pos,
                 };
 
+                let mut is_float = false;
+                let mut has_exponent = false;
+
                 // Parse number literal
                 loop {
                     let next_ch = match self.chars.peek() {
-                        Some(ch) => ch,
+                        Some(ch) => *ch,
                         None => break,
                     };
 
                     match next_ch {
-                        '0'..='9' => {
-                            self.chars.next();
-
-                            self.column_pos += 1;
-                            pos += 1;
+                        '0'..='9' => {}
+                        // A fraction needs a digit after the dot, `1.abs` is
+                        // still a method call
+                        '.' if !is_float && self.digit_follows(1) => {
+                            is_float = true;
+                        }
+                        'e' | 'E' if !has_exponent && self.exponent_follows() => {
+                            is_float = true;
+                            has_exponent = true;
+
+                            // With a sign, the `e` is taken here and the
+                            // sign below
+                            if !self.digit_follows(1) {
+                                self.chars.next();
+
+                                self.column_pos += 1;
+                                pos += 1;
+                            }
                         }
-                        // '.' => {
-                        //     self.chars.next();
-
-                        //     self.column_pos += 1;
-                        //     pos += 1;
-                        // },
                         _ => break,
                     }
+
+                    self.chars.next();
+
+                    self.column_pos += 1;
+                    pos += 1;
                 }
 
                 token_pos.end_column = self.column_pos;
 
-                Token::Number(token_pos, src[start..pos].parse().unwrap())
+                if is_float {
+                    Token::Float(token_pos, src[start..pos].parse().unwrap())
+                } else {
+                    Token::Number(token_pos, src[start..pos].parse().unwrap())
+                }
             }
 
             'A'..='Z' => {
@@ -328,18 +501,29 @@ impl Lexer<'_> {
                 let src_ident = &src[start..pos];
 
                 match src_ident {
+                    "true" | "false" => {
+                        token_pos.end_column = self.column_pos;
+                        Token::Bool(token_pos, src_ident == "true")
+                    }
                     "binary" => Token::Binary,
+                    "break" => Token::Break,
                     "class" => Token::Class,
                     "def_e" => Token::DefE,
                     "def" => Token::Def,
+                    "else" => Token::Else,
+                    "elsif" => Token::Elsif,
                     "end" => Token::End,
+                    "if" => Token::If,
                     "impl" => Token::Impl,
                     "loop" => Token::Loop,
+                    "next" => Token::Next,
                     "ret" => Token::Ret,
                     "self" => Token::SelfRef,
                     "struct" => Token::Struct,
                     "trait" => Token::Trait,
                     "unary" => Token::Unary,
+                    "until" => Token::Until,
+                    "while" => Token::While,
                     ident => {
                         token_pos.end_column = self.column_pos;
                         Token::Ident(token_pos, ident.to_string())
@@ -347,8 +531,6 @@ impl Lexer<'_> {
                 }
             }
 
-            '=' => Token::Assign,
-
             '@' => {
                 let mut token_pos = TokenPosition {
                     line: self.line_pos,
@@ -382,30 +564,39 @@ impl Lexer<'_> {
 
             //// Operators
             //
-            // Lexing only supports a single Char, so to handle operators like
-            // `**` for exponent it will lexed as two multiplications
-            '+' => Token::Op(['+', '\0', '\0', '\0']),
-            '*' => {
-                let next_chr = match self.chars.peek() {
-                    Some(ch) => *ch,
-                    None => return Some(Token::Op(['*', '\0', '\0', '\0'])),
-                };
+            // An operator takes the characters after it for as long as they
+            // spell the start of one of `OPERATORS`, so `<=` is a single
+            // `Op` and not `<` followed by `=`
+            '+' | '*' | '/' | '%' | '<' | '>' | '=' | '!' | '&' | '|' | '~' | '^' => {
+                let mut op = [ch, '\0', '\0', '\0'];
+                let mut length = 1;
+
+                while let Some(&next_ch) = self.chars.peek() {
+                    let mut spelled: String = op[..length].iter().collect();
+                    spelled.push(next_ch);
+
+                    if length == op.len()
+                        || !OPERATORS
+                            .iter()
+                            .any(|operator| operator.starts_with(&spelled))
+                    {
+                        break;
+                    }
 
-                if next_chr != '*' {
-                    self.char_pos = pos;
-                    return Some(Token::Op(['*', '\0', '\0', '\0']));
-                }
+                    op[length] = next_ch;
+                    length += 1;
 
-                self.chars.next();
+                    self.chars.next();
 
-                self.column_pos += 1;
-                pos += 1;
+                    self.column_pos += 1;
+                    pos += 1;
+                }
 
-                Token::Op(['*', '*', '\0', '\0'])
-            },
-            '/' => Token::Op(['/', '\0', '\0', '\0']),
-            '%' => Token::Op(['%', '\0', '\0', '\0']),
-            '>' => Token::Op(['>', '\0', '\0', '\0']),
+                match op {
+                    ['=', '\0', '\0', '\0'] => Token::Assign,
+                    op => Token::Op(op),
+                }
+            }
 
             '-' => {
                 let next_chr = match self.chars.peek() {
@@ -426,13 +617,15 @@ impl Lexer<'_> {
                 Token::Arrow
             }
 
-            _ => {
-                println!("NOT IMPL{:#?}", ch);
-                todo!()
-            } // op => {
-              //     // Parse operator
-              //     Ok(Token::Op(op))
-              // },
+            ch => {
+                let token_pos = TokenPosition {
+                    line: self.line_pos,
+                    start_column: self.column_pos,
+                    end_column: self.column_pos,
+                };
+
+                Token::Illegal(token_pos, ch.to_string())
+            }
         };
 
         // Update stored position, and return
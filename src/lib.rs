@@ -1,6 +1,12 @@
 pub mod codegen;
+pub mod debug_info;
+pub mod dump;
 pub mod lexer;
 pub mod pajama_compiler;
-pub mod pajama_lib;
 pub mod parser;
+pub mod repl;
+pub mod resolver;
 pub mod semantic_analyzer;
+pub mod session;
+pub mod source;
+pub mod type_checker;
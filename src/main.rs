@@ -1,11 +1,27 @@
 mod codegen;
+mod debug_info;
+mod dump;
 mod lexer;
 mod pajama_compiler;
-mod pajama_lib;
 mod parser;
+mod repl;
+mod resolver;
 mod semantic_analyzer;
+mod session;
+mod source;
+mod type_checker;
 
-use pajama_compiler::PajamaCompiler;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use dump::{DumpPhase, DumpSink};
+use indoc::indoc;
+use pajama_compiler::{CompileError, CompileOptions, LlvmFormat, PajamaCompiler};
+use repl::Repl;
+use semantic_analyzer::Diagnostic;
+use session::Session;
+use source::SourceMap;
 
 use mimalloc_rust::raw::basic_allocation::*;
 use mimalloc_rust::GlobalMiMalloc;
@@ -13,7 +29,362 @@ use mimalloc_rust::GlobalMiMalloc;
 #[global_allocator]
 static GLOBAL_MIMALLOC: GlobalMiMalloc = GlobalMiMalloc;
 
-pub fn main() {
-    let input = std::fs::read_to_string("dev_test.pjs").unwrap();
-    PajamaCompiler::compile_and_invoke(&input);
+const USAGE: &str = indoc! {"
+    Usage: pajama <command> [options] <path>... [-- <args>...]
+           pajama repl [options] [<path>...]
+
+    Commands:
+      run      Compile the program and run it with the JIT
+      check    Lex, parse and analyze the program without generating code
+      emit     Print an intermediate representation of the program
+      build    Compile the program to a native executable
+      repl     Evaluate definitions and expressions interactively, after loading
+               the definitions in the given paths
+
+    Options:
+      --stage=<stage>  What `emit` prints: tokens, ast, mlir, llvm-mlir, llvm-ir or
+                       llvm-bc (default: mlir)
+      -O<level>        Optimization level, 0 to 3 (default: 2)
+      --passes=<pipeline>
+                       Run a textual MLIR pass pipeline, e.g. `inline,canonicalize,cse`,
+                       instead of the optimization passes picked by -O
+      --time-passes    Print how long each pass takes
+      -g               Emit debug info, so gdb and perf can show Pajama functions,
+                       lines and local variables
+      --dump=<phases>  Dump the output of compiler phases while compiling, a comma
+                       separated list of tokens, ast, typed-ast, mlir and llvm-mlir
+      --dump-to=<path> Write dumps to <path> instead of stderr
+      -o <path>        Write the output of `emit` or `build` to <path>
+      -c, --object     Make `build` stop at a native object file instead of linking
+      -h, --help       Print this message
+
+    Multiple paths are compiled as a single program, in the order given.
+    Arguments after `--` are passed to the program by `run`, after the path of
+    the first file. A `main(args StrArray)` gets them as a StrArray.
+    `run` exits with the status returned by the program's `main`, or by
+    `_mlir_ciface_main` when it's declared `-> Int32`.
+    `build` links against libpajama_runtime.a, found next to the compiler or at
+    $PAJAMA_RUNTIME_LIB, using $CC (default: cc).
+"};
+
+const EXIT_COMPILE_ERROR: u8 = 1;
+const EXIT_USAGE_ERROR: u8 = 2;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Run,
+    Check,
+    Emit,
+    Build,
+    Repl,
+}
+
+#[derive(Debug, PartialEq)]
+enum Stage {
+    Tokens,
+    Ast,
+    Mlir,
+    LlvmMlir,
+    LlvmIr,
+    LlvmBitcode,
+}
+
+#[derive(Debug)]
+struct CliOptions {
+    command: Command,
+    stage: Stage,
+    compile_options: CompileOptions,
+    output: Option<PathBuf>,
+    object_only: bool,
+    paths: Vec<PathBuf>,
+    program_args: Vec<String>,
+}
+
+impl CliOptions {
+    fn parse(args: &[String]) -> Result<CliOptions, String> {
+        let mut args = args.iter();
+
+        let command = match args.next().map(String::as_str) {
+            Some("run") => Command::Run,
+            Some("check") => Command::Check,
+            Some("emit") => Command::Emit,
+            Some("build") => Command::Build,
+            Some("repl") => Command::Repl,
+            Some(command) => return Err(format!("unknown command `{}`", command)),
+            None => return Err("missing command".to_string()),
+        };
+
+        let mut options = CliOptions {
+            command,
+            stage: Stage::Mlir,
+            compile_options: CompileOptions::default(),
+            output: None,
+            object_only: false,
+            paths: vec![],
+            program_args: vec![],
+        };
+
+        while let Some(arg) = args.next() {
+            if let Some(stage) = arg.strip_prefix("--stage=") {
+                options.stage = match stage {
+                    "tokens" => Stage::Tokens,
+                    "ast" => Stage::Ast,
+                    "mlir" => Stage::Mlir,
+                    "llvm-mlir" => Stage::LlvmMlir,
+                    "llvm-ir" => Stage::LlvmIr,
+                    "llvm-bc" => Stage::LlvmBitcode,
+                    _ => return Err(format!("unknown stage `{}`", stage)),
+                };
+            } else if let Some(level) = arg.strip_prefix("-O") {
+                options.compile_options.opt_level = match level.parse() {
+                    Ok(level) if level <= 3 => level,
+                    _ => return Err(format!("unknown optimization level `{}`", level)),
+                };
+            } else if let Some(pipeline) = arg.strip_prefix("--passes=") {
+                options.compile_options.pass_pipeline = Some(pipeline.to_string());
+            } else if let Some(phases) = arg.strip_prefix("--dump=") {
+                for name in phases.split(',') {
+                    match DumpPhase::from_name(name) {
+                        Some(phase) => options.compile_options.dump.phases.push(phase),
+                        None => return Err(format!("unknown dump phase `{}`", name)),
+                    }
+                }
+            } else if let Some(path) = arg.strip_prefix("--dump-to=") {
+                options.compile_options.dump.sink = DumpSink::File(PathBuf::from(path));
+            } else if arg == "--time-passes" {
+                options.compile_options.time_passes = true;
+            } else if arg == "-g" {
+                options.compile_options.debug_info = true;
+            } else if arg == "-o" {
+                match args.next() {
+                    Some(path) => options.output = Some(PathBuf::from(path)),
+                    None => return Err("`-o` requires a path".to_string()),
+                }
+            } else if arg == "-c" || arg == "--object" {
+                options.object_only = true;
+            } else if arg == "--" {
+                options.program_args = args.cloned().collect();
+                break;
+            } else if arg.starts_with('-') {
+                return Err(format!("unknown option `{}`", arg));
+            } else {
+                options.paths.push(PathBuf::from(arg));
+            }
+        }
+
+        if options.paths.is_empty() && options.command != Command::Repl {
+            return Err("no input files".to_string());
+        }
+
+        Ok(options)
+    }
+
+    fn default_output(&self, extension: &str) -> PathBuf {
+        let stem = self.paths[0]
+            .file_stem()
+            .map(|stem| stem.to_os_string())
+            .unwrap_or_else(|| "out".into());
+
+        Path::new(&stem).with_extension(extension)
+    }
+}
+
+pub fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut compiler_args = args.iter().take_while(|arg| *arg != "--");
+
+    if compiler_args.any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let mut options = match CliOptions::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(EXIT_USAGE_ERROR);
+        }
+    };
+
+    let input = match read_sources(&options.paths) {
+        Ok((source_map, input)) => {
+            options.compile_options.source_map = source_map;
+            input
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return ExitCode::from(EXIT_USAGE_ERROR);
+        }
+    };
+
+    match run_command(&options, &input) {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            report_error(&err);
+            ExitCode::from(EXIT_COMPILE_ERROR)
+        }
+    }
+}
+
+/// Prints the error to stderr, each syntax error and diagnostic on its own.
+fn report_error(err: &CompileError) {
+    match err {
+        CompileError::Parse(errors) => {
+            for parse_error in errors {
+                eprintln!("error: {}\n", parse_error);
+            }
+        }
+        CompileError::Semantic(diagnostics) => {
+            for diagnostic in diagnostics {
+                eprintln!("{}: {}\n", diagnostic.severity, diagnostic);
+            }
+        }
+        err => eprintln!("error: {}", err),
+    }
+}
+
+fn read_sources(paths: &[PathBuf]) -> Result<(SourceMap, String), String> {
+    let mut files = vec![];
+
+    for path in paths {
+        match std::fs::read_to_string(path) {
+            Ok(source) => files.push((path.display().to_string(), source)),
+            Err(err) => return Err(format!("could not read `{}`: {}", path.display(), err)),
+        }
+    }
+
+    Ok(SourceMap::concat(files))
+}
+
+/// Runs the command, then prints the warnings the analysis found, also when
+/// the command failed.
+fn run_command(options: &CliOptions, input: &str) -> Result<ExitCode, CompileError> {
+    let session = Session::new(options.compile_options.clone());
+    let result = run_session_command(&session, options, input);
+    print_warnings(session.take_warnings());
+
+    result
+}
+
+/// Prints the warnings to stderr, like `report_error` does diagnostics.
+fn print_warnings(warnings: Vec<Diagnostic>) {
+    for warning in warnings {
+        eprintln!("{}: {}\n", warning.severity, warning);
+    }
+}
+
+/// Runs the command, `run` exits with the status the program's `main` returns.
+fn run_session_command(
+    session: &Session,
+    options: &CliOptions,
+    input: &str,
+) -> Result<ExitCode, CompileError> {
+    match options.command {
+        Command::Run => {
+            let mut program_args = vec![options.paths[0].display().to_string()];
+            program_args.extend(options.program_args.iter().cloned());
+
+            let status_code = PajamaCompiler::compile_and_invoke_in(session, input, &program_args)?;
+
+            // Like a process exit status, only the low 8 bits are kept
+            Ok(ExitCode::from(status_code as u8))
+        }
+        Command::Check => {
+            session.check(input)?;
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Emit => {
+            let output = match options.stage {
+                Stage::Tokens => format!("{:#?}\n", PajamaCompiler::tokenize(input)?).into_bytes(),
+                Stage::Ast => format!("{:#?}\n", session.check(input)?).into_bytes(),
+                Stage::Mlir => {
+                    let mlir = PajamaCompiler::compile_to_mlir_in(session, input, false)?;
+                    format!("{}\n", mlir).into_bytes()
+                }
+                Stage::LlvmMlir => {
+                    let mlir = PajamaCompiler::compile_to_mlir_in(session, input, true)?;
+                    format!("{}\n", mlir).into_bytes()
+                }
+                Stage::LlvmIr => {
+                    PajamaCompiler::compile_to_llvm_in(session, input, LlvmFormat::Ir)?
+                }
+                Stage::LlvmBitcode => {
+                    PajamaCompiler::compile_to_llvm_in(session, input, LlvmFormat::Bitcode)?
+                }
+            };
+
+            match &options.output {
+                Some(path) => std::fs::write(path, output)?,
+                None => std::io::stdout().write_all(&output)?,
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Build => {
+            let extension = if options.object_only { "o" } else { "" };
+            let output = match &options.output {
+                Some(path) => path.clone(),
+                None => options.default_output(extension),
+            };
+
+            if options.object_only {
+                PajamaCompiler::build_object_in(session, input, &output)?;
+            } else {
+                PajamaCompiler::build_executable_in(session, input, &output)?;
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Repl => run_repl(&options.compile_options, input),
+    }
+}
+
+/// Reads inputs from stdin until EOF, an input continues over several lines
+/// until its blocks are closed.
+fn run_repl(compile_options: &CompileOptions, definitions: &str) -> Result<ExitCode, CompileError> {
+    let mut repl = Repl::new(compile_options.clone());
+
+    if !definitions.trim().is_empty() {
+        let result = repl.define(definitions);
+        print_warnings(repl.take_warnings());
+        result?;
+    }
+
+    let mut lines = std::io::stdin().lock().lines();
+    let mut input = String::new();
+
+    loop {
+        print!("{}", if input.is_empty() { "pj> " } else { "..> " });
+        std::io::stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            println!();
+            return Ok(ExitCode::SUCCESS);
+        };
+
+        input.push_str(&line?);
+        input.push('\n');
+
+        if input.trim().is_empty() {
+            input.clear();
+            continue;
+        }
+
+        if !repl.is_complete(&input) {
+            continue;
+        }
+
+        let result = repl.eval(&input);
+        print_warnings(repl.take_warnings());
+
+        match result {
+            Ok(Some(value)) => println!("=> {}", value),
+            Ok(None) => {}
+            Err(err) => report_error(&err),
+        }
+
+        input.clear();
+    }
 }
@@ -1,171 +1,385 @@
-use std::collections::HashMap;
-
-use melior::dialect::DialectRegistry;
-use melior::ir::{Location, Module};
-use melior::pass::{conversion, PassManager};
-use melior::utility::{register_all_dialects, register_all_llvm_translations};
-use melior::{pass, Context, ExecutionEngine};
-
-use crate::codegen::Compiler;
-use crate::lexer::Lexer;
-use crate::parser::Parser;
-use crate::semantic_analyzer::SemanticAnalyzer;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::dump::DumpOptions;
+use crate::lexer::Token;
+use crate::parser::{ParseError, ParserResult};
+use crate::semantic_analyzer::Diagnostic;
+use crate::session::Session;
+use crate::source::SourceMap;
 
 pub struct PajamaCompiler {}
 
-impl PajamaCompiler {
-    pub fn compile_to_string(input: &str) -> String {
-        let mut lexer = Lexer::new(input);
-        let tokens = lexer.tokenize();
+/// Has the linker drop the parts of the runtime a program doesn't use. Apple's
+/// ld doesn't know GNU ld's flag for it.
+#[cfg(target_os = "macos")]
+const DEAD_STRIP_FLAG: &str = "-Wl,-dead_strip";
+#[cfg(not(target_os = "macos"))]
+const DEAD_STRIP_FLAG: &str = "-Wl,--gc-sections";
+
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    /// 0 to 3, selects the MLIR optimization passes and the LLVM opt level
+    pub opt_level: usize,
+    /// A textual pass pipeline, e.g. `canonicalize,cse`, that replaces the
+    /// optimization passes picked by `opt_level`
+    pub pass_pipeline: Option<String>,
+    /// Prints how long each pass took to stderr
+    pub time_passes: bool,
+    /// The phases to dump, off by default
+    pub dump: DumpOptions,
+    /// The files the source was concatenated from, spans point into them
+    pub source_map: SourceMap,
+    /// Emits DWARF debug info for functions, lines and local variables
+    pub debug_info: bool,
+}
 
-        println!("{:#?}", tokens);
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            opt_level: 2,
+            pass_pipeline: None,
+            time_passes: false,
+            dump: DumpOptions::default(),
+            source_map: SourceMap::default(),
+            debug_info: false,
+        }
+    }
+}
 
-        let mut precedence_map = PajamaCompiler::build_op_precedence_map();
-        let mut parser_result = Parser::start_parse(tokens, &mut precedence_map);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LlvmFormat {
+    /// Textual LLVM IR, as in a `.ll` file
+    Ir,
+    /// LLVM bitcode, as in a `.bc` file
+    Bitcode,
+}
 
-        SemanticAnalyzer::run(&mut parser_result);
+#[derive(Debug)]
+pub enum CompileError {
+    /// Every syntax error in the program
+    Parse(Vec<ParseError>),
+    /// Every error semantic analysis found
+    Semantic(Vec<Diagnostic>),
+    Codegen(&'static str),
+    /// The stage that failed verification and the MLIR diagnostics
+    Verification(&'static str, Vec<String>),
+    PassPipeline(melior::Error, Vec<String>),
+    Execution(melior::Error),
+    /// A stage panicked, with the stage and the panic message
+    Internal(&'static str, String),
+    Translation(String),
+    /// An LLVM command line tool the translation needs that isn't installed
+    MissingTool(PathBuf),
+    Link(String),
+    Io(std::io::Error),
+}
 
-        println!("ParserResult after analysis: ######");
-        println!("{:#?}", parser_result);
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Parse(errors) => {
+                let rendered: Vec<String> = errors.iter().map(ParseError::to_string).collect();
+                write!(f, "{}", rendered.join("\n\n"))
+            }
+            CompileError::Semantic(diagnostics) => {
+                let rendered: Vec<String> = diagnostics.iter().map(Diagnostic::to_string).collect();
+                write!(f, "{}", rendered.join("\n\n"))
+            }
+            CompileError::Codegen(message) => write!(f, "codegen error: {}", message),
+            CompileError::Verification(stage, diagnostics) => {
+                write!(f, "MLIR verification failed {}", stage)?;
+                write_diagnostics(f, diagnostics)
+            }
+            CompileError::PassPipeline(err, diagnostics) => {
+                write!(f, "pass pipeline failed: {}", err)?;
+                write_diagnostics(f, diagnostics)
+            }
+            CompileError::Execution(err) => write!(f, "execution failed: {}", err),
+            CompileError::Internal(stage, message) => {
+                write!(f, "internal compiler error during {}: {}", stage, message)
+            }
+            CompileError::Translation(message) => {
+                write!(f, "LLVM translation failed: {}", message)
+            }
+            CompileError::MissingTool(program) => write!(
+                f,
+                "could not find `{}`, set MLIR_SYS_170_PREFIX to an LLVM install with it",
+                program.display()
+            ),
+            CompileError::Link(message) => write!(f, "linking failed: {}", message),
+            CompileError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
 
-        let mlir_context = PajamaCompiler::create_mlir_context();
-        let location = Location::unknown(&mlir_context);
-        let mut mlir_module = Module::new(location);
-        let mut compiler = Compiler::new(&mlir_context, &mlir_module, &parser_result);
+fn write_diagnostics(f: &mut fmt::Formatter<'_>, diagnostics: &[String]) -> fmt::Result {
+    for diagnostic in diagnostics {
+        write!(f, "\n  {}", diagnostic)?;
+    }
 
-        compiler.compile();
+    Ok(())
+}
 
-        //
+impl std::error::Error for CompileError {}
 
-        println!("PRE VERIFICATION:");
-        println!("{}", mlir_module.body().to_string());
+impl From<std::io::Error> for CompileError {
+    fn from(err: std::io::Error) -> Self {
+        CompileError::Io(err)
+    }
+}
 
-        assert!(mlir_module.as_operation().verify());
+/// One-shot helpers over `Session` for the CLI and tests, each call compiles
+/// in a fresh session. The `_in` variants compile in a session of the
+/// caller's instead, which keeps the warnings found by the analysis.
+impl PajamaCompiler {
+    pub fn tokenize(input: &str) -> Result<Vec<Token>, CompileError> {
+        let token_stream = Session::new(CompileOptions::default()).tokenize(input)?;
 
-        let pass_manager = PassManager::new(&mlir_context);
-        pass_manager.add_pass(conversion::create_func_to_llvm());
+        Ok(token_stream.tokens)
+    }
 
-        pass_manager
-            .nested_under("llvm.func")
-            .add_pass(conversion::create_arith_to_llvm());
-        pass_manager
-            .nested_under("llvm.func")
-            .add_pass(conversion::create_index_to_llvm());
-        pass_manager.add_pass(conversion::create_scf_to_control_flow());
-        pass_manager.add_pass(conversion::create_control_flow_to_llvm());
-        pass_manager.add_pass(conversion::create_finalize_mem_ref_to_llvm());
+    pub fn parse(input: &str) -> Result<ParserResult, CompileError> {
+        let session = Session::new(CompileOptions::default());
+        let token_stream = session.tokenize(input)?;
 
-        pass_manager.add_pass(conversion::create_func_to_llvm());
+        session.parse(token_stream)
+    }
 
-        pass_manager.run(&mut mlir_module).unwrap();
+    /// Lexes, parses and runs semantic analysis without generating any code.
+    pub fn check(input: &str) -> Result<ParserResult, CompileError> {
+        Session::new(CompileOptions::default()).check(input)
+    }
 
-        assert!(mlir_module.as_operation().verify());
+    /// Returns the body of the lowered module, without any optimization passes.
+    pub fn compile_to_string(input: &str) -> Result<String, CompileError> {
+        let session = Session::new(CompileOptions {
+            opt_level: 0,
+            ..CompileOptions::default()
+        });
 
-        println!("POST VERIFICATION:");
-        println!("{}", mlir_module.body().to_string());
+        let parser_result = session.check(input)?;
+        let mut mlir_module = session.generate(&parser_result)?;
+
+        session.lower(&mut mlir_module)?;
 
         let body = mlir_module.body().to_string();
 
         // For some reason spaces are being added to the ends of some lines
         // Maybe because of the extra spaces from `macro_rules! build_test_fn {` ?
-        body.replace(" \n", "\n")
-
-        // PajamaCompiler::invoke(&mlir_module);
+        Ok(body.replace(" \n", "\n"))
     }
 
-    pub fn compile_and_invoke(input: &str) {
-        let mut lexer = Lexer::new(input);
-        let tokens = lexer.tokenize();
-
-        println!("{:#?}", tokens);
-
-        let mut precedence_map = PajamaCompiler::build_op_precedence_map();
-        let mut parser_result = Parser::start_parse(tokens, &mut precedence_map);
+    /// Returns the whole MLIR module as text, either as generated by codegen
+    /// or after it has been optimized and lowered to the LLVM dialect.
+    pub fn compile_to_mlir(
+        input: &str,
+        lowered: bool,
+        options: &CompileOptions,
+    ) -> Result<String, CompileError> {
+        PajamaCompiler::compile_to_mlir_in(&Session::new(options.clone()), input, lowered)
+    }
 
-        SemanticAnalyzer::run(&mut parser_result);
+    pub fn compile_to_mlir_in(
+        session: &Session,
+        input: &str,
+        lowered: bool,
+    ) -> Result<String, CompileError> {
+        let parser_result = session.check(input)?;
+        let mut mlir_module = session.generate(&parser_result)?;
 
-        println!("ParserResult after analysis: ######");
-        println!("{:#?}", parser_result);
+        if lowered {
+            session.lower(&mut mlir_module)?;
+        }
 
-        let mlir_context = PajamaCompiler::create_mlir_context();
-        let location = Location::unknown(&mlir_context);
-        let mut mlir_module = Module::new(location);
-        let mut compiler = Compiler::new(&mlir_context, &mlir_module, &parser_result);
+        Ok(mlir_module.as_operation().to_string())
+    }
 
-        compiler.compile();
+    /// Translates the lowered module to LLVM IR or bitcode, optimized by `opt`
+    /// at `options.opt_level`.
+    pub fn compile_to_llvm(
+        input: &str,
+        format: LlvmFormat,
+        options: &CompileOptions,
+    ) -> Result<Vec<u8>, CompileError> {
+        PajamaCompiler::compile_to_llvm_in(&Session::new(options.clone()), input, format)
+    }
 
-        //
+    pub fn compile_to_llvm_in(
+        session: &Session,
+        input: &str,
+        format: LlvmFormat,
+    ) -> Result<Vec<u8>, CompileError> {
+        let mlir = PajamaCompiler::compile_to_mlir_in(session, input, true)?;
+        let llvm_ir = PajamaCompiler::run_llvm_tool(
+            "mlir-translate",
+            &["--mlir-to-llvmir"],
+            mlir.as_bytes(),
+        )?;
+
+        let opt_flag = format!("-O{}", session.options().opt_level);
+        let opt_args = match format {
+            LlvmFormat::Ir => vec![opt_flag.as_str(), "-S"],
+            LlvmFormat::Bitcode => vec![opt_flag.as_str()],
+        };
+
+        PajamaCompiler::run_llvm_tool("opt", &opt_args, &llvm_ir)
+    }
 
-        println!("PRE VERIFICATION:");
-        println!("{}", mlir_module.body().to_string());
+    /// Pipes `stdin` through one of the LLVM command line tools. They're taken
+    /// from `$MLIR_SYS_170_PREFIX/bin`, the LLVM install melior builds
+    /// against, when that's set, and from `PATH` otherwise.
+    fn run_llvm_tool(tool: &str, args: &[&str], stdin: &[u8]) -> Result<Vec<u8>, CompileError> {
+        let program = match std::env::var("MLIR_SYS_170_PREFIX") {
+            Ok(prefix) => Path::new(&prefix).join("bin").join(tool),
+            Err(_) => PathBuf::from(tool),
+        };
+
+        let mut child = Command::new(&program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => CompileError::MissingTool(program.clone()),
+                _ => CompileError::Translation(format!(
+                    "could not run `{}`: {}",
+                    program.display(),
+                    err
+                )),
+            })?;
+
+        child.stdin.take().unwrap().write_all(stdin)?;
+
+        let output = child.wait_with_output()?;
+
+        if !output.status.success() {
+            return Err(CompileError::Translation(format!(
+                "`{}` {}\n{}",
+                tool,
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
 
-        assert!(mlir_module.as_operation().verify());
+        Ok(output.stdout)
+    }
 
-        let pass_manager = PassManager::new(&mlir_context);
-        pass_manager.add_pass(conversion::create_func_to_llvm());
+    /// Compiles the program to a native object file. The object defines a C
+    /// `main` that runs the Pajama entry point.
+    pub fn build_object(
+        input: &str,
+        output: &Path,
+        options: &CompileOptions,
+    ) -> Result<(), CompileError> {
+        PajamaCompiler::build_object_in(&Session::new(options.clone()), input, output)
+    }
 
-        pass_manager
-            .nested_under("llvm.func")
-            .add_pass(conversion::create_arith_to_llvm());
-        pass_manager
-            .nested_under("llvm.func")
-            .add_pass(conversion::create_index_to_llvm());
-        pass_manager.add_pass(conversion::create_scf_to_control_flow());
-        pass_manager.add_pass(conversion::create_control_flow_to_llvm());
-        pass_manager.add_pass(conversion::create_finalize_mem_ref_to_llvm());
+    pub fn build_object_in(
+        session: &Session,
+        input: &str,
+        output: &Path,
+    ) -> Result<(), CompileError> {
+        let parser_result = session.check(input)?;
+        let mut mlir_module = session.generate_executable(&parser_result)?;
 
-        pass_manager.add_pass(conversion::create_func_to_llvm());
+        session.lower(&mut mlir_module)?;
+        session.write_object(&mlir_module, output)
+    }
 
-        pass_manager.run(&mut mlir_module).unwrap();
+    /// Compiles the program and links it with the runtime into a standalone
+    /// executable.
+    pub fn build_executable(
+        input: &str,
+        output: &Path,
+        options: &CompileOptions,
+    ) -> Result<(), CompileError> {
+        PajamaCompiler::build_executable_in(&Session::new(options.clone()), input, output)
+    }
 
-        assert!(mlir_module.as_operation().verify());
+    pub fn build_executable_in(
+        session: &Session,
+        input: &str,
+        output: &Path,
+    ) -> Result<(), CompileError> {
+        let object = output.with_extension("o");
 
-        println!("POST VERIFICATION:");
-        println!("{}", mlir_module.body().to_string());
+        PajamaCompiler::build_object_in(session, input, &object)?;
+        let result = PajamaCompiler::link_executable(&object, output);
+        let _ = std::fs::remove_file(&object);
 
-        PajamaCompiler::invoke(&mlir_module);
+        result
     }
 
-    pub fn invoke(mlir_module: &Module) {
-        let engine = ExecutionEngine::new(mlir_module, 2, &[], false);
-
-        unsafe {
-            engine
-                // .invoke_packed("main", &mut [&mut status_code as *mut i32 as *mut ()])
-                .invoke_packed("main", &mut [])
-                .unwrap();
+    pub fn link_executable(object: &Path, output: &Path) -> Result<(), CompileError> {
+        let runtime = PajamaCompiler::runtime_library()?;
+        let linker = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+        let status = Command::new(&linker)
+            .arg(object)
+            .arg(&runtime)
+            .arg("-o")
+            .arg(output)
+            .args([DEAD_STRIP_FLAG, "-lpthread", "-ldl", "-lm"])
+            .status()
+            .map_err(|err| CompileError::Link(format!("could not run `{}`: {}", linker, err)))?;
+
+        if !status.success() {
+            return Err(CompileError::Link(format!("`{}` {}", linker, status)));
         }
+
+        Ok(())
     }
 
-    fn create_mlir_context() -> Context {
-        let registry = DialectRegistry::new();
-        register_all_dialects(&registry);
+    /// Finds the `pajama_runtime` staticlib. `PAJAMA_RUNTIME_LIB` takes
+    /// precedence, otherwise it's looked up next to the compiler executable.
+    fn runtime_library() -> Result<PathBuf, CompileError> {
+        if let Ok(path) = std::env::var("PAJAMA_RUNTIME_LIB") {
+            return Ok(PathBuf::from(path));
+        }
 
-        let context = Context::new();
-        context.append_dialect_registry(&registry);
-        context.load_all_available_dialects();
-        register_all_llvm_translations(&context);
+        let exe = std::env::current_exe()?;
+        let candidates = exe
+            .ancestors()
+            .skip(1)
+            .take(2)
+            .map(|dir| dir.join("libpajama_runtime.a"));
+
+        for candidate in candidates {
+            if candidate.exists() {
+                return Ok(candidate);
+            }
+        }
 
-        // temp for development
-        context.attach_diagnostic_handler(|diagnostic| {
-            eprintln!("{}", diagnostic);
-            true
-        });
+        Err(CompileError::Link(
+            "could not find libpajama_runtime.a, set PAJAMA_RUNTIME_LIB to its path".to_string(),
+        ))
+    }
 
-        context
+    /// JIT compiles and runs the program with `args` as its arguments,
+    /// returning its exit status.
+    pub fn compile_and_invoke(
+        input: &str,
+        options: &CompileOptions,
+        args: &[String],
+    ) -> Result<i32, CompileError> {
+        PajamaCompiler::compile_and_invoke_in(&Session::new(options.clone()), input, args)
     }
 
-    fn build_op_precedence_map() -> HashMap<[char; 4], i32> {
-        let mut op_precedence_map = HashMap::with_capacity(6);
+    pub fn compile_and_invoke_in(
+        session: &Session,
+        input: &str,
+        args: &[String],
+    ) -> Result<i32, CompileError> {
+        let parser_result = session.check(input)?;
+        let mut mlir_module = session.generate(&parser_result)?;
 
-        op_precedence_map.insert(['<', '\0', '\0', '\0'], 10); // Example precedence for '<'
-        op_precedence_map.insert(['+', '\0', '\0', '\0'], 20); // Addition
-        op_precedence_map.insert(['-', '\0', '\0', '\0'], 20); // Subtraction
-        op_precedence_map.insert(['*', '\0', '\0', '\0'], 40); // Multiplication
-        op_precedence_map.insert(['/', '\0', '\0', '\0'], 40); // Division
-        op_precedence_map.insert(['%', '\0', '\0', '\0'], 40); // Modulo
-        op_precedence_map.insert(['^', '\0', '\0', '\0'], 50); // Exponentiation (e.g., '**')
+        session.lower(&mut mlir_module)?;
 
-        op_precedence_map
+        let engine = session.jit(&mlir_module)?;
+        session.invoke_main(&engine, &parser_result, args)
     }
 }
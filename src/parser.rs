@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    fmt,
     fs::OpenOptions,
     hash::Hash,
     ops::{Deref, DerefMut},
@@ -7,7 +8,49 @@ use std::{
 
 use melior::ir::attribute;
 
-use crate::lexer::Token;
+use crate::lexer::{Token, TokenPosition, TokenStream};
+use crate::source::{SourceMap, Span};
+
+/// A syntax error at a token: what the parser expected there and the token it
+/// found instead. Displays rustc-style, with the source line the token is on
+/// and the token underlined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub expected: &'static str,
+    pub found: String,
+    pub span: Span,
+    /// The file the span is in, when the source came from files
+    pub file_name: Option<String>,
+    pub source_line: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.span.line.to_string();
+        let gutter = " ".repeat(line.len());
+        let start_column = self.span.start_column.max(1);
+        let underline_length = (self.span.end_column + 1).saturating_sub(start_column);
+
+        writeln!(f, "expected {}, found {}", self.expected, self.found)?;
+        match &self.file_name {
+            Some(file_name) => {
+                writeln!(f, "{}--> {}:{}:{}", gutter, file_name, line, start_column)?
+            }
+            None => writeln!(f, "{}--> {}:{}", gutter, line, start_column)?,
+        }
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line, self.source_line)?;
+        write!(
+            f,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(start_column - 1),
+            "^".repeat(underline_length.max(1))
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
 
 #[derive(Debug)]
 pub struct Access {
@@ -15,6 +58,7 @@ pub struct Access {
     pub message: Box<Node>,
     pub index: i32,
     pub return_type: Option<BaseType>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -22,6 +66,7 @@ pub struct Array {
     pub items: Vec<Node>,
     pub item_type: BaseType,
     pub length: i64,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -29,6 +74,7 @@ pub struct Attribute {
     pub name: String,
     pub index: i32,
     pub return_type: BaseType,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -36,18 +82,21 @@ pub struct AssignAttribute {
     pub name: String,
     pub index: i32,
     pub value: Box<Node>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct AssignAttributeAccess {
     pub access: Access,
     pub value: Box<Node>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct AssignLocalVar {
     pub name: String,
     pub value: Box<Node>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -55,6 +104,7 @@ pub struct AssignConstant {
     pub name: String,
     pub value: Box<Node>,
     pub return_type: BaseType,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -62,6 +112,38 @@ pub struct Binary {
     pub op: [char; 4],
     pub left: Box<Node>,
     pub right: Box<Node>,
+    pub span: Span,
+}
+
+impl Binary {
+    /// Whether the operator compares its operands, which makes the
+    /// expression a `Bool` whatever they are.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self.op,
+            ['=', '=', '\0', '\0']
+                | ['!', '=', '\0', '\0']
+                | ['<', '\0', '\0', '\0']
+                | ['>', '\0', '\0', '\0']
+                | ['<', '=', '\0', '\0']
+                | ['>', '=', '\0', '\0']
+        )
+    }
+
+    /// Whether the operator is `&&` or `||`, which only evaluate their
+    /// right-hand side when the left one doesn't decide the result.
+    pub fn is_logical(&self) -> bool {
+        matches!(self.op, ['&', '&', '\0', '\0'] | ['|', '|', '\0', '\0'])
+    }
+}
+
+/// `-`, `!` or `~` applied to an operand. Other unary operators are calls to
+/// the function defining them.
+#[derive(Debug)]
+pub struct Unary {
+    pub op: [char; 4],
+    pub operand: Box<Node>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -69,6 +151,7 @@ pub struct Call {
     pub fn_name: String,
     pub args: Vec<Node>,
     pub return_type: Option<BaseType>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -76,27 +159,73 @@ pub struct Send {
     pub receiver: Box<Node>,
     pub message: Box<Node>,
     pub return_type: Option<BaseType>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct FnRef {
     pub fn_name: String,
+    pub span: Span,
+}
+
+/// `if cond ... elsif cond ... else ... end`, or a postfix `expr if cond`.
+/// An `elsif` is an `If` on its own in the `else` branch.
+#[derive(Debug)]
+pub struct If {
+    pub condition: Box<Node>,
+    pub then_body: Vec<Node>,
+    pub else_body: Option<Vec<Node>>,
+    /// The type both branches give, when the `if` has a value
+    pub return_type: Option<BaseType>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Int {
     pub value: u64,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Bool {
+    pub value: bool,
+    pub span: Span,
+}
+
+/// `break`, optionally with the value of the `loop` it leaves.
+#[derive(Debug)]
+pub struct Break {
+    pub value: Option<Box<Node>>,
+    /// The type of its value, once inferred
+    pub return_type: Option<BaseType>,
+    pub span: Span,
+}
+
+/// `next`, which skips to the next iteration of the loop.
+#[derive(Debug)]
+pub struct Next {
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Float {
+    pub value: f64,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct StringLiteral {
     pub value: String,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct LocalVar {
     pub name: String,
     pub return_type: Option<BaseType>,
+    /// Where the variable is declared, its first assignment or the argument
+    pub declaration: Option<Span>,
+    pub span: Span,
 }
 
 impl LocalVar {
@@ -107,6 +236,9 @@ impl LocalVar {
                 BaseType::Byte => "Byte",
                 BaseType::BytePtr => "BytePtr",
                 BaseType::Class(class_name) => class_name.as_str(),
+                BaseType::Bool => "Bool",
+                BaseType::Float32 => "Float32",
+                BaseType::Float64 => "Float64",
                 BaseType::FnRef => "FnRef",
                 BaseType::Int => "Int",
                 BaseType::Int16 => "Int16",
@@ -123,12 +255,14 @@ impl LocalVar {
 #[derive(Debug)]
 pub struct Module {
     pub methods: Vec<Node>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Class {
     pub name: String,
     pub attributes: Vec<Attribute>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -136,6 +270,7 @@ pub struct Struct {
     pub name: String,
     pub attributes: Vec<Attribute>,
     pub return_type: BaseType,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -143,33 +278,39 @@ pub struct BuildStruct {
     pub name: String,
     pub args: Vec<Node>,
     pub return_type: BaseType,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Trait {
     pub name: String,
     pub body: Vec<Node>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Impl {
     pub name: String,
     pub body: Vec<Node>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct SelfRef {
     pub return_type: BaseType,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Ret {
     pub value: Box<Node>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Const {
     pub name: String,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -182,24 +323,69 @@ pub enum Node {
     AssignLocalVar(AssignLocalVar),
     Attribute(Attribute),
     Binary(Binary),
+    Bool(Bool),
+    Break(Break),
     BuildStruct(BuildStruct),
     Call(Call),
     Class(Class),
     Const(Const),
     Def(Def),
     DefE(DefE),
+    Float(Float),
     FnRef(FnRef),
+    If(If),
     Impl(Impl),
     Int(Int),
     LocalVar(LocalVar),
     Loop(Loop),
     Module(Module),
+    Next(Next),
     Ret(Ret),
     SelfRef(SelfRef),
     Send(Send),
     StringLiteral(StringLiteral),
     Struct(Struct),
     Trait(Trait),
+    Unary(Unary),
+}
+
+impl Node {
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Access(node) => node.span,
+            Node::Array(node) => node.span,
+            Node::AssignAttribute(node) => node.span,
+            Node::AssignAttributeAccess(node) => node.span,
+            Node::AssignConstant(node) => node.span,
+            Node::AssignLocalVar(node) => node.span,
+            Node::Attribute(node) => node.span,
+            Node::Binary(node) => node.span,
+            Node::Bool(node) => node.span,
+            Node::Break(node) => node.span,
+            Node::BuildStruct(node) => node.span,
+            Node::Call(node) => node.span,
+            Node::Class(node) => node.span,
+            Node::Const(node) => node.span,
+            Node::Def(node) => node.span,
+            Node::DefE(node) => node.span,
+            Node::Float(node) => node.span,
+            Node::FnRef(node) => node.span,
+            Node::If(node) => node.span,
+            Node::Impl(node) => node.span,
+            Node::Int(node) => node.span,
+            Node::LocalVar(node) => node.span,
+            Node::Loop(node) => node.span,
+            Node::Module(node) => node.span,
+            Node::Next(node) => node.span,
+            Node::Ret(node) => node.span,
+            Node::SelfRef(node) => node.span,
+            Node::Send(node) => node.span,
+            Node::StringLiteral(node) => node.span,
+            Node::Struct(node) => node.span,
+            Node::Trait(node) => node.span,
+            Node::Unary(node) => node.span,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -212,6 +398,13 @@ pub enum BaseType {
     Int64,
     FnRef,
 
+    // Boolean Type
+    Bool,
+
+    // Float Types
+    Float32,
+    Float64,
+
     // Dynamic Types
     Array(i64, Box<BaseType>),
     Class(String),
@@ -224,10 +417,88 @@ pub enum BaseType {
     Void,
 }
 
+impl BaseType {
+    pub fn is_float(&self) -> bool {
+        matches!(self, BaseType::Float32 | BaseType::Float64)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            BaseType::Byte | BaseType::Int | BaseType::Int16 | BaseType::Int32 | BaseType::Int64
+        )
+    }
+
+    pub fn is_number(&self) -> bool {
+        self.is_float() || self.is_integer()
+    }
+
+    /// The float type arithmetic on `left` and `right` is done in, when both
+    /// are numbers and either of them is a float. Integers are converted to
+    /// it, and a `Float32` is widened when the other side is a `Float64`.
+    pub fn float_promotion(left: &BaseType, right: &BaseType) -> Option<BaseType> {
+        if !left.is_number() || !right.is_number() {
+            return None;
+        }
+
+        match (left, right) {
+            (BaseType::Float64, _) | (_, BaseType::Float64) => Some(BaseType::Float64),
+            (BaseType::Float32, _) | (_, BaseType::Float32) => Some(BaseType::Float32),
+            _ => None,
+        }
+    }
+
+    /// The integer type arithmetic on `left` and `right` is done in, when both
+    /// are integers: the wider of the two. The narrower one is extended to
+    /// it, a `Byte` with zeros and the other integers with their sign bit.
+    pub fn integer_promotion(left: &BaseType, right: &BaseType) -> Option<BaseType> {
+        match (left.integer_width(), right.integer_width()) {
+            (Some(left_width), Some(right_width)) if right_width > left_width => {
+                Some(right.clone())
+            }
+            (Some(_), Some(_)) => Some(left.clone()),
+            _ => None,
+        }
+    }
+
+    /// The number of bits of an integer type.
+    pub fn integer_width(&self) -> Option<u32> {
+        match self {
+            BaseType::Byte => Some(8),
+            BaseType::Int16 => Some(16),
+            BaseType::Int32 => Some(32),
+            BaseType::Int | BaseType::Int64 => Some(64),
+            _ => None,
+        }
+    }
+}
+
+/// The type as it's written in a program, for diagnostics.
+impl fmt::Display for BaseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseType::Array(_, item_type) => write!(f, "Array<{}>", item_type),
+            BaseType::Byte => write!(f, "Byte"),
+            BaseType::BytePtr => write!(f, "BytePtr"),
+            BaseType::Class(name) | BaseType::Struct(name) => write!(f, "{}", name),
+            BaseType::Bool => write!(f, "Bool"),
+            BaseType::Float32 => write!(f, "Float32"),
+            BaseType::Float64 => write!(f, "Float64"),
+            BaseType::FnRef => write!(f, "FnRef"),
+            BaseType::Int => write!(f, "Int"),
+            BaseType::Int16 => write!(f, "Int16"),
+            BaseType::Int32 => write!(f, "Int32"),
+            BaseType::Int64 => write!(f, "Int64"),
+            BaseType::Void => write!(f, "Void"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Arg {
     pub name: String,
     pub return_type: BaseType,
+    pub span: Span,
 }
 
 impl Arg {
@@ -237,6 +508,9 @@ impl Arg {
             BaseType::Byte => "Byte",
             BaseType::BytePtr => "BytePtr",
             BaseType::Class(class_name) => class_name.as_str(),
+            BaseType::Bool => "Bool",
+            BaseType::Float32 => "Float32",
+            BaseType::Float64 => "Float64",
             BaseType::FnRef => "FnRef",
             BaseType::Int => "Int",
             BaseType::Int16 => "Int16",
@@ -255,6 +529,7 @@ pub struct Prototype {
     pub return_type: Option<BaseType>,
     pub is_op: bool,
     pub prec: usize,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -265,23 +540,35 @@ pub struct Def {
     pub class_name: String,
     pub impl_name: String,
     pub trait_name: String,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct DefE {
     pub prototype: Prototype,
+    pub span: Span,
 }
 
+/// `loop { ... }`, `while cond ... end` or `until cond ... end`.
 #[derive(Debug)]
 pub struct Loop {
     // pub args: HashMap<String, LocalVar>,
+    /// The condition of a `while` or an `until`, a `loop` runs until it breaks
+    pub condition: Option<Box<Node>>,
+    /// Whether the loop runs until its condition holds, rather than while it does
+    pub until: bool,
     pub body: Vec<Node>,
+    /// The type of the values it breaks with, when the loop has a value
+    pub return_type: Option<BaseType>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct ParserResult {
     pub module: Node,
     pub index: ParserResultIndex,
+    /// Names the files the spans in the module point into
+    pub source_map: SourceMap,
 }
 
 #[derive(Debug)]
@@ -311,6 +598,9 @@ pub struct ParserFunctionCtx {
 #[derive(Debug)]
 pub struct Parser<'a> {
     pub tokens: Vec<Token>,
+    pub positions: Vec<TokenPosition>,
+    pub source: String,
+    pub source_map: SourceMap,
     pub pos: usize,
     pub op_precedence: &'a mut HashMap<[char; 4], i32>,
     pub index: ParserResultIndex,
@@ -329,9 +619,30 @@ impl<'a> Parser<'a> {
     //     }
     // }
 
-    pub fn start_parse(tokens: Vec<Token>, op_precedence: &mut HashMap<[char; 4], i32>) -> ParserResult {
+    pub fn start_parse(
+        token_stream: TokenStream,
+        op_precedence: &mut HashMap<[char; 4], i32>,
+    ) -> Result<ParserResult, Vec<ParseError>> {
+        let (parser_result, errors) = Parser::parse_recovering(token_stream, op_precedence);
+
+        if errors.is_empty() {
+            Ok(parser_result)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parses every top level item it can, skipping the ones with syntax
+    /// errors, and returns what was parsed along with all the errors.
+    pub fn parse_recovering(
+        token_stream: TokenStream,
+        op_precedence: &mut HashMap<[char; 4], i32>,
+    ) -> (ParserResult, Vec<ParseError>) {
         let mut parser = Parser {
-            tokens,
+            tokens: token_stream.tokens,
+            positions: token_stream.positions,
+            source: token_stream.source,
+            source_map: token_stream.source_map,
             op_precedence,
             pos: 0,
             index: ParserResultIndex {
@@ -343,22 +654,28 @@ impl<'a> Parser<'a> {
             },
         };
 
-        let module = parser.parse().unwrap();
+        let (module, errors) = parser.parse();
 
-        ParserResult {
+        let parser_result = ParserResult {
             module,
             index: parser.index,
-        }
+            source_map: parser.source_map,
+        };
+
+        (parser_result, errors)
     }
 
     // pub fn parse(&mut self) -> Result<ParserResult, &'static str> {
-    pub fn parse(&mut self) -> Result<Node, &'static str> {
+    pub fn parse(&mut self) -> (Node, Vec<ParseError>) {
         let mut methods = vec![];
+        let mut errors = vec![];
         let mut mctx = ParserModuleCtx {
             self_node: None,
             class_name: "".to_string(),
         };
 
+        self.declare_operators();
+
         loop {
             self.advance_optional_whitespace();
             if self.at_end() {
@@ -366,7 +683,8 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            let results = match self.current()? {
+            let item_start = self.pos;
+            let results = match self.curr() {
                 Token::Const(pos, name) => self.parse_constant_assignment_expr(&mut mctx),
                 Token::Class => self.parse_class(&mut mctx),
                 Token::Struct => self.parse_struct(&mut mctx),
@@ -380,17 +698,26 @@ impl<'a> Parser<'a> {
                 ),
                 Token::DefE => self.parse_def_e(&mut mctx),
                 _ => {
-                    println!("{:#?}", self.curr());
-                    Err("Expected class, def, or trait")
+                    Err(self.error("a constant, `class`, `struct`, `trait`, `def` or `def_e`"))
                 }
             };
 
-            for result in results? {
-                methods.push(result);
+            match results {
+                Ok(results) => {
+                    for result in results {
+                        methods.push(result);
+                    }
+                }
+                Err(err) => {
+                    errors.push(err);
+                    self.synchronize(item_start);
+                }
             }
         }
 
-        Ok(Node::Module(Module { methods }))
+        let span = self.span_at(0);
+
+        (Node::Module(Module { methods, span }), errors)
 
         // Ok(ParserResult {
         //     module: Node::Module(Module { methods }),
@@ -398,6 +725,38 @@ impl<'a> Parser<'a> {
         // })
     }
 
+    /// Declares the operators defined with `def binary` and `def unary`
+    /// before any item is parsed, so that they can be used above their
+    /// definitions.
+    fn declare_operators(&mut self) {
+        let mut mctx = ParserModuleCtx {
+            self_node: None,
+            class_name: "".to_string(),
+        };
+
+        for index in 0..self.tokens.len() {
+            if !matches!(self.tokens[index], Token::Def)
+                || !matches!(
+                    self.tokens.get(index + 2),
+                    Some(Token::Binary | Token::Unary)
+                )
+            {
+                continue;
+            }
+
+            // Past `def`, the errors are reported when the item is parsed
+            self.pos = index + 1;
+
+            if let Ok(prototype) = self.parse_prototype(&mut mctx) {
+                self.index
+                    .fn_prototype_index
+                    .insert(prototype.name.clone(), prototype);
+            }
+        }
+
+        self.pos = 0;
+    }
+
     // fn parse_comment(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, &'static str> {
     //     match self.curr() {
     //         Token::Comment(pos, text) => {
@@ -413,9 +772,8 @@ impl<'a> Parser<'a> {
     fn parse_constant_assignment_expr(
         &mut self,
         mctx: &mut ParserModuleCtx,
-    ) -> Result<Vec<Node>, &'static str> {
-        println!("{:#?}", self.curr());
-
+    ) -> Result<Vec<Node>, ParseError> {
+        let span = self.span();
         let name = match self.current()? {
             Token::Const(pos, name) => {
                 self.advance()?;
@@ -423,12 +781,9 @@ impl<'a> Parser<'a> {
 
                 name
             }
-            _ => return Err("Expected const node"),
+            _ => return Err(self.error("a constant name")),
         };
 
-        println!("name {:#?}", name);
-        println!("{:#?}", self.curr());
-
         let return_type = match self.current()? {
             Token::Const(_type_pos, type_name) => {
                 self.advance();
@@ -436,20 +791,18 @@ impl<'a> Parser<'a> {
 
                 self.class_base_type(type_name)
             }
-            _ => return Err("Expected type for constant"),
+            _ => return Err(self.error("a type for the constant")),
         };
 
-        println!("{:#?}", self.curr());
-
         match self.current()? {
             Token::Assign => {
                 self.advance();
                 self.advance_optional_whitespace();
             }
-            _ => return Err("Expected constant assignment"),
+            _ => return Err(self.error("`=` after the constant name")),
         };
 
-        let value = Box::new(self.parse_constant_value_expr(mctx).unwrap());
+        let value = Box::new(self.parse_constant_value_expr(mctx)?);
 
         self.index
             .constant_index
@@ -459,6 +812,7 @@ impl<'a> Parser<'a> {
             name,
             value,
             return_type,
+            span,
         })])
         // Ok(vec![])
     }
@@ -466,7 +820,7 @@ impl<'a> Parser<'a> {
     fn parse_constant_value_expr(
         &mut self,
         mctx: &mut ParserModuleCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         self.parse_nb_expr()
 
         // match self.current()? {
@@ -478,7 +832,9 @@ impl<'a> Parser<'a> {
         // }
     }
 
-    fn parse_class(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, &'static str> {
+    fn parse_class(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, ParseError> {
+        let span = self.span();
+
         // Advance past the keyword
         self.pos += 1;
 
@@ -489,26 +845,28 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 (pos, name)
             }
-            _ => return Err("Expected identifier in prototype declaration."),
+            _ => return Err(self.error("a class name")),
         };
 
         self.advance_optional_space();
 
         match self.curr() {
             Token::NewLine(_) => self.advance(),
-            _ => return Err("Expected a new line after class name"),
+            _ => return Err(self.error("a new line after the class name")),
         };
 
-        let attributes = self.parse_attributes().unwrap();
+        let attributes = self.parse_attributes()?;
 
         let class_node = Class {
             name: class_name.clone(),
             attributes,
+            span,
         };
 
         mctx.class_name = class_name.clone();
         mctx.self_node = Some(Node::SelfRef(SelfRef {
             return_type: BaseType::Class(mctx.class_name.clone()),
+            span,
         }));
 
         let mut functions = vec![];
@@ -530,7 +888,7 @@ impl<'a> Parser<'a> {
                     self.advance();
                     break;
                 }
-                _ => return Err("Expected def, impl, or end to to the class."),
+                _ => return Err(self.error("`def`, `impl` or `end` in the class")),
             };
 
             for result in results? {
@@ -545,6 +903,7 @@ impl<'a> Parser<'a> {
             let mut args = vec![Arg {
                 name: "sret".to_string(),
                 return_type: BaseType::Class(mctx.class_name.clone()),
+                span,
             }];
 
             let mut body = vec![];
@@ -553,6 +912,7 @@ impl<'a> Parser<'a> {
                 args.push(Arg {
                     name: attribute.name.clone(),
                     return_type: attribute.return_type.clone(),
+                    span: attribute.span,
                 });
 
                 body.push(Node::AssignAttribute(AssignAttribute {
@@ -561,7 +921,10 @@ impl<'a> Parser<'a> {
                     value: Box::new(Node::LocalVar(LocalVar {
                         name: attribute.name.clone(),
                         return_type: Some(attribute.return_type.clone()),
+                        declaration: None,
+                        span: attribute.span,
                     })),
+                    span: attribute.span,
                 }))
             }
 
@@ -571,6 +934,7 @@ impl<'a> Parser<'a> {
                 return_type: Some(BaseType::Class(class_name.clone())),
                 is_op: false,
                 prec: 0,
+                span,
             };
 
             self.index
@@ -584,6 +948,7 @@ impl<'a> Parser<'a> {
                 class_name: mctx.class_name.clone(),
                 impl_name: "".to_string(),
                 trait_name: "".to_string(),
+                span,
             });
 
             functions.push(new_fn);
@@ -592,6 +957,7 @@ impl<'a> Parser<'a> {
             let mut args = vec![Arg {
                 name: "sret".to_string(),
                 return_type: BaseType::Class(mctx.class_name.clone()),
+                span,
             }];
 
             let mut body = vec![];
@@ -602,6 +968,7 @@ impl<'a> Parser<'a> {
                 return_type: Some(BaseType::Class(class_name.clone())),
                 is_op: false,
                 prec: 0,
+                span,
             };
 
             self.index
@@ -615,6 +982,7 @@ impl<'a> Parser<'a> {
                 class_name: mctx.class_name.clone(),
                 impl_name: "".to_string(),
                 trait_name: "".to_string(),
+                span,
             });
 
             functions.push(new_fn);
@@ -630,7 +998,9 @@ impl<'a> Parser<'a> {
         Ok(functions)
     }
 
-    fn parse_struct(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, &'static str> {
+    fn parse_struct(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, ParseError> {
+        let span = self.span();
+
         // Advance past the keyword
         self.pos += 1;
 
@@ -641,24 +1011,24 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 (pos, name)
             }
-            _ => return Err("Expected identifier in prototype declaration."),
+            _ => return Err(self.error("a struct name")),
         };
 
         self.advance_optional_space();
 
         match self.curr() {
             Token::NewLine(_) => self.advance(),
-            _ => return Err("Expected a new line after class name"),
+            _ => return Err(self.error("a new line after the struct name")),
         };
 
-        let attributes = self.parse_attributes().unwrap();
+        let attributes = self.parse_attributes()?;
 
         match self.current()? {
             Token::End => {
                 self.advance();
                 self.advance_optional_whitespace();
             }
-            _ => return Err("Expected End to struct"),
+            _ => return Err(self.error("`end` to close the struct")),
         }
 
         // let attr_return_types = attributes.iter().map(|attr| attr.return_type.clone()).collect();
@@ -668,6 +1038,7 @@ impl<'a> Parser<'a> {
             name: struct_name.clone(),
             attributes,
             return_type,
+            span,
         };
 
         self.index
@@ -680,7 +1051,7 @@ impl<'a> Parser<'a> {
         Ok(vec![])
     }
 
-    fn parse_attributes(&mut self) -> Result<Vec<Attribute>, &'static str> {
+    fn parse_attributes(&mut self) -> Result<Vec<Attribute>, ParseError> {
         let mut attributes = vec![];
         let mut index = 0;
         loop {
@@ -688,6 +1059,7 @@ impl<'a> Parser<'a> {
 
             match self.current()? {
                 Token::Attribute(_attr_pos, attr_name) => {
+                    let span = self.span();
                     self.advance();
                     self.advance_optional_whitespace();
 
@@ -701,7 +1073,7 @@ impl<'a> Parser<'a> {
 
                             let length = match self.current()? {
                                 Token::Number(_, n) => n,
-                                _ => return Err("Expected length of array"),
+                                _ => return Err(self.error("the array length, as in `[4 x Byte]`")),
                             };
 
                             self.advance();
@@ -713,9 +1085,9 @@ impl<'a> Parser<'a> {
                                         self.advance();
                                         self.advance_optional_space();
                                     }
-                                    _ => return Err("Expected an 'x' for such as [4 x Byte]"),
+                                    _ => return Err(self.error("`x`, as in `[4 x Byte]`")),
                                 },
-                                _ => return Err("Expected type for array 1"),
+                                _ => return Err(self.error("`x`, as in `[4 x Byte]`")),
                             };
 
                             let array_return_type = match self.current()? {
@@ -723,23 +1095,24 @@ impl<'a> Parser<'a> {
                                     self.advance();
                                     self.class_base_type(type_name)
                                 }
-                                _ => return Err("Expected type for array 2"),
+                                _ => return Err(self.error("the item type, as in `[4 x Byte]`")),
                             };
 
                             match self.current()? {
                                 Token::RSquareBrace => self.advance(),
-                                _ => return Err("Expected ] to end array type"),
+                                _ => return Err(self.error("`]` to close the array type")),
                             };
 
                             BaseType::Array(length as i64, Box::new(array_return_type))
                         }
-                        _ => return Err("Expected a type after the attribute name"),
+                        _ => return Err(self.error("a type after the attribute name")),
                     };
 
                     attributes.push(Attribute {
                         name: attr_name,
                         index,
                         return_type,
+                        span,
                     });
                     index += 1;
                 }
@@ -750,7 +1123,7 @@ impl<'a> Parser<'a> {
         Ok(attributes)
     }
 
-    fn parse_trait(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, &'static str> {
+    fn parse_trait(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, ParseError> {
         let mut functions = vec![];
 
         // Advance past the keyword
@@ -763,14 +1136,14 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 name
             }
-            _ => return Err("Expected identifier in prototype declaration."),
+            _ => return Err(self.error("a trait name")),
         };
 
         self.advance_optional_space();
 
         match self.curr() {
             Token::NewLine(_) => self.advance(),
-            _ => return Err("Expected a new line after class name"),
+            _ => return Err(self.error("a new line after the trait name")),
         };
 
         loop {
@@ -785,8 +1158,7 @@ impl<'a> Parser<'a> {
                     break;
                 }
                 _ => {
-                    println!("{:#?}", self.curr());
-                    return Err("Expected only def within a trait");
+                    return Err(self.error("`def` or `end` in the trait"));
                 }
             };
 
@@ -802,7 +1174,9 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         class_name: String,
-    ) -> Result<Vec<Node>, &'static str> {
+    ) -> Result<Vec<Node>, ParseError> {
+        let span = self.span();
+
         // Advance past the keyword
         self.pos += 1;
 
@@ -813,20 +1187,21 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 name
             }
-            _ => return Err("Expected identifier in impl declaration."),
+            _ => return Err(self.error("an impl name")),
         };
 
         self.advance_optional_space();
 
         match self.curr() {
             Token::NewLine(_) => self.advance(),
-            _ => return Err("Expected a new line after impl name"),
+            _ => return Err(self.error("a new line after the impl name")),
         };
 
         if let Some(nodes) = self.index.trait_index.get_mut(&impl_name) {
             nodes.push(Class {
                 name: class_name.clone(),
                 attributes: vec![],
+                span,
             });
         } else {
             self.index.trait_index.insert(
@@ -835,11 +1210,10 @@ impl<'a> Parser<'a> {
                     (Class {
                         name: class_name.clone(),
                         attributes: vec![],
+                        span,
                     }),
                 ],
             );
-
-            println!("{:#?}", self);
         };
 
         let mut functions = vec![];
@@ -860,7 +1234,7 @@ impl<'a> Parser<'a> {
                     break;
                 }
                 _ => {
-                    return Err("Expected only def within an impl block");
+                    return Err(self.error("`def` or `end` in the impl block"));
                 }
             };
 
@@ -879,7 +1253,9 @@ impl<'a> Parser<'a> {
         impl_name: String,
         trait_name: String,
         new_function: Option<&Def>,
-    ) -> Result<Vec<Node>, &'static str> {
+    ) -> Result<Vec<Node>, ParseError> {
+        let span = self.span();
+
         // Advance past 'def' keyword
         self.pos += 1;
 
@@ -910,20 +1286,27 @@ impl<'a> Parser<'a> {
                     break;
                 }
                 _ => {
-                    let expr = self.parse_expr(mctx, &ctx)?;
+                    let expr = self.parse_statement(mctx, &ctx)?;
                     ctx.body.push(expr);
                     ctx.parsing_returnable_loc = true
                 }
             }
         }
 
+        // Entry points that return an exit status. `main` always does, and
+        // `_mlir_ciface_main` does when it's declared `-> Int32`.
+        let main_fn = ctx.prototype.name == "main"
+            || (ctx.prototype.name == "_mlir_ciface_main"
+                && ctx.prototype.return_type == Some(BaseType::Int32));
+
         let def_node = Def {
-            main_fn: ctx.prototype.name == "main",
+            main_fn,
             prototype: ctx.prototype,
             body: ctx.body,
             class_name: ctx.class_name,
             impl_name,
             trait_name,
+            span,
         };
 
         // let namespaced_fn_name = format!("{}.{}", mctx.class_name.clone(), def_node.prototype.name.clone());
@@ -963,7 +1346,9 @@ impl<'a> Parser<'a> {
         // self.index.fn_index.insert(fn_name, arg_return_types);
     }
 
-    fn parse_def_e(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, &'static str> {
+    fn parse_def_e(&mut self, mctx: &mut ParserModuleCtx) -> Result<Vec<Node>, ParseError> {
+        let span = self.span();
+
         // Advance past 'def' keyword
         self.pos += 1;
 
@@ -971,7 +1356,7 @@ impl<'a> Parser<'a> {
 
         self.advance_optional_whitespace();
 
-        let def_e_node = DefE { prototype };
+        let def_e_node = DefE { prototype, span };
 
         // let mut arg_return_types = vec![];
 
@@ -993,21 +1378,26 @@ impl<'a> Parser<'a> {
     }
 
     /// Parses the prototype of a function, whether external or user-defined.
-    fn parse_prototype(&mut self, mctx: &mut ParserModuleCtx) -> Result<Prototype, &'static str> {
+    fn parse_prototype(&mut self, mctx: &mut ParserModuleCtx) -> Result<Prototype, ParseError> {
         match self.current()? {
             Token::Space(_) => {
                 self.advance();
             }
-            _ => return Err("Expected space after def keyword"),
+            _ => return Err(self.error("a space after `def`")),
         }
 
+        let span = self.span();
         let (id, is_operator, precedence) = match self.curr() {
             Token::Ident(pos, id) => {
                 self.advance()?;
 
                 (id, false, 0)
             }
-            _ => return Err("Expected identifier in prototype declaration."),
+            Token::Binary | Token::Unary if !mctx.class_name.is_empty() => {
+                return Err(self.error("a method name, operators are defined at the top level"))
+            }
+            Token::Binary | Token::Unary => self.parse_operator_name()?,
+            _ => return Err(self.error("a function name")),
         };
 
         let mut id = id;
@@ -1020,6 +1410,7 @@ impl<'a> Parser<'a> {
             args.push(Arg {
                 name: "sret".to_string(),
                 return_type: BaseType::Class(mctx.class_name.clone()),
+                span,
             });
 
             id = format!("{}.{}", mctx.class_name, id);
@@ -1034,6 +1425,7 @@ impl<'a> Parser<'a> {
                     return_type,
                     is_op: is_operator,
                     prec: precedence,
+                    span,
                 });
             }
             Token::LParen => {
@@ -1048,11 +1440,11 @@ impl<'a> Parser<'a> {
                     return_type: None,
                     is_op: is_operator,
                     prec: precedence,
+                    span,
                 });
             }
             _ => {
-                println!("{:#?}", self.curr());
-                return Err("Expected '(' character in prototype declaration. 2");
+                return Err(self.error("`(` to start the parameter list"));
             }
         }
 
@@ -1069,6 +1461,7 @@ impl<'a> Parser<'a> {
                 return_type,
                 is_op: is_operator,
                 prec: precedence,
+                span,
             });
         }
 
@@ -1077,9 +1470,10 @@ impl<'a> Parser<'a> {
 
             // println!("{:#?}", self.curr());
 
+            let arg_span = self.span();
             let arg_name = match self.curr() {
                 Token::Ident(pos, name) => name,
-                _ => return Err("Expected identifier in parameter declaration."),
+                _ => return Err(self.error("a parameter name")),
             };
 
             self.advance()?;
@@ -1092,7 +1486,7 @@ impl<'a> Parser<'a> {
 
                     let length = match self.current()? {
                         Token::Number(_, n) => n,
-                        _ => return Err("Expected length of array"),
+                        _ => return Err(self.error("the array length, as in `[4 x Byte]`")),
                     };
 
                     self.advance_optional_space();
@@ -1106,10 +1500,10 @@ impl<'a> Parser<'a> {
                                     self.advance();
                                     self.advance_optional_space();
                                 }
-                                _ => return Err("Expected an 'x' for such as [4 x Byte]"),
+                                _ => return Err(self.error("`x`, as in `[4 x Byte]`")),
                             }
                         }
-                        _ => return Err("Expected type for array 3"),
+                        _ => return Err(self.error("`x`, as in `[4 x Byte]`")),
                     };
 
                     let array_return_type = match self.current()? {
@@ -1117,22 +1511,23 @@ impl<'a> Parser<'a> {
                             self.advance();
                             self.class_base_type(type_name)
                         }
-                        _ => return Err("Expected type for array 4"),
+                        _ => return Err(self.error("the item type, as in `[4 x Byte]`")),
                     };
 
                     match self.current()? {
                         Token::RSquareBrace => self.advance(),
-                        _ => return Err("Expected ] to end array type"),
+                        _ => return Err(self.error("`]` to close the array type")),
                     };
 
                     BaseType::Array(length as i64, Box::new(array_return_type))
                 }
-                _ => return Err("Expected type name for argument"),
+                _ => return Err(self.error("a parameter type")),
             };
 
             args.push(Arg {
                 name: arg_name,
                 return_type,
+                span: arg_span,
             });
 
             self.advance()?;
@@ -1146,7 +1541,7 @@ impl<'a> Parser<'a> {
                 Token::Comma => {
                     self.advance();
                 }
-                _ => return Err("Expected ',' or ')' character in prototype declaration. 2"),
+                _ => return Err(self.error("`,` or `)` in the parameter list")),
             }
         }
 
@@ -1158,10 +1553,57 @@ impl<'a> Parser<'a> {
             return_type,
             is_op: is_operator,
             prec: precedence,
+            span,
         })
     }
 
-    fn parse_return_type(&mut self) -> Result<Option<BaseType>, &'static str> {
+    /// Parses the name of an operator definition, `binary<> 5` or `unary<>`,
+    /// into the name of the function it defines, which uses of the operator
+    /// call, and its precedence. A binary operator is registered in
+    /// `op_precedence`.
+    fn parse_operator_name(&mut self) -> Result<(String, bool, usize), ParseError> {
+        let is_binary = matches!(self.curr(), Token::Binary);
+        let kind = if is_binary { "binary" } else { "unary" };
+
+        self.advance()?;
+
+        let (op, length) = match self.current()? {
+            Token::Op(_) => self.current_op(|_| true),
+            _ => return Err(self.error("an operator, as in `binary<> 5` or `unary<>`")),
+        };
+
+        let name = operator_fn_name(kind, &op);
+        let is_builtin = if is_binary {
+            self.op_precedence.contains_key(&op)
+                && !self.index.fn_prototype_index.contains_key(&name)
+        } else {
+            matches!(op, ['-' | '!' | '~', '\0', '\0', '\0'])
+        };
+
+        if is_builtin {
+            return Err(self.error("an operator that isn't built in"));
+        }
+
+        self.pos += length;
+
+        if !is_binary {
+            return Ok((name, true, 0));
+        }
+
+        self.advance_optional_space();
+
+        let precedence = match self.current()? {
+            Token::Number(_, precedence) if precedence <= 100 => precedence,
+            _ => return Err(self.error("a precedence up to 100, as in `binary<> 5`")),
+        };
+
+        self.advance()?;
+        self.op_precedence.insert(op, precedence as i32);
+
+        Ok((name, true, precedence as usize))
+    }
+
+    fn parse_return_type(&mut self) -> Result<Option<BaseType>, ParseError> {
         match self.current()? {
             Token::NewLine(_) => {
                 self.advance()?;
@@ -1183,10 +1625,10 @@ impl<'a> Parser<'a> {
                     //     self.advance();
                     //     return Ok(None);
                     // }
-                    _ => return Err("Expected an arrow to indicate a return type"),
+                    _ => return Err(self.error("`->` before the return type")),
                 }
             }
-            _ => return Err("Expected an end to the function definition"),
+            _ => return Err(self.error("`->` or a new line after the parameters")),
         }
 
         match self.curr() {
@@ -1194,7 +1636,7 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 Ok(Some(self.class_base_type(type_name)))
             }
-            _ => Err("Expected a return type after an arrow"),
+            _ => Err(self.error("a return type after `->`")),
         }
     }
 
@@ -1202,7 +1644,7 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         match self.parse_unary_expr(mctx, ctx) {
             Ok(left) => {
                 self.advance_optional_whitespace();
@@ -1212,32 +1654,67 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Parses an unary expression.
-    fn parse_unary_expr(
+    /// Parses an expression in a body, which can be followed by a postfix
+    /// `if` on the same line.
+    fn parse_statement(
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
-        let op = match self.current()? {
-            Token::Op(ch) => {
+    ) -> Result<Node, ParseError> {
+        let expr = self.parse_expr(mctx, ctx)?;
+
+        match self.current() {
+            Ok(Token::If) if !self.newline_before() => {
                 self.advance()?;
-                ch
+                self.advance_optional_whitespace();
+
+                let condition = self.parse_expr(mctx, ctx)?;
+
+                Ok(Node::If(If {
+                    span: expr.span(),
+                    condition: Box::new(condition),
+                    then_body: vec![expr],
+                    else_body: None,
+                    return_type: None,
+                }))
             }
+            _ => Ok(expr),
+        }
+    }
+
+    /// Parses an unary expression.
+    fn parse_unary_expr(
+        &mut self,
+        mctx: &mut ParserModuleCtx,
+        ctx: &ParserFunctionCtx,
+    ) -> Result<Node, ParseError> {
+        let span = self.span();
+        let (op, length) = match self.current()? {
+            Token::Op(_) => self.current_op(|op| {
+                let name = operator_fn_name("unary", op);
+                self.index.fn_prototype_index.contains_key(&name)
+            }),
             _ => return self.parse_primary(mctx, ctx),
         };
 
-        let mut name = String::from("unary");
-        let op_name: String = op
-            .iter() // Create an iterator over the array
-            .filter(|&&c| c != '\0') // Exclude null characters
-            .collect();
+        self.pos += length - 1;
+        self.advance()?;
 
-        name.push_str(&op_name);
+        let operand = self.parse_unary_expr(mctx, ctx)?;
+
+        if let ['-' | '!' | '~', '\0', '\0', '\0'] = op {
+            return Ok(Node::Unary(Unary {
+                op,
+                operand: Box::new(operand),
+                span,
+            }));
+        }
 
         Ok(Node::Call(Call {
-            fn_name: name,
-            args: vec![self.parse_unary_expr(mctx, ctx)?],
+            fn_name: operator_fn_name("unary", &op),
+            args: vec![operand],
             return_type: None,
+            span,
         }))
     }
 
@@ -1245,7 +1722,7 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         // println!("current:");
         // println!("{:#?}", self.curr());
 
@@ -1253,20 +1730,22 @@ impl<'a> Parser<'a> {
             Token::Attribute(_, _) => self.parse_attribute_expr(mctx, ctx),
             Token::Const(_, _) => self.parse_const_expr(mctx, ctx),
             Token::Ident(_, _) => self.parse_ident_expr(mctx, ctx),
+            Token::If => self.parse_if_expr(mctx, ctx),
+            Token::Break => self.parse_break_expr(mctx, ctx),
             Token::Loop => self.parse_loop_expr(mctx, ctx),
+            Token::Next => self.parse_next_expr(),
+            Token::While | Token::Until => self.parse_while_expr(mctx, ctx),
             Token::LParen => self.parse_paren_expr(mctx, ctx),
             Token::LSquareBrace => self.parse_array_expr(mctx, ctx),
-            Token::Number(_, _) => self.parse_nb_expr(),
+            Token::Number(_, _) | Token::Float(_, _) => self.parse_nb_expr(),
+            Token::Bool(_, _) => self.parse_bool_expr(),
             Token::Ret => self.parse_ret_expr(mctx, ctx),
             Token::SelfRef => self.parse_self_ref_expr(mctx, ctx),
             Token::StringLiteral(_, _) => self.parse_string_expr(),
             _ => {
-                println!("Debug:");
-                println!("{:#?}", self.curr());
-
                 // panic!("{:#?}", self.curr());
                 // panic!("{:#?}", self);
-                Err("Unknown expression.")
+                Err(self.error("an expression"))
             }
         };
 
@@ -1282,9 +1761,10 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         match self.curr() {
             Token::Attribute(pos, name) => {
+                let span = self.span();
                 self.advance();
                 self.advance_optional_whitespace();
 
@@ -1295,12 +1775,14 @@ impl<'a> Parser<'a> {
 
                 let receiver = Box::new(Node::SelfRef(SelfRef {
                     return_type: BaseType::Class(mctx.class_name.clone()),
+                    span,
                 }));
 
                 let message = Box::new(Node::Attribute(Attribute {
                     name,
                     index: 0,
                     return_type: BaseType::Class("".to_string()),
+                    span,
                 }));
 
                 Ok(Node::Access(Access {
@@ -1308,9 +1790,10 @@ impl<'a> Parser<'a> {
                     message,
                     index: 0,
                     return_type: None,
+                    span,
                 }))
             }
-            _ => Err("Expected SelfRef"),
+            _ => Err(self.error("`self`")),
         }
     }
 
@@ -1318,20 +1801,24 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         match self.curr() {
             Token::Ret => {
                 if !ctx.parsing_returnable_loc {
-                    return Err("Return can only be used at the root of a function.");
+                    return Err(self.error(
+                        "an expression, `ret` is only allowed at the root of a function",
+                    ));
                 }
+                let span = self.span();
                 self.advance()?;
                 self.advance_optional_whitespace();
 
                 Ok(Node::Ret(Ret {
                     value: Box::new(self.parse_expr(mctx, ctx)?),
+                    span,
                 }))
             }
-            _ => Err("Expected Ret"),
+            _ => Err(self.error("`ret`")),
         }
     }
 
@@ -1339,16 +1826,18 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         match self.curr() {
             Token::SelfRef => {
+                let span = self.span();
                 self.advance();
 
                 Ok(Node::SelfRef(SelfRef {
                     return_type: BaseType::Class(ctx.class_name.clone()),
+                    span,
                 }))
             }
-            _ => Err("Expected SelfRef"),
+            _ => Err(self.error("`self`")),
         }
     }
 
@@ -1357,13 +1846,14 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
+        let span = self.span();
         let ident_name = match self.curr() {
             Token::Ident(pos, id) => {
                 self.advance();
                 id
             }
-            _ => return Err("Expected identifier."),
+            _ => return Err(self.error("an identifier")),
         };
 
         self.advance_optional_whitespace();
@@ -1380,6 +1870,7 @@ impl<'a> Parser<'a> {
                         fn_name: ident_name,
                         args: vec![],
                         return_type: None,
+                        span,
                     }));
                 }
 
@@ -1400,7 +1891,7 @@ impl<'a> Parser<'a> {
                         Token::Comma => {
                             self.advance();
                         }
-                        _ => return Err("Expected ',' or ')' character in function call."),
+                        _ => return Err(self.error("`,` or `)` in the argument list")),
                     }
                 }
 
@@ -1408,6 +1899,7 @@ impl<'a> Parser<'a> {
                     fn_name: ident_name,
                     args,
                     return_type: None,
+                    span,
                 }))
             }
 
@@ -1422,85 +1914,17 @@ impl<'a> Parser<'a> {
                         Ok(Node::AssignLocalVar(AssignLocalVar {
                             name: ident_name,
                             value: Box::new(self.parse_expr(mctx, ctx)?),
+                            span,
                         }))
                     }
-                    _ => {
-                        // After all that, it's just a lvar. Fetch the type from the nearest assignment.
-                        let closest_assignment = ctx.body.iter().rev().find(|node| match node {
-                            Node::AssignLocalVar(asgnLvar) => asgnLvar.name == ident_name,
-                            _ => false,
-                        });
-
-                        match closest_assignment {
-                            Some(asgnLvar) => match asgnLvar {
-                                Node::AssignLocalVar(asgnLvar) => {
-                                    let return_type_name = match asgnLvar.value.as_ref() {
-                                        Node::Call(call) => {
-                                            self.pajama_class_name(&call.return_type)
-                                        }
-                                        Node::Int(_) => "Int".to_string(),
-                                        Node::LocalVar(val) => val.pajama_class_name().to_string(),
-                                        Node::Send(send) => {
-                                            self.pajama_class_name(&send.return_type)
-                                        }
-                                        Node::StringLiteral(_) => "Str".to_string(),
-                                        Node::BuildStruct(build) => {
-                                            return Ok(Node::LocalVar(LocalVar {
-                                                name: ident_name,
-                                                return_type: Some(build.return_type.clone()),
-                                            }))
-                                        }
-                                        Node::Array(array) => {
-                                            return Ok(Node::LocalVar(LocalVar {
-                                                name: ident_name,
-                                                return_type: Some(BaseType::Array(
-                                                    array.length,
-                                                    Box::new(array.item_type.clone()),
-                                                )),
-                                            }))
-                                        }
-                                        Node::Binary(binary) => {
-                                            // todo: Only Int is currently supported
-                                            "Int".to_string()
-                                        }
-                                        _ => {
-                                            println!("{:#?}", asgnLvar.value.as_ref());
-                                            return Err("Local variable assignment was given an unsupprted node, given");
-                                        }
-                                    };
-
-                                    Ok(Node::LocalVar(LocalVar {
-                                        name: ident_name,
-                                        return_type: Some(BaseType::Class(return_type_name)),
-                                    }))
-                                }
-                                _ => Err("Node other than AssignLocalVar in closest_assignment"),
-                            },
-                            None => {
-                                let arg_assignment = ctx
-                                    .prototype
-                                    .args
-                                    .iter()
-                                    .find(|node| node.name == ident_name);
-
-                                println!("{:#?}", ident_name);
-
-                                match arg_assignment {
-                                    Some(arg) => Ok(Node::LocalVar(LocalVar {
-                                        name: ident_name,
-                                        return_type: Some(BaseType::Class(
-                                            arg.pajama_class_name().to_string(),
-                                        )),
-                                    })),
-                                    // maybe a function reference, or just a typo lool
-                                    None => Ok(Node::LocalVar(LocalVar {
-                                        name: ident_name,
-                                        return_type: None,
-                                    })),
-                                }
-                            }
-                        }
-                    }
+                    // Bound to its declaration and typed by the resolver and
+                    // the semantic analyzer
+                    _ => Ok(Node::LocalVar(LocalVar {
+                        name: ident_name,
+                        return_type: None,
+                        declaration: None,
+                        span,
+                    })),
                 }
             }
         }
@@ -1510,13 +1934,14 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-        receiver: Result<Node, &'static str>,
-    ) -> Result<Node, &'static str> {
+        receiver: Result<Node, ParseError>,
+    ) -> Result<Node, ParseError> {
         let receiver = match receiver {
             Ok(node) => node,
             Err(err) => return Err(err),
         };
 
+        let span = receiver.span();
         self.advance();
 
         let node = match self.peek()? {
@@ -1525,6 +1950,7 @@ impl<'a> Parser<'a> {
                     receiver: Box::new(receiver),
                     message: Box::new(node),
                     return_type: None,
+                    span,
                 })),
                 Err(err) => return Err(err),
             },
@@ -1534,6 +1960,7 @@ impl<'a> Parser<'a> {
                     message: Box::new(node),
                     index: 0,
                     return_type: None,
+                    span,
                 })),
                 Err(err) => return Err(err),
             },
@@ -1553,8 +1980,8 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-        receiver: Result<Node, &'static str>,
-    ) -> Result<Node, &'static str> {
+        receiver: Result<Node, ParseError>,
+    ) -> Result<Node, ParseError> {
         let receiver = match receiver {
             Ok(node) => node,
             Err(err) => return Err(err),
@@ -1563,10 +1990,11 @@ impl<'a> Parser<'a> {
         self.advance();
         self.advance_optional_whitespace();
 
-        let value = Box::new(self.parse_expr(mctx, ctx).unwrap());
+        let value = Box::new(self.parse_expr(mctx, ctx)?);
 
         match receiver {
             Node::Access(access) => Ok(Node::AssignAttributeAccess(AssignAttributeAccess {
+                span: access.span,
                 access,
                 value,
             })),
@@ -1575,11 +2003,16 @@ impl<'a> Parser<'a> {
             Node::AssignLocalVar(_) => todo!(),
             Node::Attribute(_) => todo!(),
             Node::Binary(_) => todo!(),
+            Node::Bool(_) => todo!(),
             Node::Call(_) => todo!(),
             Node::Class(_) => todo!(),
             Node::Const(_) => todo!(),
             Node::Def(_) => todo!(),
             Node::DefE(_) => todo!(),
+            Node::Float(_) => todo!(),
+            Node::If(_) => todo!(),
+            Node::Break(_) => todo!(),
+            Node::Next(_) => todo!(),
             Node::Impl(_) => todo!(),
             Node::Int(_) => todo!(),
             Node::LocalVar(_) => todo!(),
@@ -1590,6 +2023,7 @@ impl<'a> Parser<'a> {
             Node::Send(_) => todo!(),
             Node::StringLiteral(_) => todo!(),
             Node::Trait(_) => todo!(),
+            Node::Unary(_) => todo!(),
             Node::AssignConstant(_) => todo!(),
             Node::Struct(_) => todo!(),
             Node::BuildStruct(_) => todo!(),
@@ -1602,7 +2036,7 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         self.parse_ident_expr(mctx, ctx)
     }
 
@@ -1610,39 +2044,63 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         match self.current()? {
             Token::Ident(_pos, ident_name) => {
+                let span = self.span();
                 self.advance()?;
                 Ok(Node::Attribute(Attribute {
                     name: ident_name,
                     index: 0,
                     return_type: BaseType::Class("".to_string()),
+                    span,
                 }))
             }
-            _ => Err("Expected Identifier for attribute access"),
+            _ => Err(self.error("an attribute or method name after `.`")),
         }
     }
 
     /// Parses a literal number.
-    fn parse_nb_expr(&mut self) -> Result<Node, &'static str> {
+    fn parse_nb_expr(&mut self) -> Result<Node, ParseError> {
         match self.curr() {
             Token::Number(pos, nb) => {
+                let span = self.span();
+                self.advance();
+                Ok(Node::Int(Int { value: nb, span }))
+            }
+            Token::Float(_, value) => {
+                let span = self.span();
+                self.advance();
+                Ok(Node::Float(Float { value, span }))
+            }
+            _ => Err(self.error("a number")),
+        }
+    }
+
+    /// Parses `true` or `false`.
+    fn parse_bool_expr(&mut self) -> Result<Node, ParseError> {
+        match self.curr() {
+            Token::Bool(_, value) => {
+                let span = self.span();
                 self.advance();
-                Ok(Node::Int(Int { value: nb }))
+                Ok(Node::Bool(Bool { value, span }))
             }
-            _ => Err("Expected number literal."),
+            _ => Err(self.error("`true` or `false`")),
         }
     }
 
     /// Parses a literal string.
-    fn parse_string_expr(&mut self) -> Result<Node, &'static str> {
+    fn parse_string_expr(&mut self) -> Result<Node, ParseError> {
         match self.curr() {
             Token::StringLiteral(pos, string) => {
+                let span = self.span();
                 self.advance();
-                Ok(Node::StringLiteral(StringLiteral { value: string }))
+                Ok(Node::StringLiteral(StringLiteral {
+                    value: string,
+                    span,
+                }))
             }
-            _ => Err("Expected string literal."),
+            _ => Err(self.error("a string literal")),
         }
     }
 
@@ -1650,7 +2108,7 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         // match self.curr() {
         //     Token::Const(pos, string) => {
         //         self.advance();
@@ -1659,12 +2117,13 @@ impl<'a> Parser<'a> {
         //     _ => Err("Expected string literal."),
         // }
 
+        let span = self.span();
         let const_name = match self.curr() {
             Token::Const(pos, name) => {
                 self.advance();
                 name
             }
-            _ => return Err("Expected string literal."),
+            _ => return Err(self.error("a struct name")),
         };
 
         match self.curr() {
@@ -1673,7 +2132,7 @@ impl<'a> Parser<'a> {
                 self.advance_optional_whitespace();
 
                 if let Token::RParen = self.curr() {
-                    return Err("At least one struct field is required");
+                    return Err(self.error("at least one struct field"));
                 }
 
                 let mut args = vec![];
@@ -1693,7 +2152,7 @@ impl<'a> Parser<'a> {
                         Token::Comma => {
                             self.advance();
                         }
-                        _ => return Err("Expected ',' or ')' character in struct build."),
+                        _ => return Err(self.error("`,` or `)` in the struct fields")),
                     }
                 }
 
@@ -1701,9 +2160,13 @@ impl<'a> Parser<'a> {
                     name: const_name.clone(),
                     args,
                     return_type: BaseType::Struct(const_name.clone()),
+                    span,
                 }))
             }
-            _ => Ok(Node::Const(Const { name: const_name })),
+            _ => Ok(Node::Const(Const {
+                name: const_name,
+                span,
+            })),
         }
     }
 
@@ -1712,10 +2175,10 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         match self.current()? {
             Token::LParen => (),
-            _ => return Err("Expected '(' character at start of parenthesized expression."),
+            _ => return Err(self.error("`(`")),
         }
 
         self.advance_optional_whitespace();
@@ -1727,7 +2190,7 @@ impl<'a> Parser<'a> {
 
         match self.current()? {
             Token::RParen => self.advance()?,
-            _ => return Err("Expected ')' character at end of parenthesized expression."),
+            _ => return Err(self.error("`)` to close the parenthesized expression")),
         };
 
         Ok(expr)
@@ -1737,10 +2200,11 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
+        let span = self.span();
         match self.current()? {
             Token::LSquareBrace => (),
-            _ => return Err("Expected '[' character at start of an array."),
+            _ => return Err(self.error("`[`")),
         }
 
         self.advance_optional_whitespace();
@@ -1756,6 +2220,7 @@ impl<'a> Parser<'a> {
                     items,
                     item_type: default_type,
                     length: 0,
+                    span,
                 }));
             }
             _ => {}
@@ -1776,7 +2241,7 @@ impl<'a> Parser<'a> {
                 Token::Comma => {
                     self.advance();
                 }
-                _ => return Err("Expected ',' or ']' character in array."),
+                _ => return Err(self.error("`,` or `]` in the array")),
             }
         }
 
@@ -1790,6 +2255,7 @@ impl<'a> Parser<'a> {
             items,
             item_type,
             length,
+            span,
         }))
     }
 
@@ -1797,13 +2263,14 @@ impl<'a> Parser<'a> {
         &mut self,
         mctx: &mut ParserModuleCtx,
         ctx: &ParserFunctionCtx,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
+        let span = self.span();
         self.pos += 1; // Advance past 'loop' keyword
         self.advance_optional_whitespace();
 
         match self.current()? {
             Token::LCurlyBrace => self.advance()?,
-            _ => return Err("Expected a curly brace after loop"),
+            _ => return Err(self.error("`{` after `loop`")),
         }
 
         let mut body = vec![];
@@ -1817,16 +2284,152 @@ impl<'a> Parser<'a> {
                     break;
                 }
                 _ => {
-                    body.push(self.parse_expr(mctx, &ctx)?);
+                    body.push(self.parse_statement(mctx, &ctx)?);
                 }
             }
         }
 
-        let loop_node = Loop { body };
+        let loop_node = Loop {
+            condition: None,
+            until: false,
+            body,
+            return_type: None,
+            span,
+        };
 
         Ok(Node::Loop(loop_node))
     }
 
+    /// Parses `while cond ... end` and `until cond ... end`.
+    fn parse_while_expr(
+        &mut self,
+        mctx: &mut ParserModuleCtx,
+        ctx: &ParserFunctionCtx,
+    ) -> Result<Node, ParseError> {
+        let span = self.span();
+        let until = matches!(self.curr(), Token::Until);
+        self.pos += 1; // Advance past 'while' or 'until' keyword
+        self.advance_optional_whitespace();
+
+        let condition = self.parse_expr(mctx, ctx)?;
+        let mut body = vec![];
+
+        loop {
+            self.advance_optional_whitespace();
+
+            match self.current()? {
+                Token::End => {
+                    self.advance();
+                    break;
+                }
+                _ => body.push(self.parse_statement(mctx, ctx)?),
+            }
+        }
+
+        Ok(Node::Loop(Loop {
+            condition: Some(Box::new(condition)),
+            until,
+            body,
+            return_type: None,
+            span,
+        }))
+    }
+
+    /// Parses `break`, and the value it breaks with when one follows on the
+    /// same line.
+    fn parse_break_expr(
+        &mut self,
+        mctx: &mut ParserModuleCtx,
+        ctx: &ParserFunctionCtx,
+    ) -> Result<Node, ParseError> {
+        let span = self.span();
+        self.pos += 1; // Advance past 'break' keyword
+        self.advance_optional_space();
+
+        let value = match self.current() {
+            Ok(Token::NewLine(_) | Token::Comment(_, _))
+            | Ok(Token::If | Token::End | Token::RCurlyBrace)
+            | Err(_) => None,
+            Ok(_) => Some(Box::new(self.parse_expr(mctx, ctx)?)),
+        };
+
+        Ok(Node::Break(Break {
+            value,
+            return_type: None,
+            span,
+        }))
+    }
+
+    fn parse_next_expr(&mut self) -> Result<Node, ParseError> {
+        let span = self.span();
+        self.pos += 1; // Advance past 'next' keyword
+
+        Ok(Node::Next(Next { span }))
+    }
+
+    /// Parses `if` up to its `end`. Each `elsif` is parsed as an `if` of its
+    /// own, which takes the `end`.
+    fn parse_if_expr(
+        &mut self,
+        mctx: &mut ParserModuleCtx,
+        ctx: &ParserFunctionCtx,
+    ) -> Result<Node, ParseError> {
+        let span = self.span();
+        self.pos += 1; // Advance past 'if' or 'elsif' keyword
+        self.advance_optional_whitespace();
+
+        let condition = self.parse_expr(mctx, ctx)?;
+        let then_body = self.parse_branch(mctx, ctx)?;
+
+        let else_body = match self.current()? {
+            Token::Elsif => Some(vec![self.parse_if_expr(mctx, ctx)?]),
+            Token::Else => {
+                self.advance()?;
+                let body = self.parse_branch(mctx, ctx)?;
+
+                match self.current()? {
+                    Token::End => {
+                        self.advance();
+                    }
+                    _ => return Err(self.error("`end` after `else`")),
+                }
+
+                Some(body)
+            }
+            _ => {
+                self.advance();
+                None
+            }
+        };
+
+        Ok(Node::If(If {
+            condition: Box::new(condition),
+            then_body,
+            else_body,
+            return_type: None,
+            span,
+        }))
+    }
+
+    /// Parses the body of a branch, up to the `elsif`, `else` or `end` that
+    /// ends it.
+    fn parse_branch(
+        &mut self,
+        mctx: &mut ParserModuleCtx,
+        ctx: &ParserFunctionCtx,
+    ) -> Result<Vec<Node>, ParseError> {
+        let mut body = vec![];
+
+        loop {
+            self.advance_optional_whitespace();
+
+            match self.current()? {
+                Token::Elsif | Token::Else | Token::End => return Ok(body),
+                _ => body.push(self.parse_statement(mctx, ctx)?),
+            }
+        }
+    }
+
     /// Parses a binary expression, given its left-hand expression.
     fn parse_binary_expr(
         &mut self,
@@ -1834,7 +2437,7 @@ impl<'a> Parser<'a> {
         ctx: &ParserFunctionCtx,
         prec: i32,
         mut left: Node,
-    ) -> Result<Node, &'static str> {
+    ) -> Result<Node, ParseError> {
         loop {
             if let Ok(Token::End) = self.current() {
                 // self.advance()?;
@@ -1847,11 +2450,13 @@ impl<'a> Parser<'a> {
                 return Ok(left);
             }
 
-            let op = match self.curr() {
-                Token::Op(op) => op,
-                _ => return Err("Invalid operator."),
+            let span = self.span();
+            let (op, length) = match self.curr() {
+                Token::Op(_) => self.current_op(|op| self.op_precedence.contains_key(op)),
+                _ => return Err(self.error("an operator")),
             };
 
+            self.pos += length - 1;
             self.advance()?;
             self.advance_optional_whitespace();
 
@@ -1860,21 +2465,69 @@ impl<'a> Parser<'a> {
 
             self.advance_optional_whitespace();
 
-            if curr_prec < next_prec {
+            // `**` is right associative, `2 ** 3 ** 2` is `2 ** (3 ** 2)`
+            if op == ['*', '*', '\0', '\0'] && curr_prec == next_prec {
+                right = self.parse_binary_expr(mctx, ctx, curr_prec, right)?;
+            } else if curr_prec < next_prec {
                 right = self.parse_binary_expr(mctx, ctx, curr_prec + 1, right)?;
             }
 
-            left = Node::Binary(Binary {
-                op,
-                left: Box::new(left),
-                right: Box::new(right),
-            });
+            let fn_name = operator_fn_name("binary", &op);
+
+            // A user-defined operator calls the function defining it
+            left = if self.index.fn_prototype_index.contains_key(&fn_name) {
+                Node::Call(Call {
+                    fn_name,
+                    args: vec![left, right],
+                    return_type: None,
+                    span,
+                })
+            } else {
+                Node::Binary(Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    span,
+                })
+            };
+        }
+    }
+
+    /// The operator spelled by the current `Op` token and the operator tokens
+    /// right after it, and how many tokens it takes. The lexer only joins the
+    /// characters of built-in operators, so a user-defined one like `<>` is
+    /// the longest run that `is_defined` accepts, or else the current token.
+    fn current_op(&self, is_defined: impl Fn(&[char; 4]) -> bool) -> ([char; 4], usize) {
+        let mut spelled = String::new();
+        let mut found = (['\0'; 4], 1);
+
+        for (count, token) in self.tokens[self.pos..].iter().enumerate() {
+            match token {
+                Token::Op(op) => spelled.extend(op.iter().filter(|&&ch| ch != '\0')),
+                Token::Assign => spelled.push('='),
+                _ => break,
+            }
+
+            if spelled.chars().count() > 4 {
+                break;
+            }
+
+            let mut op = ['\0'; 4];
+            for (slot, ch) in op.iter_mut().zip(spelled.chars()) {
+                *slot = ch;
+            }
+
+            if count == 0 || is_defined(&op) {
+                found = (op, count + 1);
+            }
         }
+
+        found
     }
 
-    fn peek(&self) -> Result<Token, &'static str> {
+    fn peek(&self) -> Result<Token, ParseError> {
         if self.pos + 1 >= self.tokens.len() {
-            Err("Peeked at end of file")
+            Err(self.error("more input"))
         } else {
             Ok(self.tokens[self.pos + 1].clone())
         }
@@ -1887,9 +2540,9 @@ impl<'a> Parser<'a> {
 
     /// Returns the current `Token`, or an error that
     /// indicates that the end of the file has been unexpectedly reached if it is the case.
-    fn current(&self) -> Result<Token, &'static str> {
+    fn current(&self) -> Result<Token, ParseError> {
         if self.pos >= self.tokens.len() {
-            Err("Position doesn't match the token count")
+            Err(self.error("more input"))
         } else {
             Ok(self.tokens[self.pos].clone())
         }
@@ -1898,7 +2551,7 @@ impl<'a> Parser<'a> {
     /// Advances the position, and returns an empty `Result` whose error
     /// indicates that the end of the file has been unexpectedly reached.
     /// This allows to use the `self.advance()?;` syntax.
-    fn advance(&mut self) -> Result<(), &'static str> {
+    fn advance(&mut self) -> Result<(), ParseError> {
         let npos = self.pos + 1;
 
         self.pos = npos;
@@ -1906,11 +2559,11 @@ impl<'a> Parser<'a> {
         if npos < self.tokens.len() {
             Ok(())
         } else {
-            Err("Unexpected end of file.")
+            Err(self.error("more input"))
         }
     }
 
-    fn advance_token(&mut self) -> Result<Token, &'static str> {
+    fn advance_token(&mut self) -> Result<Token, ParseError> {
         let npos = self.pos + 1;
 
         self.pos = npos;
@@ -1918,7 +2571,7 @@ impl<'a> Parser<'a> {
         if npos < self.tokens.len() {
             Ok(self.curr())
         } else {
-            Err("Unexpected end of file.")
+            Err(self.error("more input"))
         }
     }
 
@@ -1939,6 +2592,20 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Whether the whitespace before the current token has a new line in it.
+    fn newline_before(&self) -> bool {
+        self.tokens[..self.pos]
+            .iter()
+            .rev()
+            .take_while(|token| {
+                matches!(
+                    token,
+                    Token::Space(_) | Token::NewLine(_) | Token::Comment(_, _)
+                )
+            })
+            .any(|token| matches!(token, Token::NewLine(_) | Token::Comment(_, _)))
+    }
+
     fn advance_optional_space(&mut self) {
         match self.current() {
             Ok(token) => match token {
@@ -1951,6 +2618,87 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Skips the rest of a top level item that failed to parse, up to the next
+    /// line that starts another item or just past an `end` that starts a line,
+    /// which closes the failed item.
+    fn synchronize(&mut self, item_start: usize) {
+        self.pos = item_start + 1;
+
+        while let Some(token) = self.tokens.get(self.pos) {
+            if self.positions[self.pos].start_column() == 1 {
+                match token {
+                    Token::Class
+                    | Token::Const(_, _)
+                    | Token::Def
+                    | Token::DefE
+                    | Token::Struct
+                    | Token::Trait => return,
+                    Token::End => {
+                        self.pos += 1;
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+
+            self.pos += 1;
+        }
+    }
+
+    /// The span of the current token, or of the last one at the end of the
+    /// input.
+    fn span(&self) -> Span {
+        self.span_at(self.pos)
+    }
+
+    fn span_at(&self, index: usize) -> Span {
+        match self.positions.get(index).or(self.positions.last()) {
+            Some(position) => self.source_map.span(position),
+            None => Span::default(),
+        }
+    }
+
+    /// An error for the current token, or for the end of the input when all
+    /// the tokens have been consumed.
+    fn error(&self, expected: &'static str) -> ParseError {
+        self.error_at(self.pos, expected)
+    }
+
+    fn error_at(&self, index: usize, expected: &'static str) -> ParseError {
+        let (position, found) = match self.tokens.get(index) {
+            Some(token) => (self.positions[index].clone(), token.describe()),
+            None => {
+                // Just past the last token
+                let position = match self.positions.last() {
+                    Some(last) => {
+                        let column = last.end_column() + 1;
+                        TokenPosition::new(last.line(), column, column)
+                    }
+                    None => TokenPosition::new(1, 1, 1),
+                };
+
+                (position, "end of file".to_string())
+            }
+        };
+
+        let source_line = self
+            .source
+            .lines()
+            .nth(position.line() - 1)
+            .unwrap_or("")
+            .to_string();
+
+        let span = self.source_map.span(&position);
+
+        ParseError {
+            expected,
+            found,
+            span,
+            file_name: self.source_map.file_name(span.file).map(str::to_string),
+            source_line,
+        }
+    }
+
     /// Returns a value indicating whether or not the `Parser`
     /// has reached the end of the input.
     fn at_end(&self) -> bool {
@@ -1959,7 +2707,8 @@ impl<'a> Parser<'a> {
 
     /// Returns the precedence of the current `Token`, or 0 if it is not recognized as a binary operator.
     fn get_tok_precedence(&self) -> i32 {
-        if let Ok(Token::Op(op)) = self.current() {
+        if let Ok(Token::Op(_)) = self.current() {
+            let (op, _) = self.current_op(|op| self.op_precedence.contains_key(op));
             *self.op_precedence.get(&op).unwrap_or(&100)
         } else {
             -1
@@ -1973,6 +2722,9 @@ impl<'a> Parser<'a> {
                 BaseType::Byte => "Byte".to_string(),
                 BaseType::BytePtr => "BytePtr".to_string(),
                 BaseType::Class(class_name) => class_name.to_string(),
+                BaseType::Bool => "Bool".to_string(),
+                BaseType::Float32 => "Float32".to_string(),
+                BaseType::Float64 => "Float64".to_string(),
                 BaseType::FnRef => "FnRef".to_string(),
                 BaseType::Int => "Int".to_string(),
                 BaseType::Int16 => "Int16".to_string(),
@@ -1988,8 +2740,11 @@ impl<'a> Parser<'a> {
     pub fn class_base_type(&self, type_name: String) -> BaseType {
         match type_name.as_str() {
             // "Array" => BaseType::Array(_, _),
+            "Bool" => BaseType::Bool,
             "Byte" => BaseType::Byte,
             "BytePtr" => BaseType::BytePtr,
+            "Float32" => BaseType::Float32,
+            "Float64" => BaseType::Float64,
             "Int" => BaseType::Int,
             "Int16" => BaseType::Int16,
             "Int32" => BaseType::Int32,
@@ -1999,3 +2754,11 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+/// The name of the function a user-defined operator calls, `binary<>` for
+/// the binary operator `<>`.
+fn operator_fn_name(kind: &str, op: &[char; 4]) -> String {
+    let op: String = op.iter().filter(|&&ch| ch != '\0').collect();
+
+    format!("{}{}", kind, op)
+}
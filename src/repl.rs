@@ -0,0 +1,303 @@
+use melior::ir::{Location, Module};
+use melior::ExecutionEngine;
+use pajama_runtime::PjStr;
+
+use crate::codegen::{Compiler, C_INTERFACE_PREFIX};
+use crate::lexer::{Lexer, Token};
+use crate::pajama_compiler::{CompileError, CompileOptions};
+use crate::parser::{BaseType, Def, Node, ParserResult};
+use crate::semantic_analyzer::Diagnostic;
+use crate::session::{guard, Session};
+
+/// Keeps the definitions entered so far (classes, `def`s, `def_e`s, ...) and
+/// evaluates expressions against them. Each expression is compiled into a
+/// fresh function along with every definition, so earlier definitions stay
+/// callable, then JIT compiled and run. Local variables don't outlive the
+/// expression that assigns them.
+pub struct Repl {
+    session: Session,
+    definitions: String,
+    evaluations: usize,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Repl {
+    pub fn new(options: CompileOptions) -> Repl {
+        Repl {
+            session: Session::new(options),
+            definitions: String::new(),
+            evaluations: 0,
+            warnings: vec![],
+        }
+    }
+
+    /// Whether `input` can be evaluated, `false` while it still has a block
+    /// that isn't closed.
+    pub fn is_complete(&self, input: &str) -> bool {
+        let Ok(tokens) = guard("lexing", || Ok(Lexer::new(input).tokenize())) else {
+            // Let `eval` report the error
+            return true;
+        };
+
+        let mut depth = 0;
+        let mut previous = None;
+
+        for token in &tokens {
+            match token {
+                Token::Class
+                | Token::Def
+                | Token::Impl
+                | Token::Struct
+                | Token::Trait
+                | Token::Until
+                | Token::While
+                | Token::LCurlyBrace
+                | Token::LParen
+                | Token::LSquareBrace => depth += 1,
+                // A postfix `if`, after an expression on the same line, has no `end`
+                Token::If if !previous.is_some_and(ends_expression) => depth += 1,
+                Token::End | Token::RCurlyBrace | Token::RParen | Token::RSquareBrace => depth -= 1,
+                _ => {}
+            }
+
+            if !matches!(token, Token::Space(_)) {
+                previous = Some(token);
+            }
+        }
+
+        depth <= 0
+    }
+
+    /// Adds the definitions in `input` to the session, or evaluates it when
+    /// it's an expression and returns its value formatted for display. An
+    /// input with a definition that doesn't compile is dropped.
+    pub fn eval(&mut self, input: &str) -> Result<Option<String>, CompileError> {
+        let token_stream = self.session.tokenize(input)?;
+
+        if is_definition(&token_stream.tokens) {
+            self.define(input)?;
+            Ok(None)
+        } else {
+            self.evaluate(input)
+        }
+    }
+
+    /// Adds every definition in `input` to the session.
+    pub fn define(&mut self, input: &str) -> Result<(), CompileError> {
+        let source = format!("{}{}\n", self.definitions, input);
+        let parser_result = self.check(&source)?;
+
+        self.session.generate(&parser_result)?;
+        self.definitions = source;
+
+        Ok(())
+    }
+
+    fn evaluate(&mut self, input: &str) -> Result<Option<String>, CompileError> {
+        let fn_name = format!("_repl_{}", self.evaluations);
+        self.evaluations += 1;
+
+        // The engine runs `fn_name` through its C interface, so the function
+        // is defined under the name of its C interface
+        let def_name = format!("{}{}", C_INTERFACE_PREFIX, fn_name);
+        let source = format!("{}def {}\n{}\nend\n", self.definitions, def_name, input);
+        let mut parser_result = self.check(&source)?;
+
+        let result_type = self
+            .result_type(&parser_result, &def_name)?
+            .filter(is_displayable);
+
+        set_return_type(&mut parser_result, &def_name, result_type.clone());
+
+        let mut mlir_module = self.session.generate(&parser_result)?;
+        self.session.lower(&mut mlir_module)?;
+
+        let engine = self.session.jit(&mlir_module)?;
+
+        match result_type {
+            Some(result_type) => invoke_and_format(&engine, &fn_name, &result_type).map(Some),
+            None => invoke(&engine, &fn_name, ()).map(|_| None),
+        }
+    }
+
+    /// The warnings about the inputs defined or evaluated since the last call.
+    pub fn take_warnings(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Checks `source`, the definitions so far followed by an input. Only the
+    /// warnings about the input are kept, the definitions were warned about
+    /// when they were entered.
+    fn check(&mut self, source: &str) -> Result<ParserResult, CompileError> {
+        let result = self.session.check(source);
+        let defined_lines = self.definitions.lines().count();
+
+        self.warnings.extend(
+            self.session
+                .take_warnings()
+                .into_iter()
+                .filter(|warning| warning.span.line > defined_lines),
+        );
+
+        result
+    }
+
+    /// The type of the last expression in `fn_name`, as codegen sees it.
+    fn result_type(
+        &self,
+        parser_result: &ParserResult,
+        fn_name: &str,
+    ) -> Result<Option<BaseType>, CompileError> {
+        let Some(last_node) = find_def(parser_result, fn_name).and_then(|def| def.body.last())
+        else {
+            return Ok(None);
+        };
+
+        match last_node {
+            Node::AssignAttribute(_)
+            | Node::AssignAttributeAccess(_)
+            | Node::AssignConstant(_)
+            | Node::AssignLocalVar(_)
+            | Node::Loop(_)
+            | Node::Ret(_) => return Ok(None),
+            _ => {}
+        }
+
+        let mlir_module = Module::new(Location::unknown(self.session.context()));
+
+        guard("codegen", || {
+            let compiler = Compiler::new(self.session.context(), &mlir_module, parser_result);
+
+            Ok(compiler.node_base_type(last_node))
+        })
+    }
+}
+
+/// Whether the input starts with a top level definition rather than an
+/// expression.
+fn is_definition(tokens: &[Token]) -> bool {
+    let mut tokens = tokens.iter().filter(|token| {
+        !matches!(
+            token,
+            Token::Space(_) | Token::NewLine(_) | Token::Comment(..)
+        )
+    });
+
+    match tokens.next() {
+        Some(Token::Class | Token::Def | Token::DefE | Token::Struct | Token::Trait) => true,
+        Some(Token::Const(..)) => matches!(tokens.next(), Some(Token::Assign)),
+        _ => false,
+    }
+}
+
+/// Whether an expression can end with the token, so that an `if` right after
+/// it is a postfix one.
+fn ends_expression(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Attribute(_, _)
+            | Token::Bool(_, _)
+            | Token::Break
+            | Token::Const(_, _)
+            | Token::End
+            | Token::Float(_, _)
+            | Token::Ident(_, _)
+            | Token::Next
+            | Token::Number(_, _)
+            | Token::RCurlyBrace
+            | Token::RParen
+            | Token::RSquareBrace
+            | Token::SelfRef
+            | Token::StringLiteral(_, _)
+    )
+}
+
+/// Types the REPL can pass out of the JIT and print, arrays and structs are
+/// evaluated without printing their value.
+fn is_displayable(base_type: &BaseType) -> bool {
+    !matches!(
+        base_type,
+        BaseType::Array(..) | BaseType::Struct(_) | BaseType::Void
+    )
+}
+
+fn find_def<'a>(parser_result: &'a ParserResult, fn_name: &str) -> Option<&'a Def> {
+    let Node::Module(module) = &parser_result.module else {
+        return None;
+    };
+
+    module.methods.iter().find_map(|node| match node {
+        Node::Def(def) if def.prototype.name == fn_name => Some(def),
+        _ => None,
+    })
+}
+
+fn set_return_type(parser_result: &mut ParserResult, fn_name: &str, return_type: Option<BaseType>) {
+    if let Node::Module(module) = &mut parser_result.module {
+        for node in &mut module.methods {
+            if let Node::Def(def) = node {
+                if def.prototype.name == fn_name {
+                    def.prototype.return_type = return_type.clone();
+                }
+            }
+        }
+    }
+
+    if let Some(prototype) = parser_result.index.fn_prototype_index.get_mut(fn_name) {
+        prototype.return_type = return_type;
+    }
+}
+
+/// Runs `fn_name` with `result` as the slot its value is written to, `()` for
+/// functions that don't return one.
+fn invoke<T>(engine: &ExecutionEngine, fn_name: &str, mut result: T) -> Result<T, CompileError> {
+    guard("execution", || unsafe {
+        if std::mem::size_of::<T>() == 0 {
+            engine.invoke_packed(fn_name, &mut [])
+        } else {
+            engine.invoke_packed(fn_name, &mut [&mut result as *mut T as *mut ()])
+        }
+        .map_err(CompileError::Execution)
+    })?;
+
+    Ok(result)
+}
+
+fn invoke_and_format(
+    engine: &ExecutionEngine,
+    fn_name: &str,
+    result_type: &BaseType,
+) -> Result<String, CompileError> {
+    let formatted = match result_type {
+        BaseType::Byte => invoke(engine, fn_name, 0u8)?.to_string(),
+        BaseType::Int16 => invoke(engine, fn_name, 0i16)?.to_string(),
+        BaseType::Int32 => invoke(engine, fn_name, 0i32)?.to_string(),
+        BaseType::Int | BaseType::Int64 => invoke(engine, fn_name, 0i64)?.to_string(),
+        BaseType::Bool => (invoke(engine, fn_name, 0u8)? != 0).to_string(),
+        BaseType::Float32 => invoke(engine, fn_name, 0f32)?.to_string(),
+        BaseType::Float64 => invoke(engine, fn_name, 0f64)?.to_string(),
+        BaseType::Class(name) if name == "Str" => {
+            let pj_str = invoke(engine, fn_name, std::ptr::null::<PjStr>())?;
+
+            match unsafe { pj_str.as_ref() } {
+                Some(pj_str) => format!("{:?}", pj_str.to_string_lossy()),
+                None => "null".to_string(),
+            }
+        }
+        BaseType::Class(name) => {
+            format!(
+                "#<{} {:?}>",
+                name,
+                invoke(engine, fn_name, std::ptr::null::<()>())?
+            )
+        }
+        BaseType::BytePtr | BaseType::FnRef => {
+            format!("{:?}", invoke(engine, fn_name, std::ptr::null::<()>())?)
+        }
+        BaseType::Array(..) | BaseType::Struct(_) | BaseType::Void => {
+            unreachable!("not displayable")
+        }
+    };
+
+    Ok(formatted)
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use crate::parser::{self, BaseType, Node, ParserResult, ParserResultIndex};
+use crate::semantic_analyzer::Diagnostics;
+use crate::source::Span;
+
+/// What a scope belongs to. Lookups don't go past the function a scope is
+/// in, so that closures can be given scopes of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Function,
+    Loop,
+    Branch,
+}
+
+/// The local variables declared in a function or in one of its blocks, by
+/// name to where they're declared. They go away when the block ends.
+#[derive(Debug)]
+struct Scope {
+    kind: ScopeKind,
+    symbols: HashMap<String, Span>,
+}
+
+/// Binds every `LocalVar` to its declaration through the scopes it's nested
+/// in, and reports the variables, functions and classes a program uses but
+/// never declares. Runs before the semantic analyzer, which relies on every
+/// name being declared to infer the types.
+pub struct Resolver<'a> {
+    index: &'a ParserResultIndex,
+    scopes: Vec<Scope>,
+    diagnostics: Diagnostics,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn run(result: &mut ParserResult) -> Diagnostics {
+        let mut resolver = Resolver {
+            index: &result.index,
+            scopes: vec![],
+            diagnostics: Diagnostics::default(),
+        };
+
+        resolver.check_declared_types();
+
+        if let Node::Module(module) = &mut result.module {
+            for node in &mut module.methods {
+                match node {
+                    Node::Def(def_node) => resolver.resolve_def(def_node),
+                    Node::DefE(def_e_node) => resolver.check_prototype(&def_e_node.prototype),
+                    _ => {}
+                }
+            }
+        }
+
+        resolver.diagnostics.locate(&result.source_map);
+        resolver.diagnostics
+    }
+
+    /// The attributes of classes and structs, in the order they're declared.
+    fn check_declared_types(&mut self) {
+        let index = self.index;
+        let mut attributes: Vec<&parser::Attribute> = index
+            .class_index
+            .values()
+            .flat_map(|class| &class.attributes)
+            .chain(
+                index
+                    .struct_index
+                    .values()
+                    .flat_map(|struct_node| &struct_node.attributes),
+            )
+            .collect();
+
+        attributes.sort_by_key(|attribute| {
+            (
+                attribute.span.file.0,
+                attribute.span.line,
+                attribute.span.start_column,
+            )
+        });
+
+        for attribute in attributes {
+            self.check_type(&attribute.return_type, attribute.span);
+        }
+    }
+
+    fn check_prototype(&mut self, prototype: &parser::Prototype) {
+        for arg in &prototype.args {
+            self.check_type(&arg.return_type, arg.span);
+        }
+
+        if let Some(return_type) = &prototype.return_type {
+            self.check_type(return_type, prototype.span);
+        }
+
+        // Uses of an operator pass its operands as the arguments
+        if prototype.is_op {
+            let (kind, params) = if prototype.name.starts_with("binary") {
+                ("binary", 2)
+            } else {
+                ("unary", 1)
+            };
+
+            if prototype.args.len() != params {
+                self.diagnostics.error(
+                    prototype.span,
+                    format!(
+                        "a {} operator takes {} parameter{}, `{}` has {}",
+                        kind,
+                        params,
+                        if params == 1 { "" } else { "s" },
+                        prototype.name,
+                        prototype.args.len()
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_type(&mut self, base_type: &BaseType, span: Span) {
+        match base_type {
+            BaseType::Array(_, item_type) => self.check_type(item_type, span),
+            BaseType::Class(name) | BaseType::Struct(name) => {
+                if !self.is_type_name(name) {
+                    self.diagnostics
+                        .error(span, format!("unknown class `{}`", name));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_type_name(&self, name: &str) -> bool {
+        self.index.class_index.contains_key(name)
+            || self.index.struct_index.contains_key(name)
+            || self.index.trait_index.contains_key(name)
+    }
+
+    fn resolve_def(&mut self, def_node: &mut parser::Def) {
+        self.check_prototype(&def_node.prototype);
+        self.push_scope(ScopeKind::Function);
+
+        for arg in &def_node.prototype.args {
+            self.declare(&arg.name, arg.span);
+        }
+
+        for node in &mut def_node.body {
+            self.resolve_node(node);
+        }
+
+        self.scopes.pop();
+    }
+
+    fn resolve_node(&mut self, node: &mut Node) {
+        match node {
+            Node::AssignLocalVar(assign_node) => {
+                self.resolve_node(&mut assign_node.value);
+
+                // Assigning to a variable of an enclosing scope reassigns it
+                if self.lookup(&assign_node.name).is_none() {
+                    self.declare(&assign_node.name, assign_node.span);
+                }
+            }
+            Node::LocalVar(lvar) => self.resolve_local_var(lvar, false),
+            Node::Call(call_node) => {
+                for arg in &mut call_node.args {
+                    self.resolve_node(arg);
+                }
+
+                if !self
+                    .index
+                    .fn_prototype_index
+                    .contains_key(&call_node.fn_name)
+                {
+                    self.diagnostics.error(
+                        call_node.span,
+                        format!("undefined function `{}`", call_node.fn_name),
+                    );
+                }
+            }
+            Node::Send(send_node) => {
+                match send_node.receiver.as_mut() {
+                    Node::LocalVar(lvar) => self.resolve_local_var(lvar, true),
+                    receiver => self.resolve_node(receiver),
+                }
+
+                // The method depends on the type of the receiver, it's looked
+                // up by the semantic analyzer
+                if let Node::Call(call_node) = send_node.message.as_mut() {
+                    for arg in &mut call_node.args {
+                        self.resolve_node(arg);
+                    }
+                }
+            }
+            Node::Const(const_node) => {
+                if !self.is_type_name(&const_node.name)
+                    && !self.index.constant_index.contains_key(&const_node.name)
+                {
+                    self.diagnostics.error(
+                        const_node.span,
+                        format!("unknown class or constant `{}`", const_node.name),
+                    );
+                }
+            }
+            Node::BuildStruct(struct_node) => {
+                for arg in &mut struct_node.args {
+                    self.resolve_node(arg);
+                }
+
+                if !self.index.struct_index.contains_key(&struct_node.name) {
+                    self.diagnostics.error(
+                        struct_node.span,
+                        format!("unknown struct `{}`", struct_node.name),
+                    );
+                }
+            }
+            Node::Access(access_node) => self.resolve_node(&mut access_node.receiver),
+            Node::Array(array) => {
+                for item in &mut array.items {
+                    self.resolve_node(item);
+                }
+            }
+            Node::AssignAttribute(assign_node) => self.resolve_node(&mut assign_node.value),
+            Node::AssignAttributeAccess(assign_node) => {
+                self.resolve_node(&mut assign_node.access.receiver);
+                self.resolve_node(&mut assign_node.value);
+            }
+            Node::Binary(binary_node) => {
+                self.resolve_node(&mut binary_node.left);
+                self.resolve_node(&mut binary_node.right);
+            }
+            Node::Unary(unary_node) => self.resolve_node(&mut unary_node.operand),
+            Node::Ret(ret_node) => self.resolve_node(&mut ret_node.value),
+            Node::Loop(loop_node) => {
+                if let Some(condition) = &mut loop_node.condition {
+                    self.resolve_node(condition);
+                }
+
+                self.push_scope(ScopeKind::Loop);
+
+                for node in &mut loop_node.body {
+                    self.resolve_node(node);
+                }
+
+                self.scopes.pop();
+            }
+            Node::If(if_node) => {
+                self.resolve_node(&mut if_node.condition);
+
+                for body in std::iter::once(&mut if_node.then_body).chain(&mut if_node.else_body) {
+                    self.push_scope(ScopeKind::Branch);
+
+                    for node in body {
+                        self.resolve_node(node);
+                    }
+
+                    self.scopes.pop();
+                }
+            }
+            Node::Break(break_node) => {
+                if !self.in_loop() {
+                    self.diagnostics
+                        .error(break_node.span, "`break` outside of a loop");
+                }
+
+                if let Some(value) = &mut break_node.value {
+                    self.resolve_node(value);
+                }
+            }
+            Node::Next(next_node) => {
+                if !self.in_loop() {
+                    self.diagnostics
+                        .error(next_node.span, "`next` outside of a loop");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Binds the variable to its declaration. The receiver of a send can also
+    /// name a function, to take a reference to it.
+    fn resolve_local_var(&mut self, lvar: &mut parser::LocalVar, is_receiver: bool) {
+        if let Some(declaration) = self.lookup(&lvar.name) {
+            lvar.declaration = Some(declaration);
+        } else if !(is_receiver && self.index.fn_prototype_index.contains_key(&lvar.name)) {
+            self.diagnostics.error(
+                lvar.span,
+                format!("undefined local variable `{}`", lvar.name),
+            );
+        }
+    }
+
+    fn push_scope(&mut self, kind: ScopeKind) {
+        self.scopes.push(Scope {
+            kind,
+            symbols: HashMap::new(),
+        });
+    }
+
+    fn declare(&mut self, name: &str, span: Span) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.symbols.insert(name.to_string(), span);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Span> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(declaration) = scope.symbols.get(name) {
+                return Some(*declaration);
+            }
+
+            if scope.kind == ScopeKind::Function {
+                break;
+            }
+        }
+
+        None
+    }
+
+    /// Whether the current scope is in a loop of the current function.
+    fn in_loop(&self) -> bool {
+        self.scopes
+            .iter()
+            .rev()
+            .take_while(|scope| scope.kind != ScopeKind::Function)
+            .any(|scope| scope.kind == ScopeKind::Loop)
+    }
+}
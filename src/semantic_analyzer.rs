@@ -1,6 +1,7 @@
-use std::{borrow::BorrowMut, collections::HashMap, hash::Hash, ops::Deref};
+use std::{collections::HashMap, fmt};
 
-use crate::parser::{self, BaseType, Def, Node, Parser, ParserResult, Struct};
+use crate::parser::{self, BaseType, Node, ParserResult};
+use crate::source::{SourceMap, Span};
 
 #[derive(Debug)]
 pub struct SemanticAnalyzer {
@@ -8,17 +9,113 @@ pub struct SemanticAnalyzer {
     pub diagnostics: Diagnostics,
 }
 
-#[derive(Debug)]
-pub struct Diagnostics {}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem with the program, located at the node it was found on.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// The file the span is in, when the source came from files
+    pub file_name: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if self.span.line == 0 {
+            return Ok(());
+        }
+
+        let gutter = " ".repeat(self.span.line.to_string().len());
+        match &self.file_name {
+            Some(file_name) => write!(
+                f,
+                "\n{}--> {}:{}:{}",
+                gutter, file_name, self.span.line, self.span.start_column
+            ),
+            None => write!(
+                f,
+                "\n{}--> {}:{}",
+                gutter, self.span.line, self.span.start_column
+            ),
+        }
+    }
+}
+
+/// Everything the analyzer found wrong with the program. Any error fails the
+/// compilation, warnings are handed back to the caller to report.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.errors.push(Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+            file_name: None,
+        });
+    }
+
+    pub fn warning(&mut self, span: Span, message: impl Into<String>) {
+        self.warnings.push(Diagnostic {
+            severity: Severity::Warning,
+            message: message.into(),
+            span,
+            file_name: None,
+        });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Adds the diagnostics of another pass.
+    pub fn append(&mut self, other: Diagnostics) {
+        self.errors.extend(other.errors);
+        self.warnings.extend(other.warnings);
+    }
+
+    /// Names the file of every diagnostic, once the analysis is done.
+    pub(crate) fn locate(&mut self, source_map: &SourceMap) {
+        for diagnostic in self.errors.iter_mut().chain(self.warnings.iter_mut()) {
+            diagnostic.file_name = source_map
+                .file_name(diagnostic.span.file)
+                .map(str::to_string);
+        }
+    }
+}
 
 impl SemanticAnalyzer {
-    pub fn run(result: &mut ParserResult) -> SemanticAnalyzer {
-        Self::transform_ast(result)
+    /// Infers the types the parser couldn't in place and returns what's
+    /// wrong with the program.
+    pub fn run(result: &mut ParserResult) -> Diagnostics {
+        Self::transform_ast(result).diagnostics
     }
 
     pub fn transform_ast(result: &mut ParserResult) -> SemanticAnalyzer {
         let mut attribute_index = HashMap::new();
         let mut method_index = HashMap::new();
+        let mut diagnostics = Diagnostics::default();
 
         match &mut result.module {
             Node::Module(module) => {
@@ -29,14 +126,18 @@ impl SemanticAnalyzer {
                     method_index,
                     attribute_index,
                     &result.index.struct_index,
+                    &mut diagnostics,
                 );
             }
-            _ => todo!(),
+            node => diagnostics.error(
+                node.span(),
+                format!("expected a module, found {}", describe_node(node)),
+            ),
         }
 
-        SemanticAnalyzer {
-            diagnostics: Diagnostics {},
-        }
+        diagnostics.locate(&result.source_map);
+
+        SemanticAnalyzer { diagnostics }
     }
 }
 
@@ -75,453 +176,427 @@ fn populate_method_index(
     });
 }
 
+/// The indexes every visitor looks types up in.
+struct Indexes<'a> {
+    attribute_index: &'a HashMap<String, (i32, BaseType)>,
+    method_index: &'a HashMap<String, Option<BaseType>>,
+    struct_index: &'a HashMap<String, parser::Struct>,
+}
+
 fn run_type_inference(
     module: &mut crate::parser::Module,
-    mut method_index: HashMap<String, Option<BaseType>>,
-    mut attribute_index: HashMap<String, (i32, BaseType)>,
+    method_index: HashMap<String, Option<BaseType>>,
+    attribute_index: HashMap<String, (i32, BaseType)>,
     struct_index: &HashMap<String, parser::Struct>,
+    diagnostics: &mut Diagnostics,
 ) {
+    let indexes = Indexes {
+        attribute_index: &attribute_index,
+        method_index: &method_index,
+        struct_index,
+    };
+
     module.methods.iter_mut().for_each(|node| {
+        if let Node::Def(def_node) = node {
+            let mut lvar_index = HashMap::new();
+
+            def_node.prototype.args.iter().for_each(|arg| {
+                lvar_index.insert(arg.name.clone(), Some(arg.return_type.clone()));
+            });
+
+            visit_body(&indexes, &mut lvar_index, &mut def_node.body, diagnostics);
+        }
+    });
+}
+
+/// Visits the statements of a body and returns the type of the last one.
+fn visit_body(
+    indexes: &Indexes,
+    lvar_index: &mut HashMap<String, Option<BaseType>>,
+    body: &mut [Node],
+    diagnostics: &mut Diagnostics,
+) -> Option<BaseType> {
+    let mut returned = false;
+    let mut last_type = None;
+
+    for node in body {
+        if returned {
+            diagnostics.warning(node.span(), "unreachable code after `ret`");
+            returned = false;
+        }
+
+        if let Node::Ret(_) = node {
+            returned = true;
+        }
+
+        last_type = visit_statement(indexes, lvar_index, node, diagnostics);
+    }
+
+    last_type
+}
+
+/// Visits a statement and returns the type of its value, when it has one.
+fn visit_statement(
+    indexes: &Indexes,
+    lvar_index: &mut HashMap<String, Option<BaseType>>,
+    node: &mut Node,
+    diagnostics: &mut Diagnostics,
+) -> Option<BaseType> {
+    match node {
+        Node::AssignLocalVar(assignlocalvar_node) => {
+            let return_type = visit_value_node(
+                indexes,
+                lvar_index,
+                assignlocalvar_node.value.as_mut(),
+                diagnostics,
+            );
+
+            lvar_index.insert(assignlocalvar_node.name.clone(), return_type.clone());
+            return_type
+        }
+        Node::AssignAttribute(assign_attr_node) => {
+            visit_value_node(
+                indexes,
+                lvar_index,
+                assign_attr_node.value.as_mut(),
+                diagnostics,
+            );
+            None
+        }
+        Node::AssignAttributeAccess(node) => {
+            visit_access_node(indexes, lvar_index, &mut node.access, diagnostics);
+            visit_value_node(indexes, lvar_index, node.value.as_mut(), diagnostics);
+            None
+        }
+        Node::Ret(ret_node) => {
+            visit_ret_node(indexes, lvar_index, ret_node, diagnostics);
+            None
+        }
+        Node::Loop(loop_node) => {
+            visit_loop_node(indexes, lvar_index, loop_node, false, diagnostics);
+            None
+        }
+        Node::If(if_node) => visit_if_node(indexes, lvar_index, if_node, false, diagnostics),
+        Node::Break(break_node) => {
+            if let Some(value) = &mut break_node.value {
+                break_node.return_type =
+                    visit_value_node(indexes, lvar_index, value.as_mut(), diagnostics);
+            }
+
+            None
+        }
+        Node::Next(_) => None,
+        Node::Access(_)
+        | Node::Array(_)
+        | Node::Binary(_)
+        | Node::Bool(_)
+        | Node::Call(_)
+        | Node::Float(_)
+        | Node::Int(_)
+        | Node::LocalVar(_)
+        | Node::Send(_)
+        | Node::StringLiteral(_)
+        | Node::Unary(_) => visit_value_node(indexes, lvar_index, node, diagnostics),
+        node => {
+            diagnostics.error(
+                node.span(),
+                format!("{} isn't allowed in a function body", describe_node(node)),
+            );
+            None
+        }
+    }
+}
+
+/// Visits an expression and returns its type, when it can be inferred.
+fn visit_value_node(
+    indexes: &Indexes,
+    lvar_index: &HashMap<String, Option<BaseType>>,
+    node: &mut Node,
+    diagnostics: &mut Diagnostics,
+) -> Option<BaseType> {
+    match node {
+        Node::Access(access_node) => {
+            visit_access_node(indexes, lvar_index, access_node, diagnostics)
+        }
+        Node::Binary(node) => visit_binary_node(indexes, lvar_index, node, diagnostics),
+        Node::Unary(node) => {
+            let operand_type =
+                visit_value_node(indexes, lvar_index, node.operand.as_mut(), diagnostics);
+
+            match node.op {
+                ['!', '\0', '\0', '\0'] => Some(BaseType::Bool),
+                _ => operand_type,
+            }
+        }
+        Node::Call(node) => visit_call_node(indexes, lvar_index, node, diagnostics),
+        Node::Send(node) => visit_send_node(indexes, lvar_index, node, diagnostics),
+        Node::LocalVar(lvar) => visit_local_var_node(lvar_index, lvar, diagnostics),
+        Node::If(if_node) => visit_if_node(indexes, lvar_index, if_node, true, diagnostics),
+        Node::Loop(loop_node) => visit_loop_node(
+            indexes,
+            &mut lvar_index.clone(),
+            loop_node,
+            true,
+            diagnostics,
+        ),
+        Node::Array(array) => {
+            array.items.iter_mut().for_each(|node| {
+                visit_value_node(indexes, lvar_index, node, diagnostics);
+            });
+
+            Some(BaseType::Array(
+                array.length,
+                Box::new(array.item_type.clone()),
+            ))
+        }
+        Node::BuildStruct(struct_node) => {
+            visit_build_struct_node(indexes, lvar_index, struct_node, diagnostics)
+        }
+        Node::Bool(_) => Some(BaseType::Bool),
+        Node::Float(_) => Some(BaseType::Float64),
+        Node::Int(_) => Some(BaseType::Int),
+        Node::StringLiteral(_) => Some(BaseType::Class("Str".to_string())),
+        Node::SelfRef(self_ref) => Some(self_ref.return_type.clone()),
+        // Constants are typed by codegen from the constant index
+        Node::Const(_) => None,
+        node => {
+            diagnostics.error(
+                node.span(),
+                format!("{} can't be used as a value", describe_node(node)),
+            );
+            None
+        }
+    }
+}
+
+fn visit_local_var_node(
+    lvar_index: &HashMap<String, Option<BaseType>>,
+    lvar: &mut crate::parser::LocalVar,
+    diagnostics: &mut Diagnostics,
+) -> Option<BaseType> {
+    if let Some(Some(latest_return_type)) = lvar_index.get(&lvar.name) {
+        lvar.return_type = Some(latest_return_type.clone());
+    }
+
+    if lvar.return_type.is_none() {
+        diagnostics.error(
+            lvar.span,
+            format!("can't infer the type of `{}`", lvar.name),
+        );
+    }
+
+    lvar.return_type.clone()
+}
+
+/// Visits the condition and the branches of an `if` and infers its type,
+/// which both branches have to agree on. When the `if` is used as a value,
+/// it's an error for it to not have one.
+fn visit_if_node(
+    indexes: &Indexes,
+    lvar_index: &HashMap<String, Option<BaseType>>,
+    if_node: &mut crate::parser::If,
+    is_value: bool,
+    diagnostics: &mut Diagnostics,
+) -> Option<BaseType> {
+    visit_value_node(indexes, lvar_index, if_node.condition.as_mut(), diagnostics);
+
+    // Variables assigned in a branch are only visible in that branch
+    let then_type = visit_body(
+        indexes,
+        &mut lvar_index.clone(),
+        &mut if_node.then_body,
+        diagnostics,
+    );
+
+    let Some(else_body) = &mut if_node.else_body else {
+        if is_value {
+            diagnostics.error(
+                if_node.span,
+                "an `if` without an `else` can't be used as a value",
+            );
+        }
+
+        return None;
+    };
+
+    let else_type = visit_body(indexes, &mut lvar_index.clone(), else_body, diagnostics);
+
+    if_node.return_type = match (then_type, else_type) {
+        (Some(then_type), Some(else_type)) if then_type == else_type => Some(then_type),
+        (Some(then_type), Some(else_type)) => {
+            let promoted = BaseType::float_promotion(&then_type, &else_type);
+
+            if promoted.is_none() && is_value {
+                diagnostics.error(
+                    if_node.span,
+                    format!(
+                        "`if` and `else` have different types, `{}` and `{}`",
+                        then_type, else_type
+                    ),
+                );
+            }
+
+            promoted
+        }
+        _ => {
+            // A branch ending in a `break` or a call without a value
+            if is_value {
+                diagnostics.error(
+                    if_node.span,
+                    "an `if` can't be used as a value when a branch has none",
+                );
+            }
+
+            None
+        }
+    };
+
+    if_node.return_type.clone()
+}
+
+/// Visits the condition and the body of a loop and infers its type, which
+/// is the type of the values it breaks with. Only a `loop` can break with a
+/// value, a `while` or an `until` can end on its condition without one.
+fn visit_loop_node(
+    indexes: &Indexes,
+    lvar_index: &mut HashMap<String, Option<BaseType>>,
+    loop_node: &mut parser::Loop,
+    is_value: bool,
+    diagnostics: &mut Diagnostics,
+) -> Option<BaseType> {
+    if let Some(condition) = &mut loop_node.condition {
+        visit_value_node(indexes, lvar_index, condition.as_mut(), diagnostics);
+    }
+
+    visit_body(indexes, lvar_index, &mut loop_node.body, diagnostics);
+
+    let mut breaks = vec![];
+    collect_breaks(&loop_node.body, &mut breaks);
+
+    let mut return_type: Option<BaseType> = None;
+
+    for break_node in breaks {
+        if break_node.value.is_none() {
+            if is_value {
+                diagnostics.error(
+                    break_node.span,
+                    "`break` needs a value to leave a loop used as a value",
+                );
+            }
+
+            continue;
+        }
+
+        if loop_node.condition.is_some() {
+            diagnostics.error(
+                break_node.span,
+                "`break` with a value is only allowed in a `loop`",
+            );
+            continue;
+        }
+
+        match (&return_type, &break_node.return_type) {
+            (None, found) => return_type = found.clone(),
+            (Some(expected), Some(found)) if expected != found => diagnostics.error(
+                break_node.span,
+                format!(
+                    "`break` values have different types, `{}` and `{}`",
+                    expected, found
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    if is_value && return_type.is_none() {
+        diagnostics.error(
+            loop_node.span,
+            "a loop that doesn't break with a value can't be used as a value",
+        );
+    }
+
+    loop_node.return_type = return_type;
+    loop_node.return_type.clone()
+}
+
+/// The `break`s that leave the loop with this body, not the ones of the
+/// loops nested in it.
+fn collect_breaks<'a>(body: &'a [Node], breaks: &mut Vec<&'a parser::Break>) {
+    for node in body {
         match node {
-            Node::Def(def_node) => {
-                let mut lvar_index = HashMap::new();
-
-                def_node.prototype.args.iter().for_each(|arg| {
-                    lvar_index.insert(arg.name.clone(), Some(arg.return_type.clone()));
-                });
-
-                def_node.body.iter_mut().for_each(|node| match node {
-                    Node::Access(access_node) => {
-                        visit_access_node(&attribute_index, &lvar_index, access_node);
-                    }
-                    Node::AssignLocalVar(assignlocalvar_node) => {
-                        let return_type = match assignlocalvar_node.value.as_mut() {
-                            Node::Binary(binary_node) => visit_binary_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                binary_node,
-                            ),
-                            Node::Call(call_node) => visit_call_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                call_node,
-                            ),
-                            Node::Send(send_node) => visit_send_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                send_node,
-                            ),
-                            Node::Access(access_node) => {
-                                visit_access_node(&attribute_index, &lvar_index, access_node)
-                            }
-                            Node::AssignAttribute(_) => todo!(),
-                            Node::AssignAttributeAccess(_) => todo!(),
-                            Node::AssignLocalVar(_) => todo!(),
-                            Node::Attribute(_) => todo!(),
-                            Node::Class(_) => todo!(),
-                            Node::Const(_) => todo!(),
-                            Node::Def(_) => todo!(),
-                            Node::DefE(_) => todo!(),
-                            Node::Impl(_) => todo!(),
-                            Node::Int(_) => Some(BaseType::Int),
-                            Node::LocalVar(_) => todo!(),
-                            Node::Loop(_) => todo!(),
-                            Node::Module(_) => todo!(),
-                            Node::Ret(_) => todo!(),
-                            Node::SelfRef(_) => todo!(),
-                            Node::StringLiteral(_) => Some(BaseType::Class("Str".to_string())),
-                            Node::Trait(_) => todo!(),
-                            Node::AssignConstant(_) => todo!(),
-                            Node::Array(array) => {
-                                array.items.iter_mut().for_each(|node| {
-                                    match node {
-                                        Node::Access(access_node) => visit_access_node(
-                                            &attribute_index,
-                                            &lvar_index,
-                                            access_node,
-                                        ),
-                                        Node::Binary(node) => visit_binary_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        Node::Call(node) => visit_call_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        Node::Send(node) => visit_send_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        Node::Int(node) => Some(BaseType::Int),
-                                        _ => todo!(),
-                                    };
-                                });
-
-                                Some(BaseType::Array(
-                                    array.length,
-                                    Box::new(array.item_type.clone()),
-                                ))
-                            }
-                            Node::BuildStruct(struct_node) => visit_build_struct_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                struct_node,
-                                struct_index,
-                            ),
-                            Node::Struct(_) => todo!(),
-                            Node::FnRef(_) => todo!(),
-                        };
-
-                        lvar_index.insert(assignlocalvar_node.name.clone(), return_type);
-                    }
-                    Node::Binary(binary_node) => {
-                        visit_binary_node(
-                            &attribute_index,
-                            &method_index,
-                            &lvar_index,
-                            binary_node,
-                        );
-                    }
-                    Node::Call(call_node) => {
-                        visit_call_node(&attribute_index, &method_index, &lvar_index, call_node);
-                    }
-                    Node::Send(send_node) => {
-                        visit_send_node(&attribute_index, &method_index, &lvar_index, send_node);
-                    }
-                    Node::Ret(ret_node) => {
-                        visit_ret_node(&attribute_index, &method_index, &lvar_index, ret_node);
-                    }
-                    Node::AssignConstant(_) => todo!(),
-                    Node::Attribute(_) => todo!(),
-                    Node::Class(_) => todo!(),
-                    Node::Def(_) => todo!(),
-                    Node::DefE(_) => todo!(),
-                    Node::Impl(_) => todo!(),
-                    Node::Int(_) => {},
-                    Node::StringLiteral(_) => {},
-                    Node::LocalVar(node) => {
-                        match node.return_type {
-                            Some(_) => {}
-                            None => todo!(),
-                        }
-                        // println!("{:#?}", node);
-                    }
-                    Node::Module(_) => todo!(),
-                    Node::SelfRef(_) => todo!(),
-                    Node::Trait(_) => todo!(),
-                    Node::AssignAttribute(assign_attr_node) => {
-                        match assign_attr_node.value.as_mut() {
-                            Node::Binary(binary_node) => visit_binary_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                binary_node,
-                            ),
-                            Node::Call(call_node) => visit_call_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                call_node,
-                            ),
-                            Node::Send(send_node) => visit_send_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                send_node,
-                            ),
-                            Node::Access(access_node) => {
-                                visit_access_node(&attribute_index, &lvar_index, access_node)
-                            }
-                            Node::AssignAttribute(_) => todo!(),
-                            Node::AssignAttributeAccess(_) => todo!(),
-                            Node::AssignLocalVar(_) => todo!(),
-                            Node::Attribute(_) => todo!(),
-                            Node::Class(_) => todo!(),
-                            Node::Const(_) => todo!(),
-                            Node::Def(_) => todo!(),
-                            Node::DefE(_) => todo!(),
-                            Node::Impl(_) => todo!(),
-                            Node::Int(_) => todo!(),
-                            Node::LocalVar(lvar) => match lvar.return_type {
-                                Some(_) => lvar.return_type.clone(),
-                                None => todo!(),
-                            },
-                            Node::Loop(_) => todo!(),
-                            Node::Module(_) => todo!(),
-                            Node::Ret(_) => todo!(),
-                            Node::SelfRef(_) => todo!(),
-                            Node::StringLiteral(_) => todo!(),
-                            Node::Trait(_) => todo!(),
-                            Node::AssignConstant(_) => todo!(),
-                            Node::Array(array) => {
-                                array.items.iter_mut().for_each(|node| {
-                                    match node {
-                                        Node::Access(access_node) => visit_access_node(
-                                            &attribute_index,
-                                            &lvar_index,
-                                            access_node,
-                                        ),
-                                        Node::Binary(node) => visit_binary_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        Node::Call(node) => visit_call_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        Node::Send(node) => visit_send_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        _ => todo!(),
-                                    };
-                                });
-
-                                Some(BaseType::Array(
-                                    array.length,
-                                    Box::new(array.item_type.clone()),
-                                ))
-                            }
-                            Node::BuildStruct(_) => todo!(),
-                            Node::Struct(_) => todo!(),
-                            Node::FnRef(_) => todo!(),
-                        };
-                    }
-                    Node::Const(_) => todo!(),
-                    Node::AssignAttributeAccess(node) => {
-                        visit_access_node(&attribute_index, &lvar_index, &mut node.access);
-
-                        match node.value.as_mut() {
-                            Node::Binary(binary_node) => visit_binary_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                binary_node,
-                            ),
-                            Node::Call(call_node) => visit_call_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                call_node,
-                            ),
-                            Node::Send(send_node) => visit_send_node(
-                                &attribute_index,
-                                &method_index,
-                                &lvar_index,
-                                send_node,
-                            ),
-                            Node::Access(access_node) => {
-                                visit_access_node(&attribute_index, &lvar_index, access_node)
-                            }
-                            Node::AssignAttribute(_) => todo!(),
-                            Node::AssignAttributeAccess(_) => todo!(),
-                            Node::AssignLocalVar(_) => todo!(),
-                            Node::Attribute(_) => todo!(),
-                            Node::Class(_) => todo!(),
-                            Node::Const(_) => todo!(),
-                            Node::Def(_) => todo!(),
-                            Node::DefE(_) => todo!(),
-                            Node::Impl(_) => todo!(),
-                            Node::Int(_) => todo!(),
-                            Node::LocalVar(lvar) => match lvar.return_type {
-                                Some(_) => lvar.return_type.clone(),
-                                None => todo!(),
-                            },
-                            Node::Loop(_) => todo!(),
-                            Node::Module(_) => todo!(),
-                            Node::Ret(_) => todo!(),
-                            Node::SelfRef(_) => todo!(),
-                            Node::StringLiteral(_) => Some(BaseType::Class("Str".to_string())),
-                            Node::Trait(_) => todo!(),
-                            Node::AssignConstant(_) => todo!(),
-                            Node::Array(array) => {
-                                array.items.iter_mut().for_each(|node| {
-                                    match node {
-                                        Node::Access(access_node) => visit_access_node(
-                                            &attribute_index,
-                                            &lvar_index,
-                                            access_node,
-                                        ),
-                                        Node::Binary(node) => visit_binary_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        Node::Call(node) => visit_call_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        Node::Send(node) => visit_send_node(
-                                            &attribute_index,
-                                            &method_index,
-                                            &lvar_index,
-                                            node,
-                                        ),
-                                        _ => todo!(),
-                                    };
-                                });
-
-                                Some(BaseType::Array(
-                                    array.length,
-                                    Box::new(array.item_type.clone()),
-                                ))
-                            }
-                            Node::BuildStruct(_) => todo!(),
-                            Node::Struct(_) => todo!(),
-                            Node::FnRef(_) => todo!(),
-                        };
-                    }
-                    Node::Loop(loop_node) => {
-                        loop_node.body.iter_mut().for_each(|node| {
-                            match node {
-                                Node::Access(access_node) => {
-                                    visit_access_node(&attribute_index, &lvar_index, access_node)
-                                }
-                                Node::Binary(node) => visit_binary_node(
-                                    &attribute_index,
-                                    &method_index,
-                                    &lvar_index,
-                                    node,
-                                ),
-                                Node::Call(node) => visit_call_node(
-                                    &attribute_index,
-                                    &method_index,
-                                    &lvar_index,
-                                    node,
-                                ),
-                                Node::Send(node) => visit_send_node(
-                                    &attribute_index,
-                                    &method_index,
-                                    &lvar_index,
-                                    node,
-                                ),
-                                _ => todo!(),
-                            };
-                        });
-                    }
-                    Node::Array(array) => {
-                        array.items.iter_mut().for_each(|node| {
-                            match node {
-                                Node::Access(access_node) => {
-                                    visit_access_node(&attribute_index, &lvar_index, access_node)
-                                }
-                                Node::Binary(node) => visit_binary_node(
-                                    &attribute_index,
-                                    &method_index,
-                                    &lvar_index,
-                                    node,
-                                ),
-                                Node::Call(node) => visit_call_node(
-                                    &attribute_index,
-                                    &method_index,
-                                    &lvar_index,
-                                    node,
-                                ),
-                                Node::Send(node) => visit_send_node(
-                                    &attribute_index,
-                                    &method_index,
-                                    &lvar_index,
-                                    node,
-                                ),
-                                _ => todo!(),
-                            };
-                        });
-                    }
-                    Node::BuildStruct(_) => todo!(),
-                    Node::Struct(_) => todo!(),
-                    Node::FnRef(_) => todo!(),
-                })
+            Node::Break(break_node) => breaks.push(break_node),
+            Node::AssignLocalVar(assign_node) => {
+                collect_breaks(std::slice::from_ref(assign_node.value.as_ref()), breaks)
+            }
+            Node::If(if_node) => {
+                collect_breaks(&if_node.then_body, breaks);
+
+                if let Some(else_body) = &if_node.else_body {
+                    collect_breaks(else_body, breaks);
+                }
             }
             _ => {}
-        };
-    });
+        }
+    }
 }
 
 fn visit_ret_node(
-    attribute_index: &HashMap<String, (i32, BaseType)>,
-    method_index: &HashMap<String, Option<BaseType>>,
+    indexes: &Indexes,
     lvar_index: &HashMap<String, Option<BaseType>>,
     ret_node: &mut crate::parser::Ret,
+    diagnostics: &mut Diagnostics,
 ) {
-    match ret_node.value.as_mut() {
-        Node::Access(access_node) => visit_access_node(attribute_index, lvar_index, access_node),
-        Node::Binary(node) => visit_binary_node(attribute_index, method_index, lvar_index, node),
-        Node::Call(node) => visit_call_node(attribute_index, method_index, lvar_index, node),
-        Node::Send(node) => visit_send_node(attribute_index, method_index, lvar_index, node),
-        _ => todo!(),
-    };
+    visit_value_node(indexes, lvar_index, ret_node.value.as_mut(), diagnostics);
 }
 
 fn visit_access_node(
-    attribute_index: &HashMap<String, (i32, BaseType)>,
+    indexes: &Indexes,
     lvar_index: &HashMap<String, Option<BaseType>>,
     access_node: &mut crate::parser::Access,
+    diagnostics: &mut Diagnostics,
 ) -> Option<BaseType> {
     let class_name = match access_node.receiver.as_mut() {
         Node::LocalVar(lvar) => {
-            match lvar.return_type {
-                Some(_) => {}
-                None => todo!(),
-            }
+            let return_type = visit_local_var_node(lvar_index, lvar, diagnostics)?;
 
-            let latest_return_type = lvar_index.get(&lvar.name).unwrap();
-            lvar.return_type = latest_return_type.clone();
-
-            pajama_class_name(&lvar.return_type.as_ref().unwrap())
-        }
-        Node::Access(_) => todo!(),
-        Node::AssignAttribute(_) => todo!(),
-        Node::AssignAttributeAccess(_) => todo!(),
-        Node::AssignLocalVar(_) => todo!(),
-        Node::Attribute(_) => todo!(),
-        Node::Binary(_) => todo!(),
-        Node::Call(_) => todo!(),
-        Node::Class(_) => todo!(),
-        Node::Const(_) => todo!(),
-        Node::Def(_) => todo!(),
-        Node::DefE(_) => todo!(),
-        Node::Impl(_) => todo!(),
-        Node::Int(_) => todo!(),
-        Node::Loop(_) => todo!(),
-        Node::Module(_) => todo!(),
-        Node::Ret(_) => todo!(),
+            pajama_class_name(&return_type)
+        }
         Node::SelfRef(self_ref) => pajama_class_name(&self_ref.return_type),
-        Node::Send(_) => todo!(),
-        Node::StringLiteral(_) => todo!(),
-        Node::Trait(_) => todo!(),
-        Node::AssignConstant(_) => todo!(),
         Node::Array(_) => "Array".to_string(),
-        Node::BuildStruct(_) => todo!(),
-        Node::Struct(_) => todo!(),
-        Node::FnRef(_) => todo!(),
+        node => {
+            diagnostics.error(
+                node.span(),
+                format!(
+                    "{} can't be the receiver of an attribute access",
+                    describe_node(node)
+                ),
+            );
+            return None;
+        }
     };
 
     let attribute_name = match access_node.message.as_mut() {
         Node::Attribute(attr_node) => attr_node.name.clone(),
-        _ => todo!(),
+        node => {
+            diagnostics.error(
+                node.span(),
+                format!("expected an attribute, found {}", describe_node(node)),
+            );
+            return None;
+        }
     };
 
     let attr_key = format!("{}.{}", class_name, attribute_name);
-    let (index, return_type) = attribute_index.get(&attr_key).unwrap();
+    let Some((index, return_type)) = indexes.attribute_index.get(&attr_key) else {
+        diagnostics.error(
+            access_node.message.span(),
+            format!(
+                "undefined attribute `{}` for `{}`",
+                attribute_name, class_name
+            ),
+        );
+        return None;
+    };
 
     access_node.index = *index;
     access_node.return_type = Some(return_type.clone());
@@ -530,201 +605,184 @@ fn visit_access_node(
 }
 
 fn visit_binary_node(
-    attribute_index: &HashMap<String, (i32, BaseType)>,
-    method_index: &HashMap<String, Option<BaseType>>,
+    indexes: &Indexes,
     lvar_index: &HashMap<String, Option<BaseType>>,
     binary_node: &mut crate::parser::Binary,
+    diagnostics: &mut Diagnostics,
 ) -> Option<BaseType> {
-    match binary_node.left.as_mut() {
-        Node::Access(access_node) => visit_access_node(attribute_index, lvar_index, access_node),
-        Node::Binary(node) => visit_binary_node(attribute_index, method_index, lvar_index, node),
-        Node::Call(node) => visit_call_node(attribute_index, method_index, lvar_index, node),
-        Node::Send(node) => visit_send_node(attribute_index, method_index, lvar_index, node),
-        Node::Int(_) => Some(BaseType::Int),
-        // _ => todo!(),
-        _ => {
-            todo!()
-        },
-    };
-
-    match binary_node.right.as_mut() {
-        Node::Access(access_node) => visit_access_node(attribute_index, lvar_index, access_node),
-        Node::Binary(node) => visit_binary_node(attribute_index, method_index, lvar_index, node),
-        Node::Call(node) => visit_call_node(attribute_index, method_index, lvar_index, node),
-        Node::Send(node) => visit_send_node(attribute_index, method_index, lvar_index, node),
-        Node::Int(_) => Some(BaseType::Int),
-        _ => todo!(),
-    }
+    let left_type = visit_value_node(indexes, lvar_index, binary_node.left.as_mut(), diagnostics);
+    let right_type = visit_value_node(indexes, lvar_index, binary_node.right.as_mut(), diagnostics);
 
     // todo maybe validate the operator here since now both the left and right
     // types are known
+    if binary_node.is_comparison() || binary_node.is_logical() {
+        return Some(BaseType::Bool);
+    }
+
+    match (&left_type, &right_type) {
+        (Some(left), Some(right)) => BaseType::float_promotion(left, right)
+            .or_else(|| BaseType::integer_promotion(left, right))
+            .or(right_type),
+        _ => right_type,
+    }
 }
 
 fn visit_call_node(
-    attribute_index: &HashMap<String, (i32, BaseType)>,
-    method_index: &HashMap<String, Option<BaseType>>,
+    indexes: &Indexes,
     lvar_index: &HashMap<String, Option<BaseType>>,
     call_node: &mut crate::parser::Call,
+    diagnostics: &mut Diagnostics,
 ) -> Option<BaseType> {
-    println!("{:#?}", &call_node.fn_name);
-    println!("{:#?}", method_index);
-
-    let base_type = method_index.get(&call_node.fn_name).unwrap();
-    call_node.return_type = base_type.clone();
-
     for arg in &mut call_node.args {
-        println!("{:#?}", arg);
-
-        match arg {
-            Node::Access(access_node) => {
-                visit_access_node(attribute_index, lvar_index, access_node);
-            }
-            Node::Call(node) => {
-                visit_call_node(attribute_index, method_index, lvar_index, node);
-            }
-            Node::Send(node) => {
-                visit_send_node(attribute_index, &method_index, lvar_index, node);
-            }
-            Node::Binary(node) => {
-                visit_binary_node(attribute_index, method_index, lvar_index, node);
-            }
-            Node::LocalVar(lvar) => {
-                match lvar.return_type {
-                    Some(_) => {}
-                    None => todo!(),
-                }
-
-                let latest_return_type = lvar_index.get(&lvar.name).unwrap();
-                lvar.return_type = latest_return_type.clone();
-            }
-            Node::StringLiteral(_) => {}
-            Node::Const(_) => {}
-            Node::Int(_) => {}
-            Node::SelfRef(self_ref) => {
-                // Node::SelfRef(self_ref) => pajama_class_name(&self_ref.return_type),
-            }
-            _ => {
-                println!("{:#?}", arg);
-                todo!()
-            }
-        };
+        visit_value_node(indexes, lvar_index, arg, diagnostics);
     }
 
+    let Some(base_type) = indexes.method_index.get(&call_node.fn_name) else {
+        diagnostics.error(
+            call_node.span,
+            format!("undefined function `{}`", call_node.fn_name),
+        );
+        return None;
+    };
+
+    call_node.return_type = base_type.clone();
     base_type.clone()
 }
 
 fn visit_send_node(
-    attribute_index: &HashMap<String, (i32, BaseType)>,
-    method_index: &HashMap<String, Option<BaseType>>,
+    indexes: &Indexes,
     lvar_index: &HashMap<String, Option<BaseType>>,
     send_node: &mut crate::parser::Send,
+    diagnostics: &mut Diagnostics,
 ) -> Option<BaseType> {
     let fn_name = match send_node.message.as_mut() {
-        Node::Call(node) => &node.fn_name,
-        _ => todo!(),
+        Node::Call(node) => node.fn_name.clone(),
+        node => {
+            diagnostics.error(
+                node.span(),
+                format!("expected a method call, found {}", describe_node(node)),
+            );
+            return None;
+        }
     };
 
+    let error_count = diagnostics.errors.len();
     let basetype = match send_node.receiver.as_mut() {
-        Node::Access(access_node) => visit_access_node(attribute_index, lvar_index, access_node),
-        Node::Call(node) => visit_call_node(attribute_index, method_index, lvar_index, node),
-        Node::Send(node) => visit_send_node(attribute_index, &method_index, lvar_index, node),
-        Node::Binary(node) => visit_binary_node(attribute_index, method_index, lvar_index, node),
-        Node::LocalVar(lvar) => {
-            match lvar.return_type {
-                Some(_) => {}
-                None => {
-                    // maybe a function ref!
-                    if method_index.contains_key(&lvar.name) {
-                        send_node.return_type = Some(BaseType::FnRef);
-                        lvar.return_type = Some(BaseType::FnRef);
-                        return lvar.return_type.clone();
-                    } else {
-                        // not found, return an error
-                        todo!()
-                    }
-                }
+        // Not bound to a declaration by the resolver
+        Node::LocalVar(lvar) if lvar.declaration.is_none() => {
+            // maybe a function ref!
+            if indexes.method_index.contains_key(&lvar.name) {
+                send_node.return_type = Some(BaseType::FnRef);
+                lvar.return_type = Some(BaseType::FnRef);
+                return lvar.return_type.clone();
             }
 
-            let latest_return_type = lvar_index.get(&lvar.name).unwrap();
-            lvar.return_type = latest_return_type.clone();
-            latest_return_type.clone()
+            diagnostics.error(
+                lvar.span,
+                format!("undefined local variable or function `{}`", lvar.name),
+            );
+            None
         }
         Node::Const(node) => {
             if fn_name == "new" || fn_name == "alloca" {
                 send_node.return_type = Some(BaseType::Class(node.name.clone()));
                 Some(BaseType::Class(node.name.clone()))
-                // return;
             } else {
-                todo!("class methods")
+                diagnostics.error(
+                    send_node.message.span(),
+                    format!(
+                        "class methods aren't supported, `{}.{}`",
+                        node.name, fn_name
+                    ),
+                );
+                None
             }
         }
-        _ => None,
+        node => visit_value_node(indexes, lvar_index, node, diagnostics),
     };
 
-    let class_name = pajama_class_name(&basetype.as_ref().unwrap());
-    let message_name = match send_node.message.as_mut() {
-        Node::Call(node) => {
-            let prefixed_name = format!("{}.{}", class_name, &node.fn_name);
-            node.fn_name = prefixed_name.clone();
-
-            visit_call_node(attribute_index, method_index, lvar_index, node);
-
-            prefixed_name
+    let Some(basetype) = basetype else {
+        if diagnostics.errors.len() == error_count {
+            diagnostics.error(
+                send_node.receiver.span(),
+                format!("can't infer the type of the receiver of `{}`", fn_name),
+            );
         }
-        _ => "".to_string(),
+        return None;
     };
 
-    let base_type = method_index.get(&message_name).unwrap();
-    match base_type {
-        Some(bt) => {
-            send_node.return_type = Some(bt.clone());
-            Some(bt.clone())
-        }
-        None => None,
+    let class_name = pajama_class_name(&basetype);
+    let Node::Call(node) = send_node.message.as_mut() else {
+        unreachable!("the message was matched as a call above");
+    };
+
+    node.fn_name = format!("{}.{}", class_name, &node.fn_name);
+    let base_type = visit_call_node(indexes, lvar_index, node, diagnostics);
+
+    if base_type.is_some() {
+        send_node.return_type = base_type.clone();
     }
+
+    base_type
 }
 
 fn visit_build_struct_node(
-    attribute_index: &HashMap<String, (i32, BaseType)>,
-    method_index: &HashMap<String, Option<BaseType>>,
+    indexes: &Indexes,
     lvar_index: &HashMap<String, Option<BaseType>>,
     build_struct_node: &mut crate::parser::BuildStruct,
-    struct_index: &HashMap<String, parser::Struct>,
+    diagnostics: &mut Diagnostics,
 ) -> Option<BaseType> {
     build_struct_node.args.iter_mut().for_each(|node| {
-        match node {
-            Node::Access(access_node) => {
-                visit_access_node(&attribute_index, &lvar_index, access_node);
-            }
-            Node::Binary(node) => {
-                visit_binary_node(&attribute_index, &method_index, &lvar_index, node);
-            }
-            Node::Call(node) => {
-                visit_call_node(&attribute_index, &method_index, &lvar_index, node);
-            }
-            Node::Send(node) => {
-                visit_send_node(&attribute_index, &method_index, &lvar_index, node);
-            }
-            Node::BuildStruct(node) => {
-                visit_build_struct_node(
-                    &attribute_index,
-                    &method_index,
-                    &lvar_index,
-                    node,
-                    struct_index,
-                );
-            }
-            Node::Const(_) => {}
-            _ => todo!(),
-        };
+        visit_value_node(indexes, lvar_index, node, diagnostics);
     });
 
-    let return_type = struct_index
-        .get(&build_struct_node.name)
-        .unwrap()
-        .return_type
-        .clone();
-    Some(return_type)
+    match indexes.struct_index.get(&build_struct_node.name) {
+        Some(struct_node) => Some(struct_node.return_type.clone()),
+        None => {
+            diagnostics.error(
+                build_struct_node.span,
+                format!("undefined struct `{}`", build_struct_node.name),
+            );
+            None
+        }
+    }
+}
+
+/// What a node is, for diagnostics.
+fn describe_node(node: &Node) -> &'static str {
+    match node {
+        Node::Access(_) => "an attribute access",
+        Node::Array(_) => "an array",
+        Node::AssignAttribute(_) | Node::AssignAttributeAccess(_) => "an attribute assignment",
+        Node::AssignConstant(_) => "a constant assignment",
+        Node::AssignLocalVar(_) => "a local variable assignment",
+        Node::Attribute(_) => "an attribute",
+        Node::Binary(_) => "a binary expression",
+        Node::Bool(_) => "a boolean",
+        Node::Break(_) => "a `break`",
+        Node::BuildStruct(_) => "a struct literal",
+        Node::Call(_) => "a function call",
+        Node::Class(_) => "a class definition",
+        Node::Const(_) => "a constant",
+        Node::Def(_) => "a function definition",
+        Node::DefE(_) => "an external function declaration",
+        Node::Float(_) => "a float",
+        Node::FnRef(_) => "a function reference",
+        Node::If(_) => "an `if`",
+        Node::Impl(_) => "an impl block",
+        Node::Int(_) => "an integer",
+        Node::LocalVar(_) => "a local variable",
+        Node::Loop(_) => "a loop",
+        Node::Module(_) => "a module",
+        Node::Next(_) => "a `next`",
+        Node::Ret(_) => "a return",
+        Node::SelfRef(_) => "`self`",
+        Node::Send(_) => "a method call",
+        Node::StringLiteral(_) => "a string",
+        Node::Struct(_) => "a struct definition",
+        Node::Trait(_) => "a trait definition",
+        Node::Unary(_) => "a unary expression",
+    }
 }
 
 pub fn pajama_class_name(base_type: &BaseType) -> String {
@@ -739,6 +797,9 @@ pub fn pajama_class_name(base_type: &BaseType) -> String {
         BaseType::Int64 => "Int64".to_string(),
         BaseType::Void => "".to_string(),
         BaseType::Struct(_) => "Struct".to_string(),
+        BaseType::Bool => "Bool".to_string(),
+        BaseType::Float32 => "Float32".to_string(),
+        BaseType::Float64 => "Float64".to_string(),
         BaseType::FnRef => "FnRef".to_string(),
     }
 }
@@ -0,0 +1,637 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Once;
+use std::time::Instant;
+
+use melior::dialect::DialectRegistry;
+use melior::ir::operation::OperationPrintingFlags;
+use melior::ir::{Location, Module};
+use melior::pass::{conversion, PassManager};
+use melior::utility::{register_all_dialects, register_all_llvm_translations};
+use melior::{pass, Context, ExecutionEngine};
+
+use crate::codegen::{Compiler, JIT_MAIN};
+use crate::debug_info::DebugInfo;
+use crate::dump::{DumpPhase, Dumper};
+use crate::lexer::{Lexer, TokenStream};
+use crate::pajama_compiler::{CompileError, CompileOptions};
+use crate::parser::{BaseType, ParseError, Parser, ParserResult};
+use crate::resolver::Resolver;
+use crate::semantic_analyzer::{Diagnostic, SemanticAnalyzer};
+use crate::type_checker::TypeChecker;
+
+/// Owns an MLIR context and runs the compiler one stage at a time:
+///
+/// tokens -> `ParserResult` -> analyzed `ParserResult` -> MLIR module ->
+/// lowered module -> JIT engine or object file
+///
+/// Stages never print, MLIR diagnostics are collected and handed back with
+/// the error of the stage that produced them. The output of a stage goes to
+/// the dump sink when its phase is selected in `CompileOptions::dump`.
+/// Modules borrow the session's context, so a session can compile any number
+/// of programs.
+pub struct Session {
+    context: Context,
+    options: CompileOptions,
+    dumper: Dumper,
+    diagnostics: Rc<RefCell<Vec<String>>>,
+    warnings: RefCell<Vec<Diagnostic>>,
+}
+
+/// A step of the pass pipeline. Steps are kept apart so that each one can be
+/// run and timed on its own.
+#[derive(Clone, Copy)]
+enum PipelineStep<'a> {
+    Pass(&'static str, fn(&PassManager)),
+    Text(&'a str),
+}
+
+impl Session {
+    pub fn new(options: CompileOptions) -> Session {
+        let registry = DialectRegistry::new();
+        register_all_dialects(&registry);
+
+        let context = Context::new();
+        context.append_dialect_registry(&registry);
+        context.load_all_available_dialects();
+        register_all_llvm_translations(&context);
+
+        let diagnostics = Rc::new(RefCell::new(vec![]));
+        let handler_diagnostics = Rc::clone(&diagnostics);
+
+        context.attach_diagnostic_handler(move |diagnostic| {
+            handler_diagnostics
+                .borrow_mut()
+                .push(format!("{}: {}", diagnostic.location(), diagnostic));
+            true
+        });
+
+        Session {
+            context,
+            dumper: Dumper::new(options.dump.clone()),
+            options,
+            diagnostics,
+            warnings: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    pub fn tokenize(&self, input: &str) -> Result<TokenStream, CompileError> {
+        let mut token_stream = guard("lexing", || Ok(Lexer::new(input).token_stream()))?;
+        token_stream.source_map = self.options.source_map.clone();
+
+        self.dumper
+            .dump(DumpPhase::Tokens, || format!("{:#?}", token_stream.tokens))?;
+
+        Ok(token_stream)
+    }
+
+    pub fn parse(&self, token_stream: TokenStream) -> Result<ParserResult, CompileError> {
+        let (parser_result, errors) = self.parse_recovering(token_stream)?;
+
+        if !errors.is_empty() {
+            return Err(CompileError::Parse(errors));
+        }
+
+        Ok(parser_result)
+    }
+
+    /// Parses as much of the program as it can and returns it along with every
+    /// syntax error, for tooling that has to work on broken files.
+    pub fn parse_recovering(
+        &self,
+        token_stream: TokenStream,
+    ) -> Result<(ParserResult, Vec<ParseError>), CompileError> {
+        let (parser_result, errors) = guard("parsing", || {
+            let mut precedence_map = build_op_precedence_map();
+
+            Ok(Parser::parse_recovering(token_stream, &mut precedence_map))
+        })?;
+
+        self.dumper
+            .dump(DumpPhase::Ast, || format!("{:#?}", parser_result))?;
+
+        Ok((parser_result, errors))
+    }
+
+    pub fn analyze(&self, mut parser_result: ParserResult) -> Result<ParserResult, CompileError> {
+        let mut diagnostics = guard("semantic analysis", || {
+            let mut diagnostics = Resolver::run(&mut parser_result);
+
+            // Each pass relies on the ones before it having found no errors
+            if !diagnostics.has_errors() {
+                diagnostics.append(SemanticAnalyzer::run(&mut parser_result));
+            }
+
+            if !diagnostics.has_errors() {
+                diagnostics.append(TypeChecker::run(&parser_result));
+            }
+
+            Ok(diagnostics)
+        })?;
+
+        self.warnings
+            .borrow_mut()
+            .extend(diagnostics.warnings.drain(..));
+
+        if diagnostics.has_errors() {
+            return Err(CompileError::Semantic(diagnostics.errors));
+        }
+
+        self.dumper
+            .dump(DumpPhase::TypedAst, || format!("{:#?}", parser_result))?;
+
+        Ok(parser_result)
+    }
+
+    /// The warnings of every program analyzed so far, since the last call.
+    pub fn take_warnings(&self) -> Vec<Diagnostic> {
+        self.warnings.take()
+    }
+
+    /// Lexes, parses and analyzes `input`.
+    pub fn check(&self, input: &str) -> Result<ParserResult, CompileError> {
+        let token_stream = self.tokenize(input)?;
+        let parser_result = self.parse(token_stream)?;
+
+        self.analyze(parser_result)
+    }
+
+    /// Generates and verifies the MLIR module for an analyzed program.
+    pub fn generate(&self, parser_result: &ParserResult) -> Result<Module<'_>, CompileError> {
+        self.generate_module(parser_result, false)
+    }
+
+    /// Like `generate`, but also defines the C `main` an executable starts
+    /// at. Programs are entered at `main` when they define it, and at
+    /// `_mlir_ciface_main` otherwise.
+    pub fn generate_executable(
+        &self,
+        parser_result: &ParserResult,
+    ) -> Result<Module<'_>, CompileError> {
+        self.generate_module(parser_result, true)
+    }
+
+    /// Runs the optimization passes and lowers the module to the LLVM dialect.
+    pub fn lower(&self, mlir_module: &mut Module) -> Result<(), CompileError> {
+        let mut steps = match &self.options.pass_pipeline {
+            // Timed pieces each get a pass manager of their own. Otherwise the
+            // pipeline is parsed at once, parsing replaces a manager's passes
+            Some(pipeline) if self.options.time_passes => split_pass_pipeline(pipeline)
+                .into_iter()
+                .map(PipelineStep::Text)
+                .collect(),
+            Some(pipeline) => vec![PipelineStep::Text(pipeline)],
+            None => optimization_steps(self.options.opt_level),
+        };
+
+        if self.options.debug_info {
+            // Inlining would move a function's variables into its callers,
+            // out of the scope their debug info is declared in
+            steps.retain(|step| !matches!(step, PipelineStep::Pass("inline", _)));
+        }
+
+        steps.extend(conversion_steps());
+
+        if self.options.time_passes {
+            for step in &steps {
+                let pass_manager = PassManager::new(&self.context);
+                let name = self.add_pipeline_step(&pass_manager, step)?;
+                let started_at = Instant::now();
+
+                self.run_pass_manager(&pass_manager, mlir_module)?;
+
+                eprintln!("{:>12.3?}  {}", started_at.elapsed(), name);
+            }
+        } else {
+            let pass_manager = PassManager::new(&self.context);
+
+            for step in &steps {
+                self.add_pipeline_step(&pass_manager, step)?;
+            }
+
+            self.run_pass_manager(&pass_manager, mlir_module)?;
+        }
+
+        self.dumper
+            .dump(DumpPhase::LlvmMlir, || print_with_locations(mlir_module))?;
+
+        self.verify(mlir_module, "after lowering")
+    }
+
+    /// Creates a JIT engine for a lowered module, with the runtime's symbols
+    /// registered.
+    pub fn jit(&self, mlir_module: &Module) -> Result<ExecutionEngine, CompileError> {
+        let engine = ExecutionEngine::new(mlir_module, self.options.opt_level, &[], false);
+
+        for (name, symbol) in pajama_runtime::symbols() {
+            unsafe { engine.register_symbol(name, symbol) };
+        }
+
+        Ok(engine)
+    }
+
+    /// Runs the program's entry point with `args` as the program's arguments
+    /// and returns its exit status, `0` when the entry point doesn't return one.
+    pub fn invoke_main(
+        &self,
+        engine: &ExecutionEngine,
+        parser_result: &ParserResult,
+        args: &[String],
+    ) -> Result<i32, CompileError> {
+        let mut status_code: i32 = 0;
+
+        pajama_runtime::set_program_args(args.to_vec());
+
+        guard("execution", || unsafe {
+            // Programs with a C `main` are entered through `JIT_MAIN`, which
+            // always returns a status, the others at `_mlir_ciface_main`
+            if has_c_main(parser_result) {
+                engine.invoke_packed(JIT_MAIN, &mut [&mut status_code as *mut i32 as *mut ()])
+            } else if returns_status(parser_result) {
+                engine.invoke_packed("main", &mut [&mut status_code as *mut i32 as *mut ()])
+            } else {
+                engine.invoke_packed("main", &mut [])
+            }
+            .map_err(CompileError::Execution)
+        })?;
+
+        Ok(status_code)
+    }
+
+    /// Writes a lowered module to a native object file.
+    pub fn write_object(&self, mlir_module: &Module, output: &Path) -> Result<(), CompileError> {
+        let engine = ExecutionEngine::new(mlir_module, self.options.opt_level, &[], true);
+        engine.dump_to_object_file(&output.to_string_lossy());
+
+        if !output.exists() {
+            return Err(CompileError::Link(format!(
+                "no object file was written to `{}`",
+                output.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn generate_module(
+        &self,
+        parser_result: &ParserResult,
+        executable: bool,
+    ) -> Result<Module<'_>, CompileError> {
+        let c_main_entry_point = c_main_entry_point(parser_result, executable)?;
+        let mlir_module = Module::new(Location::unknown(&self.context));
+
+        guard("codegen", || {
+            let mut compiler = Compiler::new(&self.context, &mlir_module, parser_result);
+
+            if self.options.debug_info {
+                compiler.debug_info = Some(DebugInfo::new(
+                    &parser_result.source_map,
+                    self.options.opt_level > 0,
+                ));
+            }
+
+            compiler.compile().map_err(CompileError::Codegen)?;
+
+            if let Some((entry_point, returns_status)) = c_main_entry_point {
+                compiler.compile_c_main(entry_point, returns_status);
+            }
+
+            // The JIT can't enter a program at its C `main`, see `invoke_main`
+            match entry_point(parser_result) {
+                Some(entry_point) if !executable && has_c_main(parser_result) => {
+                    compiler.compile_jit_main(entry_point)
+                }
+                _ => {}
+            }
+
+            Ok(())
+        })?;
+
+        self.dumper
+            .dump(DumpPhase::Mlir, || print_with_locations(&mlir_module))?;
+
+        self.verify(&mlir_module, "after codegen")?;
+
+        Ok(mlir_module)
+    }
+
+    fn verify(&self, mlir_module: &Module, stage: &'static str) -> Result<(), CompileError> {
+        if mlir_module.as_operation().verify() {
+            self.diagnostics.borrow_mut().clear();
+            Ok(())
+        } else {
+            Err(CompileError::Verification(stage, self.take_diagnostics()))
+        }
+    }
+
+    fn run_pass_manager(
+        &self,
+        pass_manager: &PassManager,
+        mlir_module: &mut Module,
+    ) -> Result<(), CompileError> {
+        pass_manager
+            .run(mlir_module)
+            .map_err(|err| CompileError::PassPipeline(err, self.take_diagnostics()))
+    }
+
+    /// Adds `step` to the pass manager and returns its name. A textual step
+    /// replaces the passes added before it, so it has to come first.
+    fn add_pipeline_step<'a>(
+        &self,
+        pass_manager: &PassManager,
+        step: &PipelineStep<'a>,
+    ) -> Result<&'a str, CompileError> {
+        match step {
+            PipelineStep::Pass(name, add_pass) => {
+                add_pass(pass_manager);
+                Ok(name)
+            }
+            PipelineStep::Text(pipeline) => {
+                // Pipelines are parsed anchored on the op the manager runs on
+                pass::parse_pass_pipeline(
+                    pass_manager.as_operation_pass_manager(),
+                    &format!("builtin.module({})", pipeline),
+                )
+                .map_err(|err| CompileError::PassPipeline(err, self.take_diagnostics()))?;
+                Ok(pipeline)
+            }
+        }
+    }
+
+    fn take_diagnostics(&self) -> Vec<String> {
+        self.diagnostics.borrow_mut().drain(..).collect()
+    }
+}
+
+/// The function the program is entered at, `main` when it's defined and
+/// `_mlir_ciface_main` otherwise.
+fn entry_point(parser_result: &ParserResult) -> Option<&'static str> {
+    let fn_index = &parser_result.index.fn_prototype_index;
+
+    ["main", "_mlir_ciface_main"]
+        .into_iter()
+        .find(|name| fn_index.contains_key(*name))
+}
+
+/// Whether the program's entry point returns an exit status, see `Def::main_fn`.
+fn returns_status(parser_result: &ParserResult) -> bool {
+    let fn_index = &parser_result.index.fn_prototype_index;
+
+    fn_index.contains_key("main")
+        || fn_index
+            .get("_mlir_ciface_main")
+            .is_some_and(|prototype| prototype.return_type == Some(BaseType::Int32))
+}
+
+/// Whether the program's entry point takes the program's arguments.
+fn takes_args(parser_result: &ParserResult) -> bool {
+    entry_point(parser_result).is_some_and(|name| {
+        !parser_result.index.fn_prototype_index[name]
+            .args
+            .is_empty()
+    })
+}
+
+/// Whether the module generated for the JIT has a C `main`, either the
+/// program's own or one generated to pass the program's arguments in.
+fn has_c_main(parser_result: &ParserResult) -> bool {
+    let fn_index = &parser_result.index.fn_prototype_index;
+
+    fn_index
+        .get("main")
+        .is_some_and(|prototype| prototype.args.is_empty())
+        || takes_args(parser_result)
+}
+
+/// The entry point a C `main` has to be generated for, with whether it returns
+/// a status. Executables need one unless the program defines a `main` of its
+/// own, and entry points that take the program's arguments always need one to
+/// pass them in.
+fn c_main_entry_point(
+    parser_result: &ParserResult,
+    executable: bool,
+) -> Result<Option<(&'static str, bool)>, CompileError> {
+    let Some(entry_point) = entry_point(parser_result) else {
+        if executable {
+            return Err(CompileError::Codegen(
+                "No main or _mlir_ciface_main function defined",
+            ));
+        }
+
+        return Ok(None);
+    };
+
+    if parser_result.index.fn_prototype_index[entry_point].args.len() > 1 {
+        return Err(CompileError::Codegen(
+            "main only takes one argument, a StrArray of the program's arguments",
+        ));
+    }
+
+    if takes_args(parser_result) || (executable && entry_point != "main") {
+        Ok(Some((entry_point, returns_status(parser_result))))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Prints the module with the source location of every operation, so dumps
+/// can be traced back to the Pajama code they came from.
+fn print_with_locations(mlir_module: &Module) -> String {
+    let flags = OperationPrintingFlags::new().enable_debug_info(true, false);
+
+    mlir_module
+        .as_operation()
+        .to_string_with_flags(flags)
+        .unwrap_or_else(|_| mlir_module.as_operation().to_string())
+}
+
+thread_local! {
+    /// How many `guard`s the thread is running in, the panic hook stays quiet
+    /// while it's in one.
+    static GUARD_DEPTH: Cell<usize> = Cell::new(0);
+}
+
+/// Turns a panic inside a stage into an error, the lexer, analyzer and
+/// codegen still have `todo!()`s for input they don't handle yet. The panic
+/// isn't printed, the error is reported instead.
+///
+/// This is a stopgap until those stages return errors for everything: it
+/// relies on unwinding, so under `panic = "abort"` a `todo!()` still ends the
+/// process.
+pub(crate) fn guard<T>(
+    stage: &'static str,
+    run: impl FnOnce() -> Result<T, CompileError>,
+) -> Result<T, CompileError> {
+    install_quiet_panic_hook();
+
+    GUARD_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let result = panic::catch_unwind(AssertUnwindSafe(run));
+    GUARD_DEPTH.with(|depth| depth.set(depth.get() - 1));
+
+    match result {
+        Ok(result) => result,
+        Err(payload) => Err(CompileError::Internal(stage, panic_message(payload))),
+    }
+}
+
+/// Wraps the panic hook so that it skips panics inside a `guard`, panics
+/// anywhere else are printed as before.
+fn install_quiet_panic_hook() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let previous_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if GUARD_DEPTH.with(Cell::get) == 0 {
+                previous_hook(info);
+            }
+        }));
+    });
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+fn optimization_steps(opt_level: usize) -> Vec<PipelineStep<'static>> {
+    let canonicalize = PipelineStep::Pass("canonicalize", |pass_manager| {
+        pass_manager.add_pass(pass::transform::create_canonicalizer())
+    });
+    let cse = PipelineStep::Pass("cse", |pass_manager| {
+        pass_manager.add_pass(pass::transform::create_cse())
+    });
+    let inline = PipelineStep::Pass("inline", |pass_manager| {
+        pass_manager.add_pass(pass::transform::create_inliner())
+    });
+    let sccp = PipelineStep::Pass("sccp", |pass_manager| {
+        pass_manager.add_pass(pass::transform::create_sccp())
+    });
+    let symbol_dce = PipelineStep::Pass("symbol-dce", |pass_manager| {
+        pass_manager.add_pass(pass::transform::create_symbol_dce())
+    });
+
+    match opt_level {
+        0 => vec![],
+        1 => vec![canonicalize, cse],
+        2 => vec![inline, canonicalize, cse, sccp, symbol_dce],
+        _ => vec![
+            inline,
+            canonicalize,
+            cse,
+            sccp,
+            canonicalize,
+            cse,
+            symbol_dce,
+        ],
+    }
+}
+
+/// Lowers everything to the LLVM dialect, these always run.
+fn conversion_steps() -> Vec<PipelineStep<'static>> {
+    vec![
+        // Integer `**` becomes a call to a function implementing it
+        PipelineStep::Pass("convert-math-to-funcs", |pass_manager| {
+            pass_manager.add_pass(conversion::create_math_to_funcs())
+        }),
+        PipelineStep::Pass("convert-func-to-llvm", |pass_manager| {
+            pass_manager.add_pass(conversion::create_func_to_llvm())
+        }),
+        PipelineStep::Pass("llvm.func(convert-arith-to-llvm)", |pass_manager| {
+            pass_manager
+                .nested_under("llvm.func")
+                .add_pass(conversion::create_arith_to_llvm())
+        }),
+        PipelineStep::Pass("llvm.func(convert-index-to-llvm)", |pass_manager| {
+            pass_manager
+                .nested_under("llvm.func")
+                .add_pass(conversion::create_index_to_llvm())
+        }),
+        PipelineStep::Pass("llvm.func(convert-math-to-llvm)", |pass_manager| {
+            pass_manager
+                .nested_under("llvm.func")
+                .add_pass(conversion::create_math_to_llvm())
+        }),
+        PipelineStep::Pass("convert-scf-to-cf", |pass_manager| {
+            pass_manager.add_pass(conversion::create_scf_to_control_flow())
+        }),
+        PipelineStep::Pass("convert-cf-to-llvm", |pass_manager| {
+            pass_manager.add_pass(conversion::create_control_flow_to_llvm())
+        }),
+        PipelineStep::Pass("finalize-memref-to-llvm", |pass_manager| {
+            pass_manager.add_pass(conversion::create_finalize_mem_ref_to_llvm())
+        }),
+        PipelineStep::Pass("convert-func-to-llvm", |pass_manager| {
+            pass_manager.add_pass(conversion::create_func_to_llvm())
+        }),
+    ]
+}
+
+/// Splits a textual pass pipeline on its top level commas so that each piece
+/// can be timed, `inline,llvm.func(canonicalize,cse)` becomes two steps.
+fn split_pass_pipeline(pipeline: &str) -> Vec<&str> {
+    let mut steps = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, ch) in pipeline.char_indices() {
+        match ch {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                steps.push(pipeline[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    steps.push(pipeline[start..].trim());
+    steps.retain(|step| !step.is_empty());
+    steps
+}
+
+fn build_op_precedence_map() -> HashMap<[char; 4], i32> {
+    let mut op_precedence_map = HashMap::with_capacity(19);
+
+    op_precedence_map.insert(['|', '|', '\0', '\0'], 2); // Logical or
+    op_precedence_map.insert(['&', '&', '\0', '\0'], 3); // Logical and
+    op_precedence_map.insert(['=', '=', '\0', '\0'], 5); // Equal to
+    op_precedence_map.insert(['!', '=', '\0', '\0'], 5); // Not equal to
+    op_precedence_map.insert(['<', '\0', '\0', '\0'], 10); // Less than
+    op_precedence_map.insert(['>', '\0', '\0', '\0'], 10); // Greater than
+    op_precedence_map.insert(['<', '=', '\0', '\0'], 10); // Less than or equal to
+    op_precedence_map.insert(['>', '=', '\0', '\0'], 10); // Greater than or equal to
+    op_precedence_map.insert(['|', '\0', '\0', '\0'], 12); // Bitwise or
+    op_precedence_map.insert(['^', '\0', '\0', '\0'], 13); // Bitwise xor
+    op_precedence_map.insert(['&', '\0', '\0', '\0'], 14); // Bitwise and
+    op_precedence_map.insert(['<', '<', '\0', '\0'], 15); // Left shift
+    op_precedence_map.insert(['>', '>', '\0', '\0'], 15); // Right shift
+    op_precedence_map.insert(['+', '\0', '\0', '\0'], 20); // Addition
+    op_precedence_map.insert(['-', '\0', '\0', '\0'], 20); // Subtraction
+    op_precedence_map.insert(['*', '\0', '\0', '\0'], 40); // Multiplication
+    op_precedence_map.insert(['/', '\0', '\0', '\0'], 40); // Division
+    op_precedence_map.insert(['%', '\0', '\0', '\0'], 40); // Modulo
+    op_precedence_map.insert(['*', '*', '\0', '\0'], 50); // Exponentiation
+
+    op_precedence_map
+}
@@ -0,0 +1,75 @@
+use crate::lexer::TokenPosition;
+
+/// Identifies one of the files a program was compiled from, by its index in
+/// the `SourceMap`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FileId(pub usize);
+
+/// Where a node came from: the file, the line within that file and the
+/// columns it spans, all starting at 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub file: FileId,
+    pub line: usize,
+    pub start_column: usize,
+    pub end_column: usize,
+}
+
+#[derive(Debug, Clone)]
+struct SourceFile {
+    name: String,
+    /// The line of the concatenated source the file starts on
+    first_line: usize,
+}
+
+/// The files that were concatenated into the source the lexer sees, so that
+/// a position in that source can be traced back to a file and a line in it.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Concatenates `(name, source)` pairs into a single source, each one
+    /// followed by a new line, and returns the map of where each file is.
+    pub fn concat(files: Vec<(String, String)>) -> (SourceMap, String) {
+        let mut source_map = SourceMap::default();
+        let mut input = String::new();
+
+        for (name, source) in files {
+            let first_line = input.matches('\n').count() + 1;
+
+            source_map.files.push(SourceFile { name, first_line });
+            input.push_str(&source);
+            input.push('\n');
+        }
+
+        (source_map, input)
+    }
+
+    /// The span of a token. Without any files the line is left as is, in
+    /// `FileId(0)`.
+    pub fn span(&self, position: &TokenPosition) -> Span {
+        let line = position.line();
+
+        let (file, first_line) = self
+            .files
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, file)| file.first_line <= line)
+            .map(|(index, file)| (FileId(index), file.first_line))
+            .unwrap_or((FileId(0), 1));
+
+        Span {
+            file,
+            line: line - first_line + 1,
+            start_column: position.start_column(),
+            end_column: position.end_column(),
+        }
+    }
+
+    pub fn file_name(&self, file: FileId) -> Option<&str> {
+        self.files.get(file.0).map(|file| file.name.as_str())
+    }
+}
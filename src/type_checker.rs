@@ -0,0 +1,433 @@
+use crate::parser::{self, BaseType, Node, ParserResult, ParserResultIndex, Prototype};
+use crate::semantic_analyzer::Diagnostics;
+
+/// Checks an analyzed program against the types it declares: every call
+/// against the prototype in `fn_prototype_index`, every method send against
+/// the method of the receiver's class and the value of every function against
+/// its `-> Type`. Codegen relies on these holding, it only converts numbers
+/// where no value is lost and casts raw pointers to classes.
+pub struct TypeChecker<'a> {
+    index: &'a ParserResultIndex,
+    diagnostics: Diagnostics,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn run(result: &'a ParserResult) -> Diagnostics {
+        let mut checker = TypeChecker {
+            index: &result.index,
+            diagnostics: Diagnostics::default(),
+        };
+
+        if let Node::Module(module) = &result.module {
+            for node in &module.methods {
+                if let Node::Def(def_node) = node {
+                    checker.check_def(def_node);
+                }
+            }
+        }
+
+        checker.diagnostics.locate(&result.source_map);
+        checker.diagnostics
+    }
+
+    fn check_def(&mut self, def_node: &parser::Def) {
+        for node in &def_node.body {
+            self.check_node(node, &def_node.prototype);
+        }
+
+        // Entry points return an exit status, and `.new` and `.alloca` return
+        // their value by sret
+        let prototype = &def_node.prototype;
+        if def_node.main_fn
+            || prototype.name.ends_with(".new")
+            || prototype.name.ends_with(".alloca")
+        {
+            return;
+        }
+
+        let Some(return_type) = &prototype.return_type else {
+            return;
+        };
+
+        match def_node.body.last() {
+            // A `ret` is checked where it is
+            Some(Node::Ret(_)) => {}
+            Some(node) => self.check_return_value(node, prototype, return_type),
+            None => self.diagnostics.error(
+                def_node.span,
+                format!(
+                    "`{}` returns `{}` but its body is empty",
+                    prototype.name, return_type
+                ),
+            ),
+        }
+    }
+
+    fn check_node(&mut self, node: &Node, prototype: &Prototype) {
+        match node {
+            Node::Call(call_node) => {
+                for arg in &call_node.args {
+                    self.check_node(arg, prototype);
+                }
+
+                self.check_args(call_node, 0);
+            }
+            Node::Send(send_node) => {
+                self.check_node(&send_node.receiver, prototype);
+
+                if let Node::Call(call_node) = send_node.message.as_ref() {
+                    for arg in &call_node.args {
+                        self.check_node(arg, prototype);
+                    }
+
+                    // Calling a function reference doesn't go through a method
+                    if self.node_type(&send_node.receiver) != Some(BaseType::FnRef) {
+                        // The receiver is passed as the first argument
+                        self.check_args(call_node, 1);
+                    }
+                }
+            }
+            Node::Ret(ret_node) => {
+                self.check_node(&ret_node.value, prototype);
+
+                if let Some(return_type) = &prototype.return_type {
+                    self.check_return_value(&ret_node.value, prototype, return_type);
+                }
+            }
+            Node::Access(access_node) => self.check_node(&access_node.receiver, prototype),
+            Node::Array(array) => {
+                for item in &array.items {
+                    self.check_node(item, prototype);
+                }
+            }
+            Node::AssignAttribute(node) => self.check_node(&node.value, prototype),
+            Node::AssignAttributeAccess(node) => {
+                self.check_node(&node.access.receiver, prototype);
+                self.check_node(&node.value, prototype);
+            }
+            Node::AssignLocalVar(node) => self.check_node(&node.value, prototype),
+            Node::Binary(binary_node) => {
+                self.check_node(&binary_node.left, prototype);
+                self.check_node(&binary_node.right, prototype);
+
+                let (expected, is_expected): (&str, fn(&BaseType) -> bool) = match binary_node.op {
+                    _ if binary_node.is_logical() => {
+                        ("`Bool`s", |operand_type| *operand_type == BaseType::Bool)
+                    }
+                    ['&', '\0', '\0', '\0'] | ['|', '\0', '\0', '\0'] | ['^', '\0', '\0', '\0'] => {
+                        ("integers or `Bool`s", |operand_type| {
+                            operand_type.is_integer() || *operand_type == BaseType::Bool
+                        })
+                    }
+                    ['<', '<', '\0', '\0'] | ['>', '>', '\0', '\0'] => {
+                        ("integers", BaseType::is_integer)
+                    }
+                    ['=', '=', '\0', '\0'] | ['!', '=', '\0', '\0'] => {
+                        ("numbers or `Bool`s", |operand_type| {
+                            operand_type.is_number() || *operand_type == BaseType::Bool
+                        })
+                    }
+                    _ => ("numbers", BaseType::is_number),
+                };
+
+                for operand in [&binary_node.left, &binary_node.right] {
+                    self.check_operand(binary_node.op, operand, expected, is_expected);
+                }
+
+                // Numbers are converted to a common type, `Bool`s aren't
+                let operand_types = (
+                    self.node_type(&binary_node.left),
+                    self.node_type(&binary_node.right),
+                );
+                if let (Some(left_type), Some(right_type)) = operand_types {
+                    if is_expected(&left_type)
+                        && is_expected(&right_type)
+                        && (left_type == BaseType::Bool) != (right_type == BaseType::Bool)
+                    {
+                        self.diagnostics.error(
+                            binary_node.span,
+                            format!(
+                                "mismatched types: `{}` can't mix `{}` and `{}`",
+                                op_name(binary_node.op),
+                                left_type,
+                                right_type
+                            ),
+                        );
+                    }
+                }
+            }
+            Node::Unary(unary_node) => {
+                let (op, operand) = (unary_node.op, unary_node.operand.as_ref());
+                self.check_node(operand, prototype);
+
+                match op {
+                    ['!', '\0', '\0', '\0'] => {
+                        self.check_operand(op, operand, "a `Bool`", |operand_type| {
+                            *operand_type == BaseType::Bool
+                        })
+                    }
+                    ['~', '\0', '\0', '\0'] => {
+                        self.check_operand(op, operand, "an integer", BaseType::is_integer)
+                    }
+                    _ => self.check_operand(op, operand, "a number", BaseType::is_number),
+                }
+            }
+            Node::BuildStruct(struct_node) => {
+                for arg in &struct_node.args {
+                    self.check_node(arg, prototype);
+                }
+            }
+            Node::Loop(loop_node) => {
+                if let Some(condition) = &loop_node.condition {
+                    let keyword = if loop_node.until {
+                        "an `until`"
+                    } else {
+                        "a `while`"
+                    };
+                    self.check_condition(condition, keyword, prototype);
+                }
+
+                for node in &loop_node.body {
+                    self.check_node(node, prototype);
+                }
+            }
+            Node::Break(break_node) => {
+                if let Some(value) = &break_node.value {
+                    self.check_node(value, prototype);
+                }
+            }
+            Node::If(if_node) => {
+                self.check_condition(&if_node.condition, "an `if`", prototype);
+
+                for node in if_node
+                    .then_body
+                    .iter()
+                    .chain(if_node.else_body.iter().flatten())
+                {
+                    self.check_node(node, prototype);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks that the condition of an `if` or a loop is a `Bool`.
+    fn check_condition(&mut self, condition: &Node, keyword: &str, prototype: &Prototype) {
+        self.check_node(condition, prototype);
+
+        if let Some(condition_type) = self.node_type(condition) {
+            if condition_type != BaseType::Bool {
+                self.diagnostics.error(
+                    condition.span(),
+                    format!(
+                        "mismatched types: the condition of {} is `Bool`, found `{}`",
+                        keyword, condition_type
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Checks that an operand of an operator is of a type the operator takes.
+    fn check_operand(
+        &mut self,
+        op: [char; 4],
+        operand: &Node,
+        expected: &str,
+        is_expected: impl Fn(&BaseType) -> bool,
+    ) {
+        if let Some(operand_type) = self.node_type(operand) {
+            if !is_expected(&operand_type) {
+                self.diagnostics.error(
+                    operand.span(),
+                    format!(
+                        "mismatched types: `{}` takes {}, found `{}`",
+                        op_name(op),
+                        expected,
+                        operand_type
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Checks the arguments of a call against its prototype, skipping the
+    /// first `implicit_args` parameters.
+    fn check_args(&mut self, call_node: &parser::Call, implicit_args: usize) {
+        let Some(callee) = self.index.fn_prototype_index.get(&call_node.fn_name) else {
+            // Undefined functions are reported by the semantic analyzer
+            return;
+        };
+
+        let params = callee.args.get(implicit_args..).unwrap_or_default();
+
+        if params.len() != call_node.args.len() {
+            self.diagnostics.error(
+                call_node.span,
+                format!(
+                    "`{}` takes {} argument{} but {} {} given",
+                    call_node.fn_name,
+                    params.len(),
+                    if params.len() == 1 { "" } else { "s" },
+                    call_node.args.len(),
+                    if call_node.args.len() == 1 {
+                        "was"
+                    } else {
+                        "were"
+                    }
+                ),
+            );
+            return;
+        }
+
+        for (arg, param) in call_node.args.iter().zip(params) {
+            let Some(arg_type) = self.node_type(arg) else {
+                continue;
+            };
+
+            if !is_assignable(arg, &arg_type, &param.return_type) {
+                self.diagnostics.error(
+                    arg.span(),
+                    format!(
+                        "mismatched types: `{}` of `{}` is `{}`, found `{}`",
+                        param.name, call_node.fn_name, param.return_type, arg_type
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_return_value(&mut self, node: &Node, prototype: &Prototype, return_type: &BaseType) {
+        let Some(value_type) = self.node_type(node) else {
+            return;
+        };
+
+        if !is_assignable(node, &value_type, return_type) {
+            self.diagnostics.error(
+                node.span(),
+                format!(
+                    "mismatched types: `{}` returns `{}`, found `{}`",
+                    prototype.name, return_type, value_type
+                ),
+            );
+        }
+    }
+
+    /// The type of an expression, when it's known after semantic analysis.
+    fn node_type(&self, node: &Node) -> Option<BaseType> {
+        match node {
+            Node::Access(node) => node.return_type.clone(),
+            Node::Array(array) => Some(BaseType::Array(
+                array.length,
+                Box::new(array.item_type.clone()),
+            )),
+            Node::Binary(binary_node)
+                if binary_node.is_comparison() || binary_node.is_logical() =>
+            {
+                Some(BaseType::Bool)
+            }
+            // Integers are converted to floats when the other side is one,
+            // and to the wider integer type otherwise
+            Node::Binary(binary_node) => {
+                let left_type = self.node_type(&binary_node.left);
+
+                match (&left_type, self.node_type(&binary_node.right)) {
+                    (Some(left), Some(right)) => BaseType::float_promotion(left, &right)
+                        .or_else(|| BaseType::integer_promotion(left, &right))
+                        .or(left_type),
+                    _ => left_type,
+                }
+            }
+            Node::BuildStruct(struct_node) => self
+                .index
+                .struct_index
+                .get(&struct_node.name)
+                .map(|struct_node| struct_node.return_type.clone()),
+            Node::Bool(_) => Some(BaseType::Bool),
+            Node::Call(call_node) => call_node.return_type.clone(),
+            Node::Const(const_node) => self.index.constant_index.get(&const_node.name).cloned(),
+            Node::Float(_) => Some(BaseType::Float64),
+            Node::FnRef(_) => Some(BaseType::FnRef),
+            Node::If(if_node) => if_node.return_type.clone(),
+            Node::Int(_) => Some(BaseType::Int),
+            Node::Loop(loop_node) => loop_node.return_type.clone(),
+            Node::Unary(unary_node) if unary_node.op[0] == '!' => Some(BaseType::Bool),
+            Node::Unary(unary_node) => self.node_type(&unary_node.operand),
+            Node::LocalVar(lvar) => lvar.return_type.clone(),
+            Node::SelfRef(self_ref) => Some(self_ref.return_type.clone()),
+            Node::Send(send_node) => send_node.return_type.clone(),
+            Node::StringLiteral(_) => Some(BaseType::Class("Str".to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// The operator as it's written in a program, for diagnostics.
+fn op_name(op: [char; 4]) -> String {
+    op.iter().filter(|&&ch| ch != '\0').collect()
+}
+
+/// Whether a value of type `found` can be passed where `expected` is
+/// declared. Numbers are converted between widths and between integers and
+/// floats, and the raw pointers the runtime allocates are cast to and from
+/// classes.
+/// Whether `node`, of type `found`, can be passed where `expected` is.
+/// Numbers are only converted where no value is lost, see `is_widening`,
+/// except literals, which convert to any number type they fit in.
+fn is_assignable(node: &Node, found: &BaseType, expected: &BaseType) -> bool {
+    match (found, expected) {
+        _ if found == expected => true,
+        _ if found.is_number() && expected.is_number() => {
+            is_widening(found, expected) || is_fitting_literal(node, expected)
+        }
+        (BaseType::BytePtr, BaseType::Class(_)) | (BaseType::Class(_), BaseType::BytePtr) => true,
+        (BaseType::Array(_, found_item), BaseType::Array(_, expected_item)) => {
+            found_item == expected_item
+        }
+        _ => false,
+    }
+}
+
+/// Whether every number of type `found` converts to `expected`: integers to
+/// integers at least as wide and to floats, and a `Float32` to a `Float64`.
+fn is_widening(found: &BaseType, expected: &BaseType) -> bool {
+    match (found.integer_width(), expected.integer_width()) {
+        (Some(found_width), Some(expected_width)) => found_width <= expected_width,
+        (Some(_), None) => true,
+        (None, Some(_)) => false,
+        (None, None) => *expected == BaseType::Float64,
+    }
+}
+
+/// Whether `node` is a literal number, possibly negated, that `expected` can
+/// hold, like `1` for an `Int32` or `0.5` for a `Float32`, or an `if` whose
+/// branches both end in one. A `Byte` is unsigned, the other integers are
+/// signed.
+fn is_fitting_literal(node: &Node, expected: &BaseType) -> bool {
+    match node {
+        Node::If(if_node) => {
+            let Some(else_body) = &if_node.else_body else {
+                return false;
+            };
+
+            [&if_node.then_body, else_body].iter().all(|body| {
+                body.last()
+                    .is_some_and(|node| is_fitting_literal(node, expected))
+            })
+        }
+        Node::Float(_) => expected.is_float(),
+        Node::Int(int_node) => match expected.integer_width() {
+            Some(8) => int_node.value <= u8::MAX as u64,
+            Some(width) => int_node.value < 1 << (width - 1),
+            None => true,
+        },
+        Node::Unary(unary_node) if unary_node.op == ['-', '\0', '\0', '\0'] => {
+            match (unary_node.operand.as_ref(), expected.integer_width()) {
+                (Node::Int(int_node), Some(8)) => int_node.value == 0,
+                (Node::Int(int_node), Some(width)) => int_node.value <= 1 << (width - 1),
+                (operand, _) => is_fitting_literal(operand, expected),
+            }
+        }
+        _ => false,
+    }
+}
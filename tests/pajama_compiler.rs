@@ -1,20 +1,29 @@
-use pajama::pajama_compiler::PajamaCompiler;
+use pajama::dump::{DumpOptions, DumpPhase, DumpSink};
+use pajama::pajama_compiler::{CompileError, CompileOptions, LlvmFormat, PajamaCompiler};
+use pajama::parser::{BaseType, Def, Node, ParserResult};
+use pajama::repl::Repl;
+use pajama::session::Session;
+use pajama::source::{FileId, SourceMap, Span};
 
 use indoc::indoc;
 
 macro_rules! build_test_fn {
     ($name:ident, $input:expr, $expected_output:expr) => {
+        build_test_fn!($name, "", $input, $expected_output);
+    };
+    // `$definitions` come before the main function, which can call them
+    ($name:ident, $definitions:expr, $input:expr, $expected_output:expr) => {
         #[test]
         fn $name() {
             // Use `format!` so that $input is properly inserted into the string
             let input = format!(
-                "def _mlir_ciface_main
+                "{}def _mlir_ciface_main
                 {}
                 end",
-                $input
+                $definitions, $input
             );
 
-            let compiler_output = PajamaCompiler::compile_to_string(&input);
+            let compiler_output = PajamaCompiler::compile_to_string(&input).unwrap();
             let expected_output = $expected_output;
 
             assert_eq!(compiler_output, expected_output);
@@ -22,6 +31,17 @@ macro_rules! build_test_fn {
     };
 }
 
+/// The function at `index` among the top level definitions of a program.
+fn def_at(parser_result: &ParserResult, index: usize) -> &Def {
+    let Node::Module(module) = &parser_result.module else {
+        panic!("expected a module");
+    };
+    let Node::Def(def) = &module.methods[index] else {
+        panic!("expected a def");
+    };
+
+    def
+}
 
 //
 // Constant
@@ -193,7 +213,6 @@ build_test_fn! {
   "}
 }
 
-
 //
 // Operators
 //
@@ -266,3 +285,1867 @@ build_test_fn! {
       }
   "}
 }
+
+//
+// LLVM output
+//
+
+#[test]
+fn llvm_ir_output() {
+    let input = indoc! {"
+        def _mlir_ciface_main
+          a = 1 + 1
+        end
+    "};
+
+    let options = CompileOptions {
+        opt_level: 0,
+        ..CompileOptions::default()
+    };
+
+    let llvm_ir = match PajamaCompiler::compile_to_llvm(input, LlvmFormat::Ir, &options) {
+        // The translation runs the LLVM command line tools, which are optional
+        Err(CompileError::MissingTool(program)) => {
+            eprintln!(
+                "skipping llvm_ir_output, `{}` isn't installed",
+                program.display()
+            );
+            return;
+        }
+        result => String::from_utf8(result.unwrap()).unwrap(),
+    };
+
+    assert!(llvm_ir.contains("define void @_mlir_ciface_main()"));
+}
+
+#[test]
+fn object_output() {
+    let input = indoc! {"
+        def main -> Int32
+          0
+        end
+    "};
+
+    let object_path = std::env::temp_dir().join("pajama_object_output.o");
+    let _ = std::fs::remove_file(&object_path);
+
+    PajamaCompiler::build_object(input, &object_path, &CompileOptions::default()).unwrap();
+
+    let object = std::fs::read(&object_path).unwrap();
+    std::fs::remove_file(&object_path).unwrap();
+
+    assert!(!object.is_empty());
+}
+
+#[test]
+fn custom_pass_pipeline() {
+    let input = indoc! {"
+        def _mlir_ciface_main
+          a = 1 + 1
+        end
+    "};
+
+    let options = CompileOptions {
+        pass_pipeline: Some("canonicalize,cse".to_string()),
+        ..CompileOptions::default()
+    };
+
+    let mlir = PajamaCompiler::compile_to_mlir(input, true, &options).unwrap();
+
+    assert!(mlir.contains("llvm.func @_mlir_ciface_main()"));
+}
+
+#[test]
+fn custom_pass_pipeline_runs_every_pass() {
+    let input = indoc! {"
+        def_e unused(value Int) -> Int
+
+        def _mlir_ciface_main
+          a = 1 + 1
+        end
+    "};
+
+    for time_passes in [false, true] {
+        let options = CompileOptions {
+            pass_pipeline: Some("symbol-dce,cse".to_string()),
+            time_passes,
+            ..CompileOptions::default()
+        };
+
+        let mlir = PajamaCompiler::compile_to_mlir(input, true, &options).unwrap();
+
+        // `symbol-dce` dropped the unused declaration, though `cse` comes after
+        assert!(mlir.contains("llvm.func @_mlir_ciface_main()"));
+        assert!(!mlir.contains("@unused"));
+    }
+}
+
+//
+// Session
+//
+
+#[test]
+fn session_stages() {
+    let session = Session::new(CompileOptions::default());
+
+    let tokens = session.tokenize("def _mlir_ciface_main\n  1\nend\n").unwrap();
+    let parser_result = session.parse(tokens).unwrap();
+    let parser_result = session.analyze(parser_result).unwrap();
+    let mut mlir_module = session.generate(&parser_result).unwrap();
+
+    session.lower(&mut mlir_module).unwrap();
+
+    assert!(mlir_module
+        .body()
+        .to_string()
+        .contains("llvm.func @_mlir_ciface_main()"));
+}
+
+#[test]
+fn session_reports_errors_instead_of_panicking() {
+    let session = Session::new(CompileOptions::default());
+
+    assert!(session.check("def _mlir_ciface_main\n  ~\nend\n").is_err());
+}
+
+#[test]
+fn semantic_analysis_reports_diagnostics() {
+    let (source_map, input) = SourceMap::concat(vec![(
+        "main.pjs".to_string(),
+        "def _mlir_ciface_main\n  missing(1)\nend".to_string(),
+    )]);
+
+    let session = Session::new(CompileOptions {
+        source_map,
+        ..CompileOptions::default()
+    });
+
+    let diagnostics = match session.check(&input) {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics,
+        result => panic!("expected semantic errors, got {:?}", result),
+    };
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "undefined function `missing`\n --> main.pjs:2:3"
+    );
+
+    let input = indoc! {"
+        def one() -> Int
+          ret 1
+          2
+        end
+    "};
+
+    session.check(input).unwrap();
+
+    let warnings = session.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].message, "unreachable code after `ret`");
+    assert_eq!(warnings[0].span.line, 3);
+}
+
+#[test]
+fn warnings_are_kept_by_the_callers_session() {
+    let input = indoc! {"
+        def main() -> Int32
+          ret 0
+          1
+        end
+    "};
+
+    let session = Session::new(CompileOptions::default());
+
+    // Whether or not codegen accepts the unreachable code, analysis ran first
+    let _ = PajamaCompiler::compile_to_mlir_in(&session, input, false);
+
+    let warnings = session.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].message, "unreachable code after `ret`");
+}
+
+#[test]
+fn local_vars_are_bound_to_their_declaration() {
+    let input = indoc! {"
+        def count(start Int) -> Int
+          total = start
+          total + 1
+        end
+    "};
+
+    let parser_result = PajamaCompiler::check(input).unwrap();
+
+    let def = def_at(&parser_result, 0);
+    let Node::AssignLocalVar(assignment) = &def.body[0] else {
+        panic!("expected an assignment");
+    };
+    let Node::LocalVar(start) = assignment.value.as_ref() else {
+        panic!("expected a local variable");
+    };
+    let Node::Binary(binary) = &def.body[1] else {
+        panic!("expected a binary expression");
+    };
+    let Node::LocalVar(total) = binary.left.as_ref() else {
+        panic!("expected a local variable");
+    };
+
+    assert_eq!(start.declaration, Some(def.prototype.args[0].span));
+    assert_eq!(total.declaration, Some(assignment.span));
+}
+
+#[test]
+fn float_literals_are_typed() {
+    let input = indoc! {"
+        def scale(factor Float32) -> Float64
+          ratio = 2.5e-3
+          ratio * factor
+        end
+    "};
+
+    let parser_result = PajamaCompiler::check(input).unwrap();
+
+    let def = def_at(&parser_result, 0);
+    let Node::AssignLocalVar(assignment) = &def.body[0] else {
+        panic!("expected an assignment");
+    };
+    let Node::Float(literal) = assignment.value.as_ref() else {
+        panic!("expected a float");
+    };
+    let Node::Binary(binary) = &def.body[1] else {
+        panic!("expected a binary expression");
+    };
+    let (Node::LocalVar(ratio), Node::LocalVar(factor)) =
+        (binary.left.as_ref(), binary.right.as_ref())
+    else {
+        panic!("expected local variables");
+    };
+
+    assert_eq!(literal.value, 0.0025);
+    assert_eq!(ratio.return_type, Some(BaseType::Float64));
+    assert_eq!(factor.return_type, Some(BaseType::Float32));
+}
+
+build_test_fn! {
+  float_arithmetic,
+  indoc! {"
+    def average(total Float64, count Int) -> Float64
+      total / count + 0.5
+    end
+
+  "},
+  "average(3.5, 2)",
+  indoc! {"
+    ^bb0:
+      llvm.func @average(%arg0: f64, %arg1: i64) -> f64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x f64 : (i64) -> !llvm.ptr<f64>
+        llvm.store %arg0, %1 : !llvm.ptr<f64>
+        %2 = llvm.mlir.constant(1 : i64) : i64
+        %3 = llvm.alloca %2 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg1, %3 : !llvm.ptr<i64>
+        %4 = llvm.load %1 : !llvm.ptr<f64>
+        %5 = llvm.load %3 : !llvm.ptr<i64>
+        %6 = llvm.sitofp %5 : i64 to f64
+        %7 = llvm.fdiv %4, %6  : f64
+        %8 = llvm.mlir.constant(5.000000e-01 : f64) : f64
+        %9 = llvm.fadd %7, %8  : f64
+        llvm.return %9 : f64
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(3.500000e+00 : f64) : f64
+        %1 = llvm.mlir.constant(2 : i64) : i64
+        %2 = llvm.call @average(%0, %1) : (f64, i64) -> f64
+        llvm.return
+      }
+  "}
+}
+
+#[test]
+fn comparisons_are_bools() {
+    let input = indoc! {"
+        def in_range(number Int) -> Bool
+          low = number >= 1
+          low == true
+        end
+    "};
+
+    let parser_result = PajamaCompiler::check(input).unwrap();
+
+    let def = def_at(&parser_result, 0);
+    let Node::AssignLocalVar(assignment) = &def.body[0] else {
+        panic!("expected an assignment");
+    };
+    let Node::Binary(comparison) = assignment.value.as_ref() else {
+        panic!("expected a binary expression");
+    };
+    let Node::Binary(equality) = &def.body[1] else {
+        panic!("expected a binary expression");
+    };
+    let Node::LocalVar(low) = equality.left.as_ref() else {
+        panic!("expected a local variable");
+    };
+
+    assert_eq!(comparison.op, ['>', '=', '\0', '\0']);
+    assert!(matches!(equality.right.as_ref(), Node::Bool(bool_node) if bool_node.value));
+    assert_eq!(low.return_type, Some(BaseType::Bool));
+}
+
+build_test_fn! {
+  comparison_operators,
+  indoc! {"
+    def below(number Int, limit Int) -> Bool
+      number < limit
+    end
+
+    def differs(first Bool, second Bool) -> Bool
+      first != second
+    end
+
+  "},
+  "differs(below(3, 10), true)",
+  indoc! {"
+    ^bb0:
+      llvm.func @below(%arg0: i64, %arg1: i64) -> i1 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg0, %1 : !llvm.ptr<i64>
+        %2 = llvm.mlir.constant(1 : i64) : i64
+        %3 = llvm.alloca %2 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg1, %3 : !llvm.ptr<i64>
+        %4 = llvm.load %1 : !llvm.ptr<i64>
+        %5 = llvm.load %3 : !llvm.ptr<i64>
+        %6 = llvm.icmp \"slt\" %4, %5 : i64
+        llvm.return %6 : i1
+      }
+      llvm.func @differs(%arg0: i1, %arg1: i1) -> i1 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i1 : (i64) -> !llvm.ptr<i1>
+        llvm.store %arg0, %1 : !llvm.ptr<i1>
+        %2 = llvm.mlir.constant(1 : i64) : i64
+        %3 = llvm.alloca %2 x i1 : (i64) -> !llvm.ptr<i1>
+        llvm.store %arg1, %3 : !llvm.ptr<i1>
+        %4 = llvm.load %1 : !llvm.ptr<i1>
+        %5 = llvm.load %3 : !llvm.ptr<i1>
+        %6 = llvm.icmp \"ne\" %4, %5 : i1
+        llvm.return %6 : i1
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(3 : i64) : i64
+        %1 = llvm.mlir.constant(10 : i64) : i64
+        %2 = llvm.call @below(%0, %1) : (i64, i64) -> i1
+        %3 = llvm.mlir.constant(true) : i1
+        %4 = llvm.call @differs(%2, %3) : (i1, i1) -> i1
+        llvm.return
+      }
+  "}
+}
+
+#[test]
+fn if_expressions_are_typed() {
+    let input = indoc! {"
+        def sign(number Int) -> Int
+          if number < 0
+            0 - 1
+          elsif number == 0
+            0
+          else
+            1
+          end
+        end
+
+        def clamp(number Int) -> Int
+          number = 10 if number > 10
+          number
+        end
+    "};
+
+    let parser_result = PajamaCompiler::check(input).unwrap();
+
+    let def = def_at(&parser_result, 0);
+    let Node::If(if_node) = &def.body[0] else {
+        panic!("expected an if");
+    };
+    let Some([Node::If(elsif_node)]) = if_node.else_body.as_deref() else {
+        panic!("expected an elsif");
+    };
+
+    assert_eq!(if_node.return_type, Some(BaseType::Int));
+    assert_eq!(elsif_node.return_type, Some(BaseType::Int));
+
+    let def = def_at(&parser_result, 1);
+    let Node::If(postfix_if) = &def.body[0] else {
+        panic!("expected a postfix if");
+    };
+
+    assert!(matches!(postfix_if.then_body[0], Node::AssignLocalVar(_)));
+    assert!(postfix_if.else_body.is_none());
+}
+
+#[test]
+fn if_errors_are_reported() {
+    let input = indoc! {"
+        def pick(number Int)
+          choice = if number > 0
+            1
+          end
+        end
+
+        def mixed(flag Bool)
+          label = if flag
+            1
+          else
+            \"one\"
+          end
+        end
+
+        def log(number Int)
+          number
+        end
+
+        def logged(flag Bool)
+          count = if flag
+            1
+          else
+            log(1)
+          end
+
+          loop {
+            count = if flag
+              break
+            else
+              2
+            end
+          }
+        end
+    "};
+
+    let diagnostics = match PajamaCompiler::check(input) {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics,
+        result => panic!("expected if errors, got {:?}", result),
+    };
+
+    let errors: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.span.line, diagnostic.message.as_str()))
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            (2, "an `if` without an `else` can't be used as a value"),
+            (8, "`if` and `else` have different types, `Int` and `Str`"),
+            (20, "an `if` can't be used as a value when a branch has none"),
+            (27, "an `if` can't be used as a value when a branch has none"),
+        ]
+    );
+}
+
+build_test_fn! {
+  if_else_expression,
+  indoc! {"
+    def larger(first Int, second Int) -> Int
+      if first > second
+        first
+      else
+        second
+      end
+    end
+
+  "},
+  "larger(3, 10)",
+  indoc! {"
+    ^bb0:
+      llvm.func @larger(%arg0: i64, %arg1: i64) -> i64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg0, %1 : !llvm.ptr<i64>
+        %2 = llvm.mlir.constant(1 : i64) : i64
+        %3 = llvm.alloca %2 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg1, %3 : !llvm.ptr<i64>
+        %4 = llvm.load %1 : !llvm.ptr<i64>
+        %5 = llvm.load %3 : !llvm.ptr<i64>
+        %6 = llvm.icmp \"sgt\" %4, %5 : i64
+        llvm.cond_br %6, ^bb1, ^bb2
+      ^bb1:  // pred: ^bb0
+        %7 = llvm.load %1 : !llvm.ptr<i64>
+        llvm.br ^bb3(%7 : i64)
+      ^bb2:  // pred: ^bb0
+        %8 = llvm.load %3 : !llvm.ptr<i64>
+        llvm.br ^bb3(%8 : i64)
+      ^bb3(%9: i64):  // 2 preds: ^bb1, ^bb2
+        llvm.br ^bb4
+      ^bb4:  // pred: ^bb3
+        llvm.return %9 : i64
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(3 : i64) : i64
+        %1 = llvm.mlir.constant(10 : i64) : i64
+        %2 = llvm.call @larger(%0, %1) : (i64, i64) -> i64
+        llvm.return
+      }
+  "}
+}
+
+#[test]
+fn loops_break_with_values() {
+    let input = indoc! {"
+        def first_over(limit Int) -> Int
+          count = 0
+          while count < limit
+            count = count + 1
+            next if count == 2
+            break if count > 5
+          end
+
+          until count == 0
+            count = count - 1
+          end
+
+          found = loop {
+            count = count + 1
+            break count if count > limit
+          }
+          found
+        end
+    "};
+
+    let parser_result = PajamaCompiler::check(input).unwrap();
+
+    let def = def_at(&parser_result, 0);
+    let Node::Loop(while_loop) = &def.body[1] else {
+        panic!("expected a while loop");
+    };
+    let Node::Loop(until_loop) = &def.body[2] else {
+        panic!("expected an until loop");
+    };
+    let Node::AssignLocalVar(assignment) = &def.body[3] else {
+        panic!("expected an assignment");
+    };
+    let Node::Loop(value_loop) = assignment.value.as_ref() else {
+        panic!("expected a loop");
+    };
+
+    assert!(while_loop.condition.is_some() && !while_loop.until);
+    assert!(matches!(while_loop.body[1], Node::If(_)));
+    assert!(until_loop.condition.is_some() && until_loop.until);
+    assert!(value_loop.condition.is_none());
+    assert_eq!(value_loop.return_type, Some(BaseType::Int));
+}
+
+#[test]
+fn loop_errors_are_reported() {
+    let errors_of = |input| match PajamaCompiler::check(input) {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.span.line, diagnostic.message.clone()))
+            .collect::<Vec<_>>(),
+        result => panic!("expected loop errors, got {:?}", result),
+    };
+
+    let outside = indoc! {"
+        def outside(limit Int)
+          next
+          break if limit > 1
+        end
+    "};
+
+    assert_eq!(
+        errors_of(outside),
+        vec![
+            (2, "`next` outside of a loop".to_string()),
+            (3, "`break` outside of a loop".to_string()),
+        ]
+    );
+
+    let values = indoc! {"
+        def values(limit Int)
+          while limit > 1
+            break 3
+          end
+
+          done = until limit > 1
+            break
+          end
+
+          mixed = loop {
+            break 1 if limit > 1
+            break 2.5
+          }
+        end
+    "};
+
+    assert_eq!(
+        errors_of(values),
+        vec![
+            (
+                3,
+                "`break` with a value is only allowed in a `loop`".to_string()
+            ),
+            (
+                7,
+                "`break` needs a value to leave a loop used as a value".to_string()
+            ),
+            (
+                6,
+                "a loop that doesn't break with a value can't be used as a value".to_string()
+            ),
+            (
+                12,
+                "`break` values have different types, `Int` and `Float64`".to_string()
+            ),
+        ]
+    );
+}
+
+build_test_fn! {
+  while_loop,
+  indoc! {"
+    def count_to(limit Int) -> Int
+      count = 0
+      while count < limit
+        count = count + 1
+        break if count == 5
+      end
+      count
+    end
+
+  "},
+  "count_to(10)",
+  indoc! {"
+    ^bb0:
+      llvm.func @count_to(%arg0: i64) -> i64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg0, %1 : !llvm.ptr<i64>
+        %2 = llvm.mlir.constant(0 : i64) : i64
+        %3 = llvm.mlir.constant(1 : i64) : i64
+        %4 = llvm.alloca %3 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %2, %4 : !llvm.ptr<i64>
+        %5 = llvm.mlir.constant(false) : i1
+        %6 = llvm.mlir.constant(1 : i64) : i64
+        %7 = llvm.alloca %6 x i1 : (i64) -> !llvm.ptr<i1>
+        %8 = llvm.mlir.constant(1 : i64) : i64
+        %9 = llvm.alloca %8 x i1 : (i64) -> !llvm.ptr<i1>
+        llvm.store %5, %7 : !llvm.ptr<i1>
+        llvm.br ^bb1
+      ^bb1:  // 2 preds: ^bb0, ^bb8
+        %10 = llvm.load %7 : !llvm.ptr<i1>
+        %11 = llvm.mlir.constant(true) : i1
+        llvm.cond_br %10, ^bb2, ^bb3
+      ^bb2:  // pred: ^bb1
+        %12 = llvm.mlir.constant(false) : i1
+        llvm.br ^bb4(%12 : i1)
+      ^bb3:  // pred: ^bb1
+        %13 = llvm.load %4 : !llvm.ptr<i64>
+        %14 = llvm.load %1 : !llvm.ptr<i64>
+        %15 = llvm.icmp \"slt\" %13, %14 : i64
+        llvm.br ^bb4(%15 : i1)
+      ^bb4(%16: i1):  // 2 preds: ^bb2, ^bb3
+        llvm.br ^bb5
+      ^bb5:  // pred: ^bb4
+        llvm.cond_br %16, ^bb6, ^bb9
+      ^bb6:  // pred: ^bb5
+        %17 = llvm.mlir.constant(false) : i1
+        llvm.store %17, %9 : !llvm.ptr<i1>
+        %18 = llvm.load %4 : !llvm.ptr<i64>
+        %19 = llvm.mlir.constant(1 : i64) : i64
+        %20 = llvm.add %18, %19  : i64
+        llvm.store %20, %4 : !llvm.ptr<i64>
+        %21 = llvm.load %4 : !llvm.ptr<i64>
+        %22 = llvm.mlir.constant(5 : i64) : i64
+        %23 = llvm.icmp \"eq\" %21, %22 : i64
+        llvm.cond_br %23, ^bb7, ^bb8
+      ^bb7:  // pred: ^bb6
+        %24 = llvm.mlir.constant(true) : i1
+        llvm.store %24, %7 : !llvm.ptr<i1>
+        llvm.store %24, %9 : !llvm.ptr<i1>
+        llvm.br ^bb8
+      ^bb8:  // 2 preds: ^bb6, ^bb7
+        llvm.br ^bb1
+      ^bb9:  // pred: ^bb5
+        %25 = llvm.load %4 : !llvm.ptr<i64>
+        llvm.return %25 : i64
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(10 : i64) : i64
+        %1 = llvm.call @count_to(%0) : (i64) -> i64
+        llvm.return
+      }
+  "}
+}
+
+// The two variables and the `stop` and `skip` flags of both loops are
+// allocated in the entry block
+build_test_fn! {
+  nested_loops_allocate_in_the_entry_block,
+  indoc! {"
+    outer = 0
+    while outer < 3
+      inner = 0
+      while inner < 3
+        inner = inner + 1
+      end
+      outer = outer + 1
+    end
+  "},
+  indoc! {"
+    ^bb0:
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(0 : i64) : i64
+        %1 = llvm.mlir.constant(1 : i64) : i64
+        %2 = llvm.alloca %1 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %0, %2 : !llvm.ptr<i64>
+        %3 = llvm.mlir.constant(false) : i1
+        %4 = llvm.mlir.constant(1 : i64) : i64
+        %5 = llvm.alloca %4 x i1 : (i64) -> !llvm.ptr<i1>
+        %6 = llvm.mlir.constant(1 : i64) : i64
+        %7 = llvm.alloca %6 x i1 : (i64) -> !llvm.ptr<i1>
+        llvm.store %3, %5 : !llvm.ptr<i1>
+        %8 = llvm.mlir.constant(1 : i64) : i64
+        %9 = llvm.alloca %8 x i64 : (i64) -> !llvm.ptr<i64>
+        %10 = llvm.mlir.constant(1 : i64) : i64
+        %11 = llvm.alloca %10 x i1 : (i64) -> !llvm.ptr<i1>
+        %12 = llvm.mlir.constant(1 : i64) : i64
+        %13 = llvm.alloca %12 x i1 : (i64) -> !llvm.ptr<i1>
+        llvm.br ^bb1
+      ^bb1:  // 2 preds: ^bb0, ^bb13
+        %14 = llvm.load %5 : !llvm.ptr<i1>
+        %15 = llvm.mlir.constant(true) : i1
+        llvm.cond_br %14, ^bb2, ^bb3
+      ^bb2:  // pred: ^bb1
+        %16 = llvm.mlir.constant(false) : i1
+        llvm.br ^bb4(%16 : i1)
+      ^bb3:  // pred: ^bb1
+        %17 = llvm.load %2 : !llvm.ptr<i64>
+        %18 = llvm.mlir.constant(3 : i64) : i64
+        %19 = llvm.icmp \"slt\" %17, %18 : i64
+        llvm.br ^bb4(%19 : i1)
+      ^bb4(%20: i1):  // 2 preds: ^bb2, ^bb3
+        llvm.br ^bb5
+      ^bb5:  // pred: ^bb4
+        llvm.cond_br %20, ^bb6, ^bb14
+      ^bb6:  // pred: ^bb5
+        %21 = llvm.mlir.constant(false) : i1
+        llvm.store %21, %7 : !llvm.ptr<i1>
+        %22 = llvm.mlir.constant(0 : i64) : i64
+        llvm.store %22, %9 : !llvm.ptr<i64>
+        %23 = llvm.mlir.constant(false) : i1
+        llvm.store %23, %11 : !llvm.ptr<i1>
+        llvm.br ^bb7
+      ^bb7:  // 2 preds: ^bb6, ^bb12
+        %24 = llvm.load %11 : !llvm.ptr<i1>
+        %25 = llvm.mlir.constant(true) : i1
+        llvm.cond_br %24, ^bb8, ^bb9
+      ^bb8:  // pred: ^bb7
+        %26 = llvm.mlir.constant(false) : i1
+        llvm.br ^bb10(%26 : i1)
+      ^bb9:  // pred: ^bb7
+        %27 = llvm.load %9 : !llvm.ptr<i64>
+        %28 = llvm.mlir.constant(3 : i64) : i64
+        %29 = llvm.icmp \"slt\" %27, %28 : i64
+        llvm.br ^bb10(%29 : i1)
+      ^bb10(%30: i1):  // 2 preds: ^bb8, ^bb9
+        llvm.br ^bb11
+      ^bb11:  // pred: ^bb10
+        llvm.cond_br %30, ^bb12, ^bb13
+      ^bb12:  // pred: ^bb11
+        %31 = llvm.mlir.constant(false) : i1
+        llvm.store %31, %13 : !llvm.ptr<i1>
+        %32 = llvm.load %9 : !llvm.ptr<i64>
+        %33 = llvm.mlir.constant(1 : i64) : i64
+        %34 = llvm.add %32, %33  : i64
+        llvm.store %34, %9 : !llvm.ptr<i64>
+        llvm.br ^bb7
+      ^bb13:  // pred: ^bb11
+        %35 = llvm.load %2 : !llvm.ptr<i64>
+        %36 = llvm.mlir.constant(1 : i64) : i64
+        %37 = llvm.add %35, %36  : i64
+        llvm.store %37, %2 : !llvm.ptr<i64>
+        llvm.br ^bb1
+      ^bb14:  // pred: ^bb5
+        llvm.return
+      }
+  "}
+}
+
+#[test]
+fn logical_and_unary_operators() {
+    let input = indoc! {"
+        def check(number Int, flag Bool) -> Bool
+          number > 1 && !flag || number == -3
+        end
+
+        def flip(number Int) -> Int
+          ~number
+        end
+    "};
+
+    let parser_result = PajamaCompiler::check(input).unwrap();
+
+    let def = def_at(&parser_result, 0);
+    let Node::Binary(or) = &def.body[0] else {
+        panic!("expected a binary expression");
+    };
+    let Node::Binary(and) = or.left.as_ref() else {
+        panic!("expected a binary expression");
+    };
+    let Node::Binary(equality) = or.right.as_ref() else {
+        panic!("expected a binary expression");
+    };
+
+    assert_eq!(or.op, ['|', '|', '\0', '\0']);
+    assert_eq!(and.op, ['&', '&', '\0', '\0']);
+    assert!(matches!(and.right.as_ref(), Node::Unary(not) if not.op[0] == '!'));
+    assert!(matches!(equality.right.as_ref(), Node::Unary(minus) if minus.op[0] == '-'));
+
+    let diagnostics = match PajamaCompiler::check(indoc! {"
+        def wrong(number Int, flag Bool, ratio Float64)
+          both = number && flag
+          not = !number
+          inverted = ~ratio
+          negated = -flag
+        end
+    "})
+    {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics,
+        result => panic!("expected type errors, got {:?}", result),
+    };
+
+    let errors: Vec<&str> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            "mismatched types: `&&` takes `Bool`s, found `Int`",
+            "mismatched types: `!` takes a `Bool`, found `Int`",
+            "mismatched types: `~` takes an integer, found `Float64`",
+            "mismatched types: `-` takes a number, found `Bool`",
+        ]
+    );
+}
+
+build_test_fn! {
+  short_circuit_operators,
+  indoc! {"
+    def positive(number Int) -> Bool
+      number > 0
+    end
+
+    def check(number Int, flag Bool) -> Bool
+      flag && positive(-number)
+    end
+
+    def flip(flag Bool) -> Bool
+      !flag
+    end
+
+  "},
+  "check(3, false)",
+  indoc! {"
+    ^bb0:
+      llvm.func @positive(%arg0: i64) -> i1 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg0, %1 : !llvm.ptr<i64>
+        %2 = llvm.load %1 : !llvm.ptr<i64>
+        %3 = llvm.mlir.constant(0 : i64) : i64
+        %4 = llvm.icmp \"sgt\" %2, %3 : i64
+        llvm.return %4 : i1
+      }
+      llvm.func @check(%arg0: i64, %arg1: i1) -> i1 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg0, %1 : !llvm.ptr<i64>
+        %2 = llvm.mlir.constant(1 : i64) : i64
+        %3 = llvm.alloca %2 x i1 : (i64) -> !llvm.ptr<i1>
+        llvm.store %arg1, %3 : !llvm.ptr<i1>
+        %4 = llvm.load %3 : !llvm.ptr<i1>
+        llvm.cond_br %4, ^bb1, ^bb2
+      ^bb1:  // pred: ^bb0
+        %5 = llvm.load %1 : !llvm.ptr<i64>
+        %6 = llvm.mlir.constant(0 : i64) : i64
+        %7 = llvm.sub %6, %5  : i64
+        %8 = llvm.call @positive(%7) : (i64) -> i1
+        llvm.br ^bb3(%8 : i1)
+      ^bb2:  // pred: ^bb0
+        %9 = llvm.mlir.constant(false) : i1
+        llvm.br ^bb3(%9 : i1)
+      ^bb3(%10: i1):  // 2 preds: ^bb1, ^bb2
+        llvm.br ^bb4
+      ^bb4:  // pred: ^bb3
+        llvm.return %10 : i1
+      }
+      llvm.func @flip(%arg0: i1) -> i1 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i1 : (i64) -> !llvm.ptr<i1>
+        llvm.store %arg0, %1 : !llvm.ptr<i1>
+        %2 = llvm.load %1 : !llvm.ptr<i1>
+        %3 = llvm.mlir.constant(true) : i1
+        %4 = llvm.xor %2, %3  : i1
+        llvm.return %4 : i1
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(3 : i64) : i64
+        %1 = llvm.mlir.constant(false) : i1
+        %2 = llvm.call @check(%0, %1) : (i64, i1) -> i1
+        llvm.return
+      }
+  "}
+}
+
+#[test]
+fn bitwise_operator_precedence() {
+    let input = indoc! {"
+        def flags(number Int, mask Int) -> Bool
+          number & mask | number ^ mask << 2 == 1
+        end
+
+        def power(number Int) -> Int
+          2 ** 3 ** number * 2
+        end
+    "};
+
+    let parser_result = PajamaCompiler::check(input).unwrap();
+
+    let def = def_at(&parser_result, 0);
+    let Node::Binary(equality) = &def.body[0] else {
+        panic!("expected a binary expression");
+    };
+    let Node::Binary(or) = equality.left.as_ref() else {
+        panic!("expected a binary expression");
+    };
+    let (Node::Binary(and), Node::Binary(xor)) = (or.left.as_ref(), or.right.as_ref()) else {
+        panic!("expected binary expressions");
+    };
+
+    assert_eq!(equality.op, ['=', '=', '\0', '\0']);
+    assert_eq!(or.op, ['|', '\0', '\0', '\0']);
+    assert_eq!(and.op, ['&', '\0', '\0', '\0']);
+    assert_eq!(xor.op, ['^', '\0', '\0', '\0']);
+    assert!(
+        matches!(xor.right.as_ref(), Node::Binary(shift) if shift.op == ['<', '<', '\0', '\0'])
+    );
+
+    let def = def_at(&parser_result, 1);
+    let Node::Binary(product) = &def.body[0] else {
+        panic!("expected a binary expression");
+    };
+    let Node::Binary(power) = product.left.as_ref() else {
+        panic!("expected a binary expression");
+    };
+
+    assert_eq!(power.op, ['*', '*', '\0', '\0']);
+    assert!(matches!(power.left.as_ref(), Node::Int(base) if base.value == 2));
+    assert!(matches!(power.right.as_ref(), Node::Binary(exponent) if exponent.op[1] == '*'));
+}
+
+build_test_fn! {
+  bitwise_operators,
+  indoc! {"
+    def pack(high Int, low Int) -> Int
+      (high << 8 | low & 255) ^ (high >> 2)
+    end
+
+    def root(ratio Float64) -> Float64
+      ratio ** 0.5
+    end
+
+  "},
+  "pack(1, 2)",
+  indoc! {"
+    ^bb0:
+      llvm.func @pack(%arg0: i64, %arg1: i64) -> i64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg0, %1 : !llvm.ptr<i64>
+        %2 = llvm.mlir.constant(1 : i64) : i64
+        %3 = llvm.alloca %2 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg1, %3 : !llvm.ptr<i64>
+        %4 = llvm.load %1 : !llvm.ptr<i64>
+        %5 = llvm.mlir.constant(8 : i64) : i64
+        %6 = llvm.shl %4, %5  : i64
+        %7 = llvm.load %3 : !llvm.ptr<i64>
+        %8 = llvm.mlir.constant(255 : i64) : i64
+        %9 = llvm.and %7, %8  : i64
+        %10 = llvm.or %6, %9  : i64
+        %11 = llvm.load %1 : !llvm.ptr<i64>
+        %12 = llvm.mlir.constant(2 : i64) : i64
+        %13 = llvm.ashr %11, %12  : i64
+        %14 = llvm.xor %10, %13  : i64
+        llvm.return %14 : i64
+      }
+      llvm.func @root(%arg0: f64) -> f64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x f64 : (i64) -> !llvm.ptr<f64>
+        llvm.store %arg0, %1 : !llvm.ptr<f64>
+        %2 = llvm.load %1 : !llvm.ptr<f64>
+        %3 = llvm.mlir.constant(5.000000e-01 : f64) : f64
+        %4 = llvm.intr.pow(%2, %3)  : (f64, f64) -> f64
+        llvm.return %4 : f64
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.mlir.constant(2 : i64) : i64
+        %2 = llvm.call @pack(%0, %1) : (i64, i64) -> i64
+        llvm.return
+      }
+  "}
+}
+
+build_test_fn! {
+  operands_of_different_widths_are_extended,
+  indoc! {"
+    def in_range(byte Byte, count Int32) -> Bool
+      byte < 200 && count + 1 > byte
+    end
+
+  "},
+  "1",
+  indoc! {"
+    ^bb0:
+      llvm.func @in_range(%arg0: i8, %arg1: i32) -> i1 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i8 : (i64) -> !llvm.ptr<i8>
+        llvm.store %arg0, %1 : !llvm.ptr<i8>
+        %2 = llvm.mlir.constant(1 : i64) : i64
+        %3 = llvm.alloca %2 x i32 : (i64) -> !llvm.ptr<i32>
+        llvm.store %arg1, %3 : !llvm.ptr<i32>
+        %4 = llvm.load %1 : !llvm.ptr<i8>
+        %5 = llvm.mlir.constant(200 : i64) : i64
+        %6 = llvm.zext %4 : i8 to i64
+        %7 = llvm.icmp \"slt\" %6, %5 : i64
+        llvm.cond_br %7, ^bb1, ^bb2
+      ^bb1:  // pred: ^bb0
+        %8 = llvm.load %3 : !llvm.ptr<i32>
+        %9 = llvm.mlir.constant(1 : i64) : i64
+        %10 = llvm.sext %8 : i32 to i64
+        %11 = llvm.add %10, %9  : i64
+        %12 = llvm.load %1 : !llvm.ptr<i8>
+        %13 = llvm.zext %12 : i8 to i64
+        %14 = llvm.icmp \"sgt\" %11, %13 : i64
+        llvm.br ^bb3(%14 : i1)
+      ^bb2:  // pred: ^bb0
+        %15 = llvm.mlir.constant(false) : i1
+        llvm.br ^bb3(%15 : i1)
+      ^bb3(%16: i1):  // 2 preds: ^bb1, ^bb2
+        llvm.br ^bb4
+      ^bb4:  // pred: ^bb3
+        llvm.return %16 : i1
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        llvm.return
+      }
+  "}
+}
+
+build_test_fn! {
+  bytes_passed_as_wider_integers_are_zero_extended,
+  indoc! {"
+    def widen(number Int) -> Int
+      number
+    end
+
+    def pass_byte(byte Byte) -> Int
+      widen(byte)
+    end
+
+  "},
+  "1",
+  indoc! {"
+    ^bb0:
+      llvm.func @widen(%arg0: i64) -> i64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg0, %1 : !llvm.ptr<i64>
+        %2 = llvm.load %1 : !llvm.ptr<i64>
+        llvm.return %2 : i64
+      }
+      llvm.func @pass_byte(%arg0: i8) -> i64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i8 : (i64) -> !llvm.ptr<i8>
+        llvm.store %arg0, %1 : !llvm.ptr<i8>
+        %2 = llvm.load %1 : !llvm.ptr<i8>
+        %3 = llvm.zext %2 : i8 to i64
+        %4 = llvm.call @widen(%3) : (i64) -> i64
+        llvm.return %4 : i64
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        llvm.return
+      }
+  "}
+}
+
+#[test]
+fn operands_must_be_numbers() {
+    let diagnostics = match PajamaCompiler::check(indoc! {"
+        def wrong(flag Bool, count Int)
+          sum = \"name\" + 1
+          less = flag < count
+          same = flag == count
+        end
+    "})
+    {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics,
+        result => panic!("expected type errors, got {:?}", result),
+    };
+
+    let errors: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.span.line, diagnostic.message.as_str()))
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            (2, "mismatched types: `+` takes numbers, found `Str`"),
+            (3, "mismatched types: `<` takes numbers, found `Bool`"),
+            (4, "mismatched types: `==` can't mix `Bool` and `Int`"),
+        ]
+    );
+}
+
+#[test]
+fn codegen_reports_unsupported_operators() {
+    let session = Session::new(CompileOptions::default());
+
+    // Analysis would already reject `&` on floats, so it's skipped. The
+    // error also comes out of the operand of another operator.
+    for expression in ["1.5 & 2.5", "(1.5 & 2.5) + 1"] {
+        let tokens = session
+            .tokenize(&format!("def _mlir_ciface_main\n  {}\nend\n", expression))
+            .unwrap();
+        let parser_result = session.parse(tokens).unwrap();
+
+        match session.generate(&parser_result).map(|_| ()) {
+            Err(CompileError::Codegen(message)) => {
+                assert_eq!(message, "bitwise and shift operators only take integers")
+            }
+            result => panic!(
+                "expected a codegen error for `{}`, got {:?}",
+                expression, result
+            ),
+        }
+    }
+}
+
+#[test]
+fn user_defined_operators_are_calls() {
+    let input = indoc! {"
+        def combine(first Int, second Int) -> Int
+          first <> second + 1 <> %%second
+        end
+
+        def binary<> 5 (left Int, right Int) -> Int
+          left * right - right
+        end
+
+        def unary%% (value Int) -> Int
+          value * value
+        end
+    "};
+
+    let parser_result = PajamaCompiler::check(input).unwrap();
+
+    let def = def_at(&parser_result, 0);
+    let Node::Call(outer) = &def.body[0] else {
+        panic!("expected a call");
+    };
+    let [Node::Call(inner), Node::Call(unary)] = outer.args.as_slice() else {
+        panic!("expected calls");
+    };
+
+    assert_eq!(outer.fn_name, "binary<>");
+    assert_eq!(inner.fn_name, "binary<>");
+    assert_eq!(unary.fn_name, "unary%%");
+    assert_eq!(outer.return_type, Some(BaseType::Int));
+    assert!(matches!(&inner.args[1], Node::Binary(sum) if sum.op == ['+', '\0', '\0', '\0']));
+
+    let prototype = &parser_result.index.fn_prototype_index["binary<>"];
+
+    assert!(prototype.is_op);
+    assert_eq!(prototype.prec, 5);
+}
+
+#[test]
+fn operator_definition_errors_are_reported() {
+    let builtin = indoc! {"
+        def binary+ 5 (left Int, right Int) -> Int
+          left
+        end
+    "};
+
+    let err = match PajamaCompiler::parse(builtin) {
+        Err(CompileError::Parse(errors)) => errors[0].clone(),
+        result => panic!("expected a parse error, got {:?}", result),
+    };
+
+    assert_eq!(err.span.line, 1);
+    assert_eq!(
+        err.to_string().lines().next(),
+        Some("expected an operator that isn't built in, found operator `+`")
+    );
+
+    let arity = indoc! {"
+        def binary<> 5 (value Int) -> Int
+          value
+        end
+    "};
+
+    let diagnostics = match PajamaCompiler::check(arity) {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics,
+        result => panic!("expected resolver errors, got {:?}", result),
+    };
+
+    assert_eq!(
+        diagnostics
+            .iter()
+            .next()
+            .map(|diagnostic| diagnostic.message.as_str()),
+        Some("a binary operator takes 2 parameters, `binary<>` has 1")
+    );
+}
+
+build_test_fn! {
+  user_defined_operators,
+  indoc! {"
+    def binary|> 1 (value Int, times Int) -> Int
+      value * times
+    end
+
+  "},
+  "2 + 1 |> 3",
+  indoc! {"
+    ^bb0:
+      llvm.func @\"binary|>\"(%arg0: i64, %arg1: i64) -> i64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg0, %1 : !llvm.ptr<i64>
+        %2 = llvm.mlir.constant(1 : i64) : i64
+        %3 = llvm.alloca %2 x i64 : (i64) -> !llvm.ptr<i64>
+        llvm.store %arg1, %3 : !llvm.ptr<i64>
+        %4 = llvm.load %1 : !llvm.ptr<i64>
+        %5 = llvm.load %3 : !llvm.ptr<i64>
+        %6 = llvm.mul %4, %5  : i64
+        llvm.return %6 : i64
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(2 : i64) : i64
+        %1 = llvm.mlir.constant(1 : i64) : i64
+        %2 = llvm.mlir.constant(3 : i64) : i64
+        %3 = llvm.mlir.constant(3 : i64) : i64
+        %4 = llvm.call @\"binary|>\"(%2, %3) : (i64, i64) -> i64
+        llvm.return
+      }
+  "}
+}
+
+#[test]
+fn resolver_reports_undefined_names() {
+    let input = indoc! {"
+        def make(thing Thing)
+          loop {
+            inner = 1
+          }
+          inner
+          nope()
+          Missing.new()
+        end
+    "};
+
+    let diagnostics = match PajamaCompiler::check(input) {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics,
+        result => panic!("expected resolver errors, got {:?}", result),
+    };
+
+    let errors: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.span.line, diagnostic.message.as_str()))
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            (1, "unknown class `Thing`"),
+            (5, "undefined local variable `inner`"),
+            (6, "undefined function `nope`"),
+            (7, "unknown class or constant `Missing`"),
+        ]
+    );
+}
+
+#[test]
+fn type_errors_are_reported() {
+    let input = indoc! {"
+        class Str
+          @length Int
+        end
+
+        def double(number Int) -> Int
+          number * 2
+        end
+
+        def name() -> Int
+          \"pajama\"
+        end
+
+        def _mlir_ciface_main
+          double(1, 2)
+          double(\"two\")
+        end
+    "};
+
+    let diagnostics = match PajamaCompiler::check(input) {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics,
+        result => panic!("expected type errors, got {:?}", result),
+    };
+
+    let errors: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.span.line, diagnostic.message.as_str()))
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            (10, "mismatched types: `name` returns `Int`, found `Str`"),
+            (14, "`double` takes 1 argument but 2 were given"),
+            (15, "mismatched types: `number` of `double` is `Int`, found `Str`"),
+        ]
+    );
+}
+
+#[test]
+fn lossy_conversions_are_rejected() {
+    let input = indoc! {"
+        def_e exit(code Int32)
+
+        def to_byte(number Int64) -> Byte
+          number
+        end
+
+        def half(number Float64) -> Int
+          number / 2.0
+        end
+
+        def narrow(number Float64) -> Float32
+          number
+        end
+
+        def widen(byte Byte, number Int32) -> Float64
+          ratio = 0.5
+          byte + number * ratio
+        end
+
+        def _mlir_ciface_main
+          exit(255)
+          exit(-1)
+          exit(3000000000)
+          to_byte(-1)
+        end
+    "};
+
+    let diagnostics = match PajamaCompiler::check(input) {
+        Err(CompileError::Semantic(diagnostics)) => diagnostics,
+        result => panic!("expected type errors, got {:?}", result),
+    };
+
+    let errors: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.span.line, diagnostic.message.as_str()))
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            (
+                4,
+                "mismatched types: `to_byte` returns `Byte`, found `Int64`"
+            ),
+            (8, "mismatched types: `half` returns `Int`, found `Float64`"),
+            (
+                12,
+                "mismatched types: `narrow` returns `Float32`, found `Float64`"
+            ),
+            (
+                23,
+                "mismatched types: `code` of `exit` is `Int32`, found `Int`"
+            ),
+        ]
+    );
+}
+
+build_test_fn! {
+  numbers_are_converted_to_the_expected_type,
+  indoc! {"
+    def_e exit(code Int32)
+
+    def widen(number Int16) -> Int64
+      number
+    end
+
+    def to_int(byte Byte) -> Int
+      byte
+    end
+
+    def scale(ratio Float32) -> Float64
+      ratio
+    end
+
+  "},
+  "exit(-1)\nscale(-0.5)",
+  indoc! {"
+    ^bb0:
+      llvm.func @exit(i32) attributes {sym_visibility = \"private\"}
+      llvm.func @widen(%arg0: i16) -> i64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i16 : (i64) -> !llvm.ptr<i16>
+        llvm.store %arg0, %1 : !llvm.ptr<i16>
+        %2 = llvm.load %1 : !llvm.ptr<i16>
+        %3 = llvm.sext %2 : i16 to i64
+        llvm.return %3 : i64
+      }
+      llvm.func @to_int(%arg0: i8) -> i64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x i8 : (i64) -> !llvm.ptr<i8>
+        llvm.store %arg0, %1 : !llvm.ptr<i8>
+        %2 = llvm.load %1 : !llvm.ptr<i8>
+        %3 = llvm.zext %2 : i8 to i64
+        llvm.return %3 : i64
+      }
+      llvm.func @scale(%arg0: f32) -> f64 {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.alloca %0 x f32 : (i64) -> !llvm.ptr<f32>
+        llvm.store %arg0, %1 : !llvm.ptr<f32>
+        %2 = llvm.load %1 : !llvm.ptr<f32>
+        %3 = llvm.fpext %2 : f32 to f64
+        llvm.return %3 : f64
+      }
+      llvm.func @_mlir_ciface_main() {
+        %0 = llvm.mlir.constant(1 : i64) : i64
+        %1 = llvm.mlir.constant(0 : i64) : i64
+        %2 = llvm.mlir.constant(-1 : i64) : i64
+        %3 = llvm.trunc %2 : i64 to i32
+        llvm.call @exit(%3) : (i32) -> ()
+        %4 = llvm.mlir.constant(5.000000e-01 : f64) : f64
+        %5 = llvm.mlir.constant(-5.000000e-01 : f64) : f64
+        %6 = llvm.fptrunc %5 : f64 to f32
+        %7 = llvm.call @scale(%6) : (f32) -> f64
+        llvm.return
+      }
+  "}
+}
+
+#[test]
+fn dump_selected_phases() {
+    let dump_path = std::env::temp_dir().join("pajama_dump_selected_phases.txt");
+    let options = CompileOptions {
+        dump: DumpOptions {
+            phases: vec![DumpPhase::Tokens, DumpPhase::Mlir],
+            sink: DumpSink::File(dump_path.clone()),
+        },
+        ..CompileOptions::default()
+    };
+
+    PajamaCompiler::compile_to_mlir("def _mlir_ciface_main\n  1\nend\n", true, &options).unwrap();
+
+    let dump = std::fs::read_to_string(&dump_path).unwrap();
+    std::fs::remove_file(&dump_path).unwrap();
+
+    assert!(dump.contains("// ----- tokens -----"));
+    assert!(dump.contains("// ----- mlir -----"));
+    assert!(!dump.contains("// ----- ast -----"));
+    assert!(!dump.contains("// ----- llvm-mlir -----"));
+}
+
+#[test]
+fn mlir_dump_has_source_locations() {
+    let dump_path = std::env::temp_dir().join("pajama_mlir_dump_has_source_locations.txt");
+    let (source_map, input) = SourceMap::concat(vec![(
+        "main.pjs".to_string(),
+        "def _mlir_ciface_main\n  1\nend".to_string(),
+    )]);
+    let options = CompileOptions {
+        dump: DumpOptions {
+            phases: vec![DumpPhase::Mlir],
+            sink: DumpSink::File(dump_path.clone()),
+        },
+        source_map,
+        ..CompileOptions::default()
+    };
+
+    PajamaCompiler::compile_to_mlir(&input, false, &options).unwrap();
+
+    let dump = std::fs::read_to_string(&dump_path).unwrap();
+    std::fs::remove_file(&dump_path).unwrap();
+
+    // The function and the constant in its body
+    assert!(dump.contains("\"main.pjs\":1:1"));
+    assert!(dump.contains("\"main.pjs\":2:3"));
+}
+
+#[test]
+fn debug_info_describes_functions_and_locals() {
+    let dump_path = std::env::temp_dir().join("pajama_debug_info_describes_functions.txt");
+    let input = indoc! {"
+        def add(a Int, b Int) -> Int
+          sum = a + b
+          sum
+        end
+
+        def _mlir_ciface_main
+          add(1, 2)
+        end
+    "};
+    let options = CompileOptions {
+        dump: DumpOptions {
+            phases: vec![DumpPhase::LlvmMlir],
+            sink: DumpSink::File(dump_path.clone()),
+        },
+        debug_info: true,
+        ..CompileOptions::default()
+    };
+
+    PajamaCompiler::compile_to_mlir(input, true, &options).unwrap();
+
+    let dump = std::fs::read_to_string(&dump_path).unwrap();
+    std::fs::remove_file(&dump_path).unwrap();
+
+    assert!(dump.contains("#llvm.di_subprogram<"));
+    assert!(dump.contains("name = \"add\""));
+    assert!(dump.contains("llvm.intr.dbg.declare"));
+    assert!(dump.contains("name = \"sum\""));
+    assert!(dump.contains("arg = 2"));
+}
+
+//
+// Exit status
+//
+
+#[test]
+fn main_returns_status() {
+    let input = indoc! {"
+        def _mlir_ciface_main -> Int32
+          3
+        end
+    "};
+
+    let compiler_output = PajamaCompiler::compile_to_string(input).unwrap();
+    let expected_output = indoc! {"
+        ^bb0:
+          llvm.func @_mlir_ciface_main() -> i32 {
+            %0 = llvm.mlir.constant(3 : i64) : i64
+            %1 = llvm.mlir.constant(3 : i32) : i32
+            llvm.return %1 : i32
+          }
+    "};
+
+    assert_eq!(compiler_output, expected_output);
+}
+
+#[test]
+fn jit_runs_main() {
+    let run = |input| PajamaCompiler::compile_and_invoke(input, &CompileOptions::default(), &[]);
+
+    assert_eq!(run("def main\n  3\nend\n").unwrap(), 0);
+    assert_eq!(run("def main -> Int32\n  3\nend\n").unwrap(), 3);
+}
+
+#[test]
+fn main_takes_args() {
+    let input = indoc! {"
+        def_e pj_str_array_get(args StrArray, index Int) -> Str
+        def_e env_get(name Str) -> Str
+
+        class Str
+          @buffer     BytePtr
+          @length     Int
+          @max_length Int
+        end
+
+        class StrArray
+          @buffer BytePtr
+          @length Int
+        end
+
+        def main(args StrArray) -> Int32
+          env_get(pj_str_array_get(args, 1))
+          3
+        end
+    "};
+
+    let compiler_output = PajamaCompiler::compile_to_string(input).unwrap();
+    let expected_output = indoc! {"
+        ^bb0:
+          llvm.func @pj_str_array_get(!llvm.ptr<struct<(ptr<i8>, i64)>>, i64) -> !llvm.ptr<struct<(ptr<i8>, i64, i64)>> attributes {sym_visibility = \"private\"}
+          llvm.func @env_get(!llvm.ptr<struct<(ptr<i8>, i64, i64)>>) -> !llvm.ptr<struct<(ptr<i8>, i64, i64)>> attributes {sym_visibility = \"private\"}
+          llvm.func @pj_main(%arg0: !llvm.ptr<struct<(ptr<i8>, i64)>>) -> i32 {
+            %0 = llvm.mlir.constant(1 : i64) : i64
+            %1 = llvm.call @pj_str_array_get(%arg0, %0) : (!llvm.ptr<struct<(ptr<i8>, i64)>>, i64) -> !llvm.ptr<struct<(ptr<i8>, i64, i64)>>
+            %2 = llvm.call @env_get(%1) : (!llvm.ptr<struct<(ptr<i8>, i64, i64)>>) -> !llvm.ptr<struct<(ptr<i8>, i64, i64)>>
+            %3 = llvm.mlir.constant(3 : i64) : i64
+            %4 = llvm.mlir.constant(3 : i32) : i32
+            llvm.return %4 : i32
+          }
+          llvm.func @pj_main_args() -> !llvm.ptr<struct<(ptr<i8>, i64)>> attributes {sym_visibility = \"private\"}
+          llvm.func @main() -> i32 {
+            %0 = llvm.call @pj_main_args() : () -> !llvm.ptr<struct<(ptr<i8>, i64)>>
+            %1 = llvm.call @pj_main(%0) : (!llvm.ptr<struct<(ptr<i8>, i64)>>) -> i32
+            llvm.return %1 : i32
+          }
+          llvm.func @_mlir_ciface_pj_jit_main() -> i32 {
+            %0 = llvm.call @main() : () -> i32
+            llvm.return %0 : i32
+          }
+    "};
+
+    assert_eq!(compiler_output, expected_output);
+}
+
+#[test]
+fn jit_passes_args_to_main() {
+    let input = indoc! {"
+        def_e pj_str_array_get(args StrArray, index Int) -> Str
+
+        class Str
+          @buffer     BytePtr
+          @length     Int
+          @max_length Int
+        end
+
+        class StrArray
+          @buffer BytePtr
+          @length Int
+        end
+
+        def ENTRY_POINT(args StrArray) -> Int32
+          arg = pj_str_array_get(args, 1)
+
+          if arg.length == 4
+            4
+          else
+            0
+          end
+        end
+    "};
+
+    for entry_point in ["main", "_mlir_ciface_main"] {
+        let input = input.replace("ENTRY_POINT", entry_point);
+        let args = ["program".to_string(), "four".to_string()];
+
+        let status =
+            PajamaCompiler::compile_and_invoke(&input, &CompileOptions::default(), &args).unwrap();
+
+        assert_eq!(status, 4, "entered at `{}`", entry_point);
+
+        // An index out of bounds gives an empty `Str`
+        let status =
+            PajamaCompiler::compile_and_invoke(&input, &CompileOptions::default(), &args[..1])
+                .unwrap();
+
+        assert_eq!(status, 0, "entered at `{}`", entry_point);
+    }
+}
+
+#[test]
+fn repl_runs_expressions() {
+    let mut repl = Repl::new(CompileOptions::default());
+
+    assert_eq!(repl.eval("1 + 4\n").unwrap(), Some("5".to_string()));
+    assert_eq!(repl.eval("2.5 * 2.0\n").unwrap(), Some("5".to_string()));
+}
+
+#[test]
+fn repl_keeps_definitions() {
+    let mut repl = Repl::new(CompileOptions::default());
+
+    assert!(!repl.is_complete("def two -> Int\n"));
+    assert!(repl.is_complete("def two -> Int\n  2\nend\n"));
+
+    assert_eq!(repl.eval("def two -> Int\n  2\nend\n").unwrap(), None);
+    assert_eq!(repl.eval("two() + 3\n").unwrap(), Some("5".to_string()));
+    assert_eq!(repl.eval("two()\n").unwrap(), Some("2".to_string()));
+}
+
+#[test]
+fn repl_warns_about_each_input_once() {
+    let mut repl = Repl::new(CompileOptions::default());
+
+    // Whether or not codegen accepts the unreachable code, analysis ran first
+    let _ = repl.eval("def one -> Int\n  ret 1\n  2\nend\n");
+
+    let warnings = repl.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].message, "unreachable code after `ret`");
+
+    let _ = repl.eval("1 + 1\n");
+    assert!(repl.take_warnings().is_empty());
+}
+
+#[test]
+fn repl_waits_for_if_and_loop_blocks() {
+    let repl = Repl::new(CompileOptions::default());
+
+    assert!(!repl.is_complete("if true\n  1\n"));
+    assert!(repl.is_complete("if true\n  1\nelse\n  2\nend\n"));
+    assert!(!repl.is_complete("number = if true\n"));
+
+    assert!(!repl.is_complete("loop {\n  break\n"));
+    assert!(repl.is_complete("loop {\n  break\n}\n"));
+    assert!(!repl.is_complete("while true\n  break\n"));
+    assert!(repl.is_complete("while true\n  break\nend\n"));
+
+    // A postfix `if` has no `end`
+    assert!(repl.is_complete("number = 1 if true\n"));
+    assert!(!repl.is_complete("def clamp(number Int) -> Int\n  number = 10 if number > 10\n"));
+    assert!(repl.is_complete("def clamp(number Int) -> Int\n  number = 10 if number > 10\nend\n"));
+}
+
+#[test]
+fn parse_error_points_at_token() {
+    let input = indoc! {"
+        # A class
+        class foo
+        end
+    "};
+
+    let err = match PajamaCompiler::parse(input) {
+        Err(CompileError::Parse(errors)) => errors[0].clone(),
+        result => panic!("expected a parse error, got {:?}", result),
+    };
+
+    assert_eq!(err.span.line, 2);
+    assert_eq!(err.span.start_column, 7);
+    assert_eq!(
+        err.to_string(),
+        indoc! {"
+            expected a class name, found identifier `foo`
+             --> 2:7
+              |
+            2 | class foo
+              |       ^^^"}
+    );
+}
+
+#[test]
+fn parse_reports_every_error() {
+    let input = indoc! {"
+        def first(a Int
+        end
+
+        def second
+          (1
+        end
+
+        def third
+          3
+        end
+    "};
+
+    let session = Session::new(CompileOptions::default());
+    let token_stream = session.tokenize(input).unwrap();
+    let (parser_result, errors) = session.parse_recovering(token_stream).unwrap();
+
+    let lines: Vec<usize> = errors.iter().map(|err| err.span.line).collect();
+    assert_eq!(lines, vec![2, 6]);
+
+    assert!(parser_result.index.fn_prototype_index.contains_key("third"));
+}
+
+#[test]
+fn malformed_constants_and_attributes_are_parse_errors() {
+    let input = indoc! {"
+        LIMIT Int = nope
+
+        class Point
+          @x [4 y Byte]
+        end
+
+        def third
+          3
+        end
+    "};
+
+    let session = Session::new(CompileOptions::default());
+    let token_stream = session.tokenize(input).unwrap();
+    let (parser_result, errors) = session.parse_recovering(token_stream).unwrap();
+
+    let errors: Vec<(usize, String)> = errors
+        .iter()
+        .map(|err| {
+            (
+                err.span.line,
+                err.to_string().lines().next().unwrap().to_string(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            (1, "expected a number, found identifier `nope`".to_string()),
+            (
+                4,
+                "expected `x`, as in `[4 x Byte]`, found identifier `y`".to_string()
+            ),
+        ]
+    );
+    assert!(parser_result.index.fn_prototype_index.contains_key("third"));
+}
+
+#[test]
+fn unsupported_constant_initializers_are_codegen_errors() {
+    let input = indoc! {"
+        RATIO Float64 = 1.5
+
+        def _mlir_ciface_main
+          1
+        end
+    "};
+
+    match PajamaCompiler::compile_to_mlir(input, false, &CompileOptions::default()) {
+        Err(CompileError::Codegen(message)) => {
+            assert_eq!(message, "constants can only be initialized with an integer")
+        }
+        result => panic!("expected a codegen error, got {:?}", result),
+    }
+}
+
+#[test]
+fn nodes_carry_spans() {
+    let (source_map, input) = SourceMap::concat(vec![
+        ("one.pj".to_string(), "def one() -> Int\n  1\nend".to_string()),
+        ("two.pj".to_string(), "def two() -> Int\n  one() + 1\nend".to_string()),
+    ]);
+
+    let session = Session::new(CompileOptions {
+        source_map,
+        ..CompileOptions::default()
+    });
+    let parser_result = session.check(&input).unwrap();
+
+    let def = def_at(&parser_result, 1);
+
+    let span = |line, start_column, end_column| Span {
+        file: FileId(1),
+        line,
+        start_column,
+        end_column,
+    };
+
+    assert_eq!(def.span, span(1, 1, 3));
+    assert_eq!(def.prototype.span, span(1, 5, 7));
+
+    let Node::Binary(binary) = &def.body[0] else {
+        panic!("expected a binary expression");
+    };
+
+    assert_eq!(binary.span, span(2, 9, 9));
+    assert_eq!(binary.left.span(), span(2, 3, 5));
+    assert_eq!(binary.right.span(), span(2, 11, 11));
+}